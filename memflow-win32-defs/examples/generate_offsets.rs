@@ -108,12 +108,14 @@ pub fn main() {
                     nt_build_number: win_id.0.build_number(),
 
                     arch: win_id.1,
+
+                    schema_version: WIN32_OFFSET_SCHEMA_VERSION,
                 },
 
                 offsets: offsets.0,
             };
 
-            let offsetstr = toml::to_string_pretty(&offset_file).unwrap();
+            let offsetstr = offset_file.to_toml().unwrap();
 
             let file_name = format!(
                 "{}_{}_{}_{}_{}.toml",
@@ -2,7 +2,7 @@ use std::cmp::{Ord, Ordering, PartialEq};
 use std::fmt;
 
 #[derive(Debug, Clone, Default)]
-#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub struct Win32Guid {
     pub file_name: String,
     pub guid: String,
@@ -18,7 +18,7 @@ impl Win32Guid {
 }
 
 #[derive(Debug, Clone, Copy, Default)]
-#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 #[repr(C)]
 pub struct Win32Version {
     nt_major_version: u32,
@@ -63,38 +63,111 @@ impl Win32Version {
             self.build_number(),
         )
     }
+
+    /// Returns true if this version is a Windows 11 (or Windows Server 2025) kernel.
+    ///
+    /// Windows 11 still reports itself as NT 10.0, so only the build number is checked.
+    pub fn is_windows_11(&self) -> bool {
+        self.build_number() >= 22000
+    }
+
+    /// Returns the name of the Windows Server release that shipped with this build (e.g. `Windows Server 2022`).
+    ///
+    /// Server editions share most of their build numbers with client releases,
+    /// so this does not imply that the target actually runs a server edition.
+    /// Returns `None` if no server release was built from this build number.
+    pub fn server_release_name(&self) -> Option<&'static str> {
+        let build_number = self.build_number();
+        SERVER_RELEASES
+            .iter()
+            .find(|(b, _)| *b == build_number)
+            .map(|(_, name)| *name)
+    }
+
+    /// Returns the marketing name of this Windows version (e.g. `Windows 11 23H2`).
+    ///
+    /// Builds that are shared between client and server editions map to the client name.
+    /// Unreleased builds map to the closest preceding release.
+    /// If the build number is unknown `Unknown` is returned.
+    pub fn marketing_name(&self) -> &'static str {
+        let build_number = self.build_number();
+
+        // server-only builds are not part of the client releases
+        if let Some(name) = self.server_release_name() {
+            if !RELEASES.iter().any(|(b, _)| *b == build_number) {
+                return name;
+            }
+        }
+
+        RELEASES
+            .iter()
+            .rev()
+            .find(|(b, _)| *b <= build_number)
+            .map(|(_, name)| *name)
+            .unwrap_or("Unknown")
+    }
 }
 
+/// Build numbers of all major releases, sorted by build number.
+const RELEASES: &[(u32, &str)] = &[
+    (511, "Windows NT 3.1"),
+    (528, "Windows NT 3.5"),
+    (807, "Windows NT 3.51"),
+    (1381, "Windows NT 4.0"),
+    (2195, "Windows 2000"),
+    (2600, "Windows XP"),
+    (3790, "Windows Server 2003"),
+    (6000, "Windows Vista"),
+    (7600, "Windows 7"),
+    (9200, "Windows 8"),
+    (9600, "Windows 8.1"),
+    (10240, "Windows 10 1507"),
+    (10586, "Windows 10 1511"),
+    (14393, "Windows 10 1607"),
+    (15063, "Windows 10 1703"),
+    (16299, "Windows 10 1709"),
+    (17134, "Windows 10 1803"),
+    (17763, "Windows 10 1809"),
+    (18362, "Windows 10 1903"),
+    (18363, "Windows 10 1909"),
+    (19041, "Windows 10 2004"),
+    (19042, "Windows 10 20H2"),
+    (19043, "Windows 10 21H1"),
+    (19044, "Windows 10 21H2"),
+    (19045, "Windows 10 22H2"),
+    (22000, "Windows 11 21H2"),
+    (22621, "Windows 11 22H2"),
+    (22631, "Windows 11 23H2"),
+    (26100, "Windows 11 24H2"),
+];
+
+/// Build numbers of all Windows Server releases based on the NT 10.0 kernel, sorted by build number.
+const SERVER_RELEASES: &[(u32, &str)] = &[
+    (14393, "Windows Server 2016"),
+    (17763, "Windows Server 2019"),
+    (20348, "Windows Server 2022"),
+    (26100, "Windows Server 2025"),
+];
+
 impl PartialOrd for Win32Version {
     fn partial_cmp(&self, other: &Win32Version) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
+/// Versions are ordered by major version, minor version and (masked) build number.
+///
+/// An unknown build number (0) orders before all known build numbers of the same major and minor version.
+/// This keeps `eq` and `cmp` consistent so `Win32Version` can safely be used as a key in ordered collections.
 impl Ord for Win32Version {
     fn cmp(&self, other: &Win32Version) -> Ordering {
-        if self.nt_build_number != 0 && other.nt_build_number != 0 {
-            return self.nt_build_number.cmp(&other.nt_build_number);
-        }
-
-        if self.nt_major_version != other.nt_major_version {
-            self.nt_major_version.cmp(&other.nt_major_version)
-        } else if self.nt_minor_version != other.nt_minor_version {
-            self.nt_minor_version.cmp(&other.nt_minor_version)
-        } else {
-            Ordering::Equal
-        }
+        self.as_tuple().cmp(&other.as_tuple())
     }
 }
 
 impl PartialEq for Win32Version {
     fn eq(&self, other: &Win32Version) -> bool {
-        if self.nt_build_number != 0 && other.nt_build_number != 0 {
-            self.nt_build_number.eq(&other.nt_build_number)
-        } else {
-            self.nt_major_version == other.nt_major_version
-                && self.nt_minor_version == other.nt_minor_version
-        }
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -137,3 +210,131 @@ impl fmt::Display for Win32Version {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marketing_name() {
+        let cases = [
+            ((0, 0, 0), "Unknown"),
+            ((3, 10, 511), "Windows NT 3.1"),
+            ((4, 0, 1381), "Windows NT 4.0"),
+            ((5, 1, 2600), "Windows XP"),
+            ((5, 2, 3790), "Windows Server 2003"),
+            ((6, 1, 7601), "Windows 7"),
+            ((6, 3, 9600), "Windows 8.1"),
+            ((10, 0, 10240), "Windows 10 1507"),
+            ((10, 0, 18362), "Windows 10 1903"),
+            ((10, 0, 19045), "Windows 10 22H2"),
+            ((10, 0, 20348), "Windows Server 2022"),
+            ((10, 0, 22000), "Windows 11 21H2"),
+            ((10, 0, 22621), "Windows 11 22H2"),
+            ((10, 0, 22631), "Windows 11 23H2"),
+            ((10, 0, 26100), "Windows 11 24H2"),
+            ((10, 0, 26200), "Windows 11 24H2"),
+            ((0, 0, 19041), "Windows 10 2004"),
+        ];
+
+        for (version, name) in cases {
+            assert_eq!(Win32Version::from(version).marketing_name(), name);
+        }
+    }
+
+    #[test]
+    fn ordering_mixed_build_numbers() {
+        let unknown = Win32Version::from((10, 0));
+        let win10 = Win32Version::from((10, 0, 19041));
+        let win11 = Win32Version::from((10, 0, 22621));
+
+        assert_ne!(unknown, win10);
+        assert_ne!(unknown, win11);
+        assert!(unknown < win10);
+        assert!(unknown < win11);
+        assert!(win10 < win11);
+
+        // version checks against a bare major.minor version
+        assert!(win10 >= (10, 0).into());
+        assert!(win10 >= (6, 2).into());
+        assert!(Win32Version::from((6, 1, 7601)) < (6, 2).into());
+        assert!(Win32Version::from((6, 2)) > (6, 1, 7601).into());
+
+        // checked builds compare by their masked build number
+        assert_eq!(Win32Version::from((10, 0, 0xC000_4A61)), win10);
+
+        // eq and cmp must agree for all combinations
+        let versions = [
+            unknown,
+            win10,
+            win11,
+            Win32Version::from((0, 0, 19041)),
+            Win32Version::from((6, 1)),
+            Win32Version::from((6, 1, 7601)),
+        ];
+        for a in versions.iter() {
+            for b in versions.iter() {
+                assert_eq!(a == b, a.cmp(b) == Ordering::Equal);
+                assert_eq!(a.cmp(b), b.cmp(a).reverse());
+            }
+        }
+
+        let map = versions
+            .iter()
+            .map(|v| (*v, v.build_number()))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        assert_eq!(map.len(), versions.len());
+    }
+
+    #[test]
+    fn server_release_name() {
+        let cases = [
+            ((6, 3, 9600), None),
+            ((10, 0, 14393), Some("Windows Server 2016")),
+            ((10, 0, 17763), Some("Windows Server 2019")),
+            ((10, 0, 19045), None),
+            ((10, 0, 20348), Some("Windows Server 2022")),
+            ((10, 0, 22621), None),
+            ((10, 0, 26100), Some("Windows Server 2025")),
+        ];
+
+        for (version, expected) in cases {
+            assert_eq!(Win32Version::from(version).server_release_name(), expected);
+        }
+
+        // server 2022 must not be treated like a windows 11 build
+        assert!(!Win32Version::from((10, 0, 20348)).is_windows_11());
+    }
+
+    #[test]
+    fn is_windows_11() {
+        let cases = [
+            ((6, 1, 7601), false),
+            ((10, 0, 19045), false),
+            ((10, 0, 20348), false),
+            ((10, 0, 22000), true),
+            ((10, 0, 22621), true),
+            ((10, 0, 26100), true),
+        ];
+
+        for (version, expected) in cases {
+            assert_eq!(Win32Version::from(version).is_windows_11(), expected);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let version = Win32Version::from((10, 0, 0xC000_4A61));
+        let json = serde_json::to_string(&version).unwrap();
+        let parsed: Win32Version = serde_json::from_str(&json).unwrap();
+        // `eq` masks the checked build flag, so the raw fields are compared
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", version));
+
+        let guid = Win32Guid::new("ntkrnlmp.pdb", "3844DBB920174967BE7AA4A2C20430FA1");
+        let json = serde_json::to_string(&guid).unwrap();
+        let parsed: Win32Guid = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.file_name, guid.file_name);
+        assert_eq!(parsed.guid, guid.guid);
+    }
+}
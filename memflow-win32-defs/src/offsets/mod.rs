@@ -7,9 +7,10 @@ pub mod pdb;
 pub mod symstore;
 
 pub mod offset_table;
+pub use offset_table::WIN32_OFFSET_SCHEMA_VERSION;
 #[doc(hidden)]
 pub use offset_table::{
-    MmVadOffsetTable, Win32OffsetFile, Win32OffsetHeader, Win32OffsetTable,
+    MmVadOffsetTable, Win32OffsetFile, Win32OffsetHeader, Win32OffsetSecondary, Win32OffsetTable,
     Win32OffsetsArchitecture,
 };
 
@@ -33,53 +34,97 @@ use std::{fs::File, io::Read, path::Path};
 
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
-#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub struct Win32ArchOffsets {
-    pub peb_ldr: usize,             // _PEB::Ldr
-    pub peb_process_params: usize,  // _PEB::ProcessParameters
-    pub ldr_list: usize,            // _PEB_LDR_DATA::InLoadOrderModuleList
-    pub ldr_data_base: usize,       // _LDR_DATA_TABLE_ENTRY::DllBase
-    pub ldr_data_size: usize,       // _LDR_DATA_TABLE_ENTRY::SizeOfImage
-    pub ldr_data_full_name: usize,  // _LDR_DATA_TABLE_ENTRY::FullDllName
-    pub ldr_data_base_name: usize,  // _LDR_DATA_TABLE_ENTRY::BaseDllName
-    pub ppm_image_path_name: usize, // _RTL_USER_PROCESS_PARAMETERS::ImagePathName
-    pub ppm_command_line: usize,    // _RTL_USER_PROCESS_PARAMETERS::CommandLine
+    pub peb_ldr: usize,                  // _PEB::Ldr
+    pub peb_process_params: usize,       // _PEB::ProcessParameters
+    pub peb_being_debugged: usize,       // _PEB::BeingDebugged
+    pub peb_nt_global_flag: usize,       // _PEB::NtGlobalFlag
+    pub ldr_list: usize,                 // _PEB_LDR_DATA::InLoadOrderModuleList
+    pub ldr_list_memory_order: usize,    // _PEB_LDR_DATA::InMemoryOrderModuleList
+    pub ldr_list_init_order: usize,      // _PEB_LDR_DATA::InInitializationOrderModuleList
+    pub ldr_data_base: usize,            // _LDR_DATA_TABLE_ENTRY::DllBase
+    pub ldr_data_size: usize,            // _LDR_DATA_TABLE_ENTRY::SizeOfImage
+    pub ldr_data_full_name: usize,       // _LDR_DATA_TABLE_ENTRY::FullDllName
+    pub ldr_data_base_name: usize,       // _LDR_DATA_TABLE_ENTRY::BaseDllName
+    pub ldr_data_time_date_stamp: usize, // _LDR_DATA_TABLE_ENTRY::TimeDateStamp
+    pub ppm_current_directory: usize,    // _RTL_USER_PROCESS_PARAMETERS::CurrentDirectory.DosPath
+    pub ppm_dll_path: usize,             // _RTL_USER_PROCESS_PARAMETERS::DllPath
+    pub ppm_image_path_name: usize,      // _RTL_USER_PROCESS_PARAMETERS::ImagePathName
+    pub ppm_command_line: usize,         // _RTL_USER_PROCESS_PARAMETERS::CommandLine
+    pub ppm_environment: usize,          // _RTL_USER_PROCESS_PARAMETERS::Environment
+    pub ppm_environment_size: usize,     // _RTL_USER_PROCESS_PARAMETERS::EnvironmentSize
+    pub teb_stack_base: usize,           // _NT_TIB::StackBase
+    pub teb_stack_limit: usize,          // _NT_TIB::StackLimit
 }
 
 pub const X86: Win32ArchOffsets = Win32ArchOffsets {
     peb_ldr: 0xc,
     peb_process_params: 0x10,
+    peb_being_debugged: 0x2,
+    peb_nt_global_flag: 0x68,
     ldr_list: 0xc,
+    ldr_list_memory_order: 0x14,
+    ldr_list_init_order: 0x1c,
     ldr_data_base: 0x18,
     ldr_data_size: 0x20,
     ldr_data_full_name: 0x24,
     ldr_data_base_name: 0x2c,
+    ldr_data_time_date_stamp: 0x44,
+    ppm_current_directory: 0x24,
+    ppm_dll_path: 0x30,
     ppm_image_path_name: 0x38,
     ppm_command_line: 0x40,
+    ppm_environment: 0x48,
+    ppm_environment_size: 0x290,
+    teb_stack_base: 0x4,
+    teb_stack_limit: 0x8,
 };
 
 pub const X64: Win32ArchOffsets = Win32ArchOffsets {
     peb_ldr: 0x18,
     peb_process_params: 0x20,
+    peb_being_debugged: 0x2,
+    peb_nt_global_flag: 0xbc,
     ldr_list: 0x10,
+    ldr_list_memory_order: 0x20,
+    ldr_list_init_order: 0x30,
     ldr_data_base: 0x30,
     ldr_data_size: 0x40,
     ldr_data_full_name: 0x48,
     ldr_data_base_name: 0x58,
+    ldr_data_time_date_stamp: 0x80,
+    ppm_current_directory: 0x38,
+    ppm_dll_path: 0x50,
     ppm_image_path_name: 0x60,
     ppm_command_line: 0x70,
+    ppm_environment: 0x80,
+    ppm_environment_size: 0x3f0,
+    teb_stack_base: 0x8,
+    teb_stack_limit: 0x10,
 };
 
 pub const AARCH64: Win32ArchOffsets = Win32ArchOffsets {
     peb_ldr: 0x18,
     peb_process_params: 0x20,
+    peb_being_debugged: 0x2,
+    peb_nt_global_flag: 0xbc,
     ldr_list: 0x10,
+    ldr_list_memory_order: 0x20,
+    ldr_list_init_order: 0x30,
     ldr_data_base: 0x30,
     ldr_data_size: 0x40,
     ldr_data_full_name: 0x48,
     ldr_data_base_name: 0x58,
+    ldr_data_time_date_stamp: 0x80,
+    ppm_current_directory: 0x38,
+    ppm_dll_path: 0x50,
     ppm_image_path_name: 0x60,
     ppm_command_line: 0x70,
+    ppm_environment: 0x80,
+    ppm_environment_size: 0x3f0,
+    teb_stack_base: 0x8,
+    teb_stack_limit: 0x10,
 };
 
 impl Win32OffsetsArchitecture {
@@ -178,6 +223,40 @@ impl Win32Offsets {
             .copied()
             .unwrap_or(0);
 
+        let ob_type_index_table = symbols
+            .find_symbol("ObTypeIndexTable")
+            .or_else(|| symbols.find_symbol("_ObTypeIndexTable"))
+            .copied()
+            .unwrap_or(0);
+
+        let obp_root_directory_object = symbols
+            .find_symbol("ObpRootDirectoryObject")
+            .or_else(|| symbols.find_symbol("_ObpRootDirectoryObject"))
+            .copied()
+            .unwrap_or(0);
+        let ob_header_cookie = symbols
+            .find_symbol("ObHeaderCookie")
+            .or_else(|| symbols.find_symbol("_ObHeaderCookie"))
+            .copied()
+            .unwrap_or(0);
+
+        let ke_service_descriptor_table = symbols
+            .find_symbol("KeServiceDescriptorTable")
+            .or_else(|| symbols.find_symbol("_KeServiceDescriptorTable"))
+            .copied()
+            .unwrap_or(0);
+
+        let pool_big_page_table = symbols
+            .find_symbol("PoolBigPageTable")
+            .or_else(|| symbols.find_symbol("_PoolBigPageTable"))
+            .copied()
+            .unwrap_or(0);
+        let pool_big_page_table_size = symbols
+            .find_symbol("PoolBigPageTableSize")
+            .or_else(|| symbols.find_symbol("_PoolBigPageTableSize"))
+            .copied()
+            .unwrap_or(0);
+
         let list_blink = list
             .find_field("Blink")
             .ok_or_else(|| {
@@ -201,6 +280,15 @@ impl Win32Offsets {
                     .log_warn("_KPROCESS::DirectoryTableBase not found")
             })?
             .offset as _;
+        // _KPROCESS::DeepFreeze is part of the ProcessFlags bitfield,
+        // the flags are only used if the DeepFreeze bit exists
+        let (kproc_flags, kproc_deep_freeze_bit) = match (
+            kproc.find_field("ProcessFlags"),
+            kproc.find_field("DeepFreeze"),
+        ) {
+            (Some(flags), Some(deep_freeze)) => (flags.offset as _, deep_freeze.bit_offset as _),
+            _ => (0, 0),
+        };
         let eproc_pid = eproc
             .find_field("UniqueProcessId")
             .ok_or_else(|| {
@@ -287,6 +375,251 @@ impl Win32Offsets {
             0
         };
 
+        // kernel modules
+        let kldr_time_date_stamp = PdbStruct::new(pdb_slice, "_KLDR_DATA_TABLE_ENTRY")
+            .ok()
+            .and_then(|kldr| kldr.find_field("TimeDateStamp").map(|f| f.offset))
+            .unwrap_or(0) as _;
+
+        // object types
+        let object_type_name = PdbStruct::new(pdb_slice, "_OBJECT_TYPE")
+            .ok()
+            .and_then(|object_type| object_type.find_field("Name").map(|f| f.offset))
+            .unwrap_or(0) as _;
+
+        // object headers
+        let object_header = PdbStruct::new(pdb_slice, "_OBJECT_HEADER").ok();
+        let object_header_field = |name| {
+            object_header
+                .as_ref()
+                .and_then(|object_header| object_header.find_field(name).map(|f| f.offset))
+                .unwrap_or(0) as _
+        };
+        let object_header_body = object_header_field("Body");
+        let object_header_type_index = object_header_field("TypeIndex");
+        let object_header_info_mask = object_header_field("InfoMask");
+        let object_header_name_info_name = PdbStruct::new(pdb_slice, "_OBJECT_HEADER_NAME_INFO")
+            .ok()
+            .and_then(|name_info| name_info.find_field("Name").map(|f| f.offset))
+            .unwrap_or(0) as _;
+        let symbolic_link_target = PdbStruct::new(pdb_slice, "_OBJECT_SYMBOLIC_LINK")
+            .ok()
+            .and_then(|symbolic_link| symbolic_link.find_field("LinkTarget").map(|f| f.offset))
+            .unwrap_or(0) as _;
+
+        // big pool allocations
+        let big_pages = PdbStruct::new(pdb_slice, "_POOL_TRACKER_BIG_PAGES").ok();
+        let big_pages_field = |name| {
+            big_pages
+                .as_ref()
+                .and_then(|big_pages| big_pages.find_field(name).map(|f| f.offset))
+                .unwrap_or(0) as _
+        };
+        let big_pages_key = big_pages_field("Key");
+        let big_pages_number_of_bytes = big_pages_field("NumberOfBytes");
+        let big_pages_process_billed = big_pages_field("ProcessBilled");
+
+        let ki_processor_block = symbols
+            .find_symbol("KiProcessorBlock")
+            .or_else(|| symbols.find_symbol("_KiProcessorBlock"))
+            .copied()
+            .unwrap_or(0);
+        let ke_number_processors = symbols
+            .find_symbol("KeNumberProcessors")
+            .or_else(|| symbols.find_symbol("_KeNumberProcessors"))
+            .copied()
+            .unwrap_or(0);
+        // _KPCR::PrcbData on x86 (_KPCR::Prcb is a pointer there), _KPCR::Prcb on x64
+        let kpcr = PdbStruct::new(pdb_slice, "_KPCR").ok();
+        let kpcr_prcb = kpcr
+            .as_ref()
+            .and_then(|kpcr| {
+                kpcr.find_field("PrcbData")
+                    .or_else(|| kpcr.find_field("Prcb"))
+                    .map(|f| f.offset)
+            })
+            .unwrap_or(0) as _;
+
+        // _KPCR::IdtBase on x64, _KPCR::IDT on x86
+        let kpcr_idt_base = kpcr
+            .as_ref()
+            .and_then(|kpcr| {
+                kpcr.find_field("IdtBase")
+                    .or_else(|| kpcr.find_field("IDT"))
+                    .map(|f| f.offset)
+            })
+            .unwrap_or(0) as _;
+
+        let cmp_hive_list_head = symbols
+            .find_symbol("CmpHiveListHead")
+            .or_else(|| symbols.find_symbol("_CmpHiveListHead"))
+            .copied()
+            .unwrap_or(0);
+        // registry hives
+        let cmhive = PdbStruct::new(pdb_slice, "_CMHIVE").ok();
+        let cmhive_field = |name| {
+            cmhive
+                .as_ref()
+                .and_then(|cmhive| cmhive.find_field(name).map(|f| f.offset))
+                .unwrap_or(0) as _
+        };
+        let cmhive_hive_list = cmhive_field("HiveList");
+        let cmhive_file_full_path = cmhive_field("FileFullPath");
+        let cmhive_hive_root_path = cmhive_field("HiveRootPath");
+        let hhive = PdbStruct::new(pdb_slice, "_HHIVE").ok();
+        let hhive_field = |name| {
+            hhive
+                .as_ref()
+                .and_then(|hhive| hhive.find_field(name).map(|f| f.offset))
+                .unwrap_or(0) as _
+        };
+        let hhive_base_block = hhive_field("BaseBlock");
+        let hhive_storage = hhive_field("Storage");
+        let hbase_block_root_cell = PdbStruct::new(pdb_slice, "_HBASE_BLOCK")
+            .ok()
+            .and_then(|base_block| base_block.find_field("RootCell").map(|f| f.offset))
+            .unwrap_or(0) as _;
+        let dual = PdbStruct::new(pdb_slice, "_DUAL").ok();
+        let dual_map = dual
+            .as_ref()
+            .and_then(|dual| dual.find_field("Map").map(|f| f.offset))
+            .unwrap_or(0) as _;
+        let dual_size = dual.as_ref().map(|dual| dual.size()).unwrap_or(0) as _;
+        // _HMAP_ENTRY::BlockAddress was replaced by BlockOffset and PermanentBinAddress in windows 10
+        let hmap_entry = PdbStruct::new(pdb_slice, "_HMAP_ENTRY").ok();
+        let hmap_entry_field = |name| {
+            hmap_entry
+                .as_ref()
+                .and_then(|hmap_entry| hmap_entry.find_field(name).map(|f| f.offset))
+        };
+        let hmap_entry_block_address = hmap_entry_field("BlockAddress").unwrap_or(0) as _;
+        let hmap_entry_block_offset = hmap_entry_field("BlockOffset").unwrap_or(0) as _;
+        // PermanentBinAddress is not the first field so a missing field can be detected by its offset
+        let hmap_entry_permanent_bin_address =
+            hmap_entry_field("PermanentBinAddress").unwrap_or(0) as _;
+        let hmap_entry_size = hmap_entry
+            .as_ref()
+            .map(|hmap_entry| hmap_entry.size())
+            .unwrap_or(0) as _;
+
+        let hvl_enlightenments = symbols
+            .find_symbol("HvlEnlightenments")
+            .or_else(|| symbols.find_symbol("_HvlEnlightenments"))
+            .copied()
+            .unwrap_or(0);
+        let hvl_hypervisor_connected = symbols
+            .find_symbol("HvlHypervisorConnected")
+            .or_else(|| symbols.find_symbol("_HvlHypervisorConnected"))
+            .copied()
+            .unwrap_or(0);
+
+        // _EPROCESS::Vm is a _MMSUPPORT_FULL since windows 10 1809,
+        // the working set counters are stored in _MMSUPPORT_FULL::Instance from then on
+        let (eproc_working_set_size, eproc_peak_working_set_size) = eproc
+            .find_field("Vm")
+            .and_then(|vm| {
+                let (instance, mmsupport) = if vm.type_name == "_MMSUPPORT_FULL" {
+                    (
+                        PdbStruct::new(pdb_slice, "_MMSUPPORT_FULL")
+                            .ok()?
+                            .find_field("Instance")?
+                            .offset,
+                        PdbStruct::new(pdb_slice, "_MMSUPPORT_INSTANCE").ok()?,
+                    )
+                } else {
+                    (0, PdbStruct::new(pdb_slice, &vm.type_name).ok()?)
+                };
+                let working_set_size = mmsupport
+                    .find_field("WorkingSetLeafSize")
+                    .or_else(|| mmsupport.find_field("WorkingSetSize"))?
+                    .offset;
+                let peak_working_set_size = mmsupport.find_field("PeakWorkingSetSize")?.offset;
+                Some((
+                    (vm.offset + instance + working_set_size) as _,
+                    (vm.offset + instance + peak_working_set_size) as _,
+                ))
+            })
+            .unwrap_or((0, 0));
+
+        let eproc_commit_charge = eproc
+            .find_field("CommitCharge")
+            .map(|f| f.offset)
+            .unwrap_or(0) as _;
+        let eproc_commit_charge_peak = eproc
+            .find_field("CommitChargePeak")
+            .map(|f| f.offset)
+            .unwrap_or(0) as _;
+
+        let eproc_job = eproc.find_field("Job").map(|f| f.offset).unwrap_or(0) as _;
+        let eproc_job_links = eproc.find_field("JobLinks").map(|f| f.offset).unwrap_or(0) as _;
+        let ejob_process_list_head = PdbStruct::new(pdb_slice, "_EJOB")
+            .ok()
+            .and_then(|ejob| ejob.find_field("ProcessListHead").map(|f| f.offset))
+            .unwrap_or(0) as _;
+
+        // _EPROCESS::SectionObject points to a _SECTION since windows 8
+        // and to a _SECTION_OBJECT with the control area in its _SEGMENT before
+        let eproc_section_object = eproc
+            .find_field("SectionObject")
+            .map(|f| f.offset)
+            .unwrap_or(0) as _;
+        let section_control_area = PdbStruct::new(pdb_slice, "_SECTION")
+            .ok()
+            .and_then(|section| section.find_field("u1").map(|f| f.offset))
+            .unwrap_or(0) as _;
+        let section_object_segment = PdbStruct::new(pdb_slice, "_SECTION_OBJECT")
+            .ok()
+            .and_then(|section| section.find_field("Segment").map(|f| f.offset))
+            .unwrap_or(0) as _;
+        let segment_control_area = PdbStruct::new(pdb_slice, "_SEGMENT")
+            .ok()
+            .and_then(|segment| segment.find_field("ControlArea").map(|f| f.offset))
+            .unwrap_or(0) as _;
+        let control_area_file_pointer = PdbStruct::new(pdb_slice, "_CONTROL_AREA")
+            .ok()
+            .and_then(|control_area| control_area.find_field("FilePointer").map(|f| f.offset))
+            .unwrap_or(0) as _;
+        let file_object_file_name = PdbStruct::new(pdb_slice, "_FILE_OBJECT")
+            .ok()
+            .and_then(|file_object| file_object.find_field("FileName").map(|f| f.offset))
+            .unwrap_or(0) as _;
+
+        let ethread_cid = ethread.find_field("Cid").map(|f| f.offset).unwrap_or(0) as _;
+        let ethread_win32_start_address = ethread
+            .find_field("Win32StartAddress")
+            .map(|f| f.offset)
+            .unwrap_or(0) as _;
+
+        let mmvad_subsection = PdbStruct::new(pdb_slice, "_MMVAD")
+            .ok()
+            .and_then(|mmvad| mmvad.find_field("Subsection").map(|f| f.offset))
+            .unwrap_or(0) as _;
+        let mmvad_private_memory_bit = mm_vad_flags
+            .find_field("PrivateMemory")
+            .map(|f| f.bit_offset)
+            .unwrap_or(0) as _;
+        let subsection_control_area = PdbStruct::new(pdb_slice, "_SUBSECTION")
+            .ok()
+            .and_then(|subsection| subsection.find_field("ControlArea").map(|f| f.offset))
+            .unwrap_or(0) as _;
+
+        let eproc_create_time = eproc
+            .find_field("CreateTime")
+            .map(|f| f.offset)
+            .unwrap_or(0) as _;
+
+        // only exists on kernels with kernel virtual address shadowing (KPTI)
+        let kproc_user_dtb = kproc
+            .find_field("UserDirectoryTableBase")
+            .map(|f| f.offset)
+            .unwrap_or(0) as _;
+
+        let eproc_session = eproc.find_field("Session").map(|f| f.offset).unwrap_or(0) as _;
+        let mm_session_space_session_id = PdbStruct::new(pdb_slice, "_MM_SESSION_SPACE")
+            .ok()
+            .and_then(|session| session.find_field("SessionId").map(|f| f.offset))
+            .unwrap_or(0) as _;
+
         let eproc_vad_root = eproc
             .find_field("VadRoot") // MM_AVL_TABLE *PhysicalVadRoot / MM_AVL_TABLE VadRoot / RTL_AVL_TREE VadRoot
             .ok_or_else(|| {
@@ -348,6 +681,83 @@ impl Win32Offsets {
             teb_peb,
             teb_peb_x86,
 
+            kproc_flags,
+            kproc_deep_freeze_bit,
+
+            kldr_time_date_stamp,
+
+            ob_type_index_table,
+            object_type_name,
+
+            obp_root_directory_object,
+            ob_header_cookie,
+            object_header_body,
+            object_header_type_index,
+            object_header_info_mask,
+            object_header_name_info_name,
+            symbolic_link_target,
+
+            ke_service_descriptor_table,
+
+            pool_big_page_table,
+            pool_big_page_table_size,
+            big_pages_key,
+            big_pages_number_of_bytes,
+            big_pages_process_billed,
+
+            ki_processor_block,
+            ke_number_processors,
+            kpcr_prcb,
+
+            kpcr_idt_base,
+
+            cmp_hive_list_head,
+            cmhive_hive_list,
+            cmhive_file_full_path,
+            cmhive_hive_root_path,
+            hhive_base_block,
+            hhive_storage,
+            hbase_block_root_cell,
+            dual_map,
+            dual_size,
+            hmap_entry_block_address,
+            hmap_entry_block_offset,
+            hmap_entry_permanent_bin_address,
+            hmap_entry_size,
+
+            hvl_enlightenments,
+            hvl_hypervisor_connected,
+
+            eproc_working_set_size,
+            eproc_peak_working_set_size,
+            eproc_commit_charge,
+            eproc_commit_charge_peak,
+
+            eproc_job,
+            eproc_job_links,
+            ejob_process_list_head,
+
+            eproc_section_object,
+            section_control_area,
+            section_object_segment,
+            segment_control_area,
+            control_area_file_pointer,
+            file_object_file_name,
+
+            ethread_cid,
+            ethread_win32_start_address,
+
+            mmvad_subsection,
+            mmvad_private_memory_bit,
+            subsection_control_area,
+
+            eproc_create_time,
+
+            kproc_user_dtb,
+
+            eproc_session,
+            mm_session_space_session_id,
+
             mmvad: MmVadOffsetTable {
                 vad_node,
                 starting_vpn,
@@ -441,6 +851,297 @@ impl Win32Offsets {
         self.0.teb_peb_x86 as usize
     }
 
+    /// _KPROCESS::ProcessFlags offset
+    /// Exists since version 6.2, only set if _KPROCESS::DeepFreeze exists
+    pub fn kproc_flags(&self) -> usize {
+        self.0.kproc_flags as usize
+    }
+    /// _KPROCESS::DeepFreeze bit in _KPROCESS::ProcessFlags
+    /// Exists since version 6.2
+    pub fn kproc_deep_freeze_bit(&self) -> usize {
+        self.0.kproc_deep_freeze_bit as usize
+    }
+
+    /// _KLDR_DATA_TABLE_ENTRY::TimeDateStamp offset
+    /// Exists since version 5.0
+    pub fn kldr_time_date_stamp(&self) -> usize {
+        self.0.kldr_time_date_stamp as usize
+    }
+
+    /// ObTypeIndexTable offset
+    /// Exists since version 6.1
+    pub fn ob_type_index_table(&self) -> usize {
+        self.0.ob_type_index_table as usize
+    }
+    /// _OBJECT_TYPE::Name offset
+    pub fn object_type_name(&self) -> usize {
+        self.0.object_type_name as usize
+    }
+
+    /// ObpRootDirectoryObject offset
+    pub fn obp_root_directory_object(&self) -> usize {
+        self.0.obp_root_directory_object as usize
+    }
+    /// ObHeaderCookie offset
+    /// Exists since version 10.0
+    pub fn ob_header_cookie(&self) -> usize {
+        self.0.ob_header_cookie as usize
+    }
+    /// _OBJECT_HEADER::Body offset
+    pub fn object_header_body(&self) -> usize {
+        self.0.object_header_body as usize
+    }
+    /// _OBJECT_HEADER::TypeIndex offset
+    /// Exists since version 6.1
+    pub fn object_header_type_index(&self) -> usize {
+        self.0.object_header_type_index as usize
+    }
+    /// _OBJECT_HEADER::InfoMask offset
+    /// Exists since version 6.1
+    pub fn object_header_info_mask(&self) -> usize {
+        self.0.object_header_info_mask as usize
+    }
+    /// _OBJECT_HEADER_NAME_INFO::Name offset
+    pub fn object_header_name_info_name(&self) -> usize {
+        self.0.object_header_name_info_name as usize
+    }
+    /// _OBJECT_SYMBOLIC_LINK::LinkTarget offset
+    pub fn symbolic_link_target(&self) -> usize {
+        self.0.symbolic_link_target as usize
+    }
+
+    /// KeServiceDescriptorTable offset
+    pub fn ke_service_descriptor_table(&self) -> usize {
+        self.0.ke_service_descriptor_table as usize
+    }
+
+    /// PoolBigPageTable offset
+    pub fn pool_big_page_table(&self) -> usize {
+        self.0.pool_big_page_table as usize
+    }
+    /// PoolBigPageTableSize offset
+    pub fn pool_big_page_table_size(&self) -> usize {
+        self.0.pool_big_page_table_size as usize
+    }
+    /// _POOL_TRACKER_BIG_PAGES::Key offset
+    pub fn big_pages_key(&self) -> usize {
+        self.0.big_pages_key as usize
+    }
+    /// _POOL_TRACKER_BIG_PAGES::NumberOfBytes offset
+    pub fn big_pages_number_of_bytes(&self) -> usize {
+        self.0.big_pages_number_of_bytes as usize
+    }
+    /// _POOL_TRACKER_BIG_PAGES::ProcessBilled offset
+    /// Exists since version 10.0.18362
+    pub fn big_pages_process_billed(&self) -> usize {
+        self.0.big_pages_process_billed as usize
+    }
+
+    /// KiProcessorBlock offset
+    pub fn ki_processor_block(&self) -> usize {
+        self.0.ki_processor_block as usize
+    }
+
+    /// KeNumberProcessors offset
+    pub fn ke_number_processors(&self) -> usize {
+        self.0.ke_number_processors as usize
+    }
+
+    /// _KPCR::Prcb offset
+    pub fn kpcr_prcb(&self) -> usize {
+        self.0.kpcr_prcb as usize
+    }
+
+    /// _KPCR::IdtBase offset
+    pub fn kpcr_idt_base(&self) -> usize {
+        self.0.kpcr_idt_base as usize
+    }
+
+    /// CmpHiveListHead offset
+    pub fn cmp_hive_list_head(&self) -> usize {
+        self.0.cmp_hive_list_head as usize
+    }
+
+    /// _CMHIVE::HiveList offset
+    pub fn cmhive_hive_list(&self) -> usize {
+        self.0.cmhive_hive_list as usize
+    }
+
+    /// _CMHIVE::FileFullPath offset
+    pub fn cmhive_file_full_path(&self) -> usize {
+        self.0.cmhive_file_full_path as usize
+    }
+
+    /// _CMHIVE::HiveRootPath offset
+    pub fn cmhive_hive_root_path(&self) -> usize {
+        self.0.cmhive_hive_root_path as usize
+    }
+
+    /// _HHIVE::BaseBlock offset
+    pub fn hhive_base_block(&self) -> usize {
+        self.0.hhive_base_block as usize
+    }
+
+    /// _HHIVE::Storage offset
+    pub fn hhive_storage(&self) -> usize {
+        self.0.hhive_storage as usize
+    }
+
+    /// _HBASE_BLOCK::RootCell offset
+    pub fn hbase_block_root_cell(&self) -> usize {
+        self.0.hbase_block_root_cell as usize
+    }
+
+    /// _DUAL::Map offset
+    pub fn dual_map(&self) -> usize {
+        self.0.dual_map as usize
+    }
+
+    /// Size of the _DUAL struct
+    pub fn dual_size(&self) -> usize {
+        self.0.dual_size as usize
+    }
+
+    /// _HMAP_ENTRY::BlockAddress offset
+    pub fn hmap_entry_block_address(&self) -> usize {
+        self.0.hmap_entry_block_address as usize
+    }
+
+    /// _HMAP_ENTRY::BlockOffset offset
+    pub fn hmap_entry_block_offset(&self) -> usize {
+        self.0.hmap_entry_block_offset as usize
+    }
+
+    /// _HMAP_ENTRY::PermanentBinAddress offset
+    pub fn hmap_entry_permanent_bin_address(&self) -> usize {
+        self.0.hmap_entry_permanent_bin_address as usize
+    }
+
+    /// Size of the _HMAP_ENTRY struct
+    pub fn hmap_entry_size(&self) -> usize {
+        self.0.hmap_entry_size as usize
+    }
+
+    /// HvlEnlightenments offset
+    pub fn hvl_enlightenments(&self) -> usize {
+        self.0.hvl_enlightenments as usize
+    }
+
+    /// HvlHypervisorConnected offset
+    pub fn hvl_hypervisor_connected(&self) -> usize {
+        self.0.hvl_hypervisor_connected as usize
+    }
+
+    /// _EPROCESS::Vm working set size in pages
+    pub fn eproc_working_set_size(&self) -> usize {
+        self.0.eproc_working_set_size as usize
+    }
+
+    /// _EPROCESS::Vm peak working set size in pages
+    pub fn eproc_peak_working_set_size(&self) -> usize {
+        self.0.eproc_peak_working_set_size as usize
+    }
+
+    /// _EPROCESS::CommitCharge in pages
+    pub fn eproc_commit_charge(&self) -> usize {
+        self.0.eproc_commit_charge as usize
+    }
+
+    /// _EPROCESS::CommitChargePeak in pages
+    pub fn eproc_commit_charge_peak(&self) -> usize {
+        self.0.eproc_commit_charge_peak as usize
+    }
+
+    /// _EPROCESS::Job
+    pub fn eproc_job(&self) -> usize {
+        self.0.eproc_job as usize
+    }
+
+    /// _EPROCESS::JobLinks
+    pub fn eproc_job_links(&self) -> usize {
+        self.0.eproc_job_links as usize
+    }
+
+    /// _EJOB::ProcessListHead
+    pub fn ejob_process_list_head(&self) -> usize {
+        self.0.ejob_process_list_head as usize
+    }
+
+    /// _EPROCESS::SectionObject
+    pub fn eproc_section_object(&self) -> usize {
+        self.0.eproc_section_object as usize
+    }
+
+    /// _SECTION::u1 (ControlArea)
+    pub fn section_control_area(&self) -> usize {
+        self.0.section_control_area as usize
+    }
+
+    /// _SECTION_OBJECT::Segment
+    pub fn section_object_segment(&self) -> usize {
+        self.0.section_object_segment as usize
+    }
+
+    /// _SEGMENT::ControlArea
+    pub fn segment_control_area(&self) -> usize {
+        self.0.segment_control_area as usize
+    }
+
+    /// _CONTROL_AREA::FilePointer
+    pub fn control_area_file_pointer(&self) -> usize {
+        self.0.control_area_file_pointer as usize
+    }
+
+    /// _FILE_OBJECT::FileName
+    pub fn file_object_file_name(&self) -> usize {
+        self.0.file_object_file_name as usize
+    }
+
+    /// _ETHREAD::Cid
+    pub fn ethread_cid(&self) -> usize {
+        self.0.ethread_cid as usize
+    }
+
+    /// _ETHREAD::Win32StartAddress
+    pub fn ethread_win32_start_address(&self) -> usize {
+        self.0.ethread_win32_start_address as usize
+    }
+
+    /// _MMVAD::Subsection
+    pub fn mmvad_subsection(&self) -> usize {
+        self.0.mmvad_subsection as usize
+    }
+
+    /// Bit offset of _MMVAD_FLAGS::PrivateMemory
+    pub fn mmvad_private_memory_bit(&self) -> usize {
+        self.0.mmvad_private_memory_bit as usize
+    }
+
+    /// _SUBSECTION::ControlArea
+    pub fn subsection_control_area(&self) -> usize {
+        self.0.subsection_control_area as usize
+    }
+
+    /// _EPROCESS::CreateTime
+    pub fn eproc_create_time(&self) -> usize {
+        self.0.eproc_create_time as usize
+    }
+
+    /// _KPROCESS::UserDirectoryTableBase
+    pub fn kproc_user_dtb(&self) -> usize {
+        self.0.kproc_user_dtb as usize
+    }
+
+    /// _EPROCESS::Session
+    pub fn eproc_session(&self) -> usize {
+        self.0.eproc_session as usize
+    }
+
+    /// _MM_SESSION_SPACE::SessionId
+    pub fn mm_session_space_session_id(&self) -> usize {
+        self.0.mm_session_space_session_id as usize
+    }
+
     /// _MMVAD_SHORT offsets
     pub fn mm_vad(&self) -> MmVadOffsetTable {
         self.0.mmvad
@@ -453,10 +1154,10 @@ impl Win32Offsets {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     // this test is not ideal for the CI so it's disabled for now.
     /*
+    use super::*;
+
     #[test]
     fn download_pdb() {
         let guid = Win32Guid {
@@ -4,6 +4,18 @@ use std::convert::TryFrom;
 use std::str;
 
 use memflow::dataview::Pod;
+use memflow::error::{Error, ErrorKind, ErrorOrigin};
+
+/// Current version of the offset file schema.
+///
+/// The version has to be increased whenever the meaning of an existing field changes.
+/// Offset files without a `schema_version` field are treated as version 1.
+pub const WIN32_OFFSET_SCHEMA_VERSION: u32 = 1;
+
+#[cfg(feature = "serde")]
+fn default_schema_version() -> u32 {
+    1
+}
 
 /// Describes an offset file.
 /// At compile time this crate will create a binary blob of all
@@ -29,6 +41,136 @@ pub struct Win32OffsetFile {
     pub offsets: Win32OffsetTable,
 }
 
+/// Offsets for a second architecture of the same build stored in an offset file
+///
+/// This allows x86 and x64 offsets of a build to be kept in a single file:
+///
+/// ```toml
+/// [header]
+/// # ...
+/// arch = "X64"
+///
+/// [offsets]
+/// # ...
+///
+/// [secondary]
+/// pdb_file_name = "ntkrpamp.pdb"
+/// pdb_guid = "..."
+/// arch = "X86"
+///
+/// [secondary.offsets]
+/// # ...
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Win32OffsetSecondary {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pdb_file_name: BinaryString<128>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pdb_guid: BinaryString<128>,
+    pub arch: Win32OffsetsArchitecture,
+    pub offsets: Win32OffsetTable,
+}
+
+/// Layout of an offset file with an optional secondary architecture block
+#[cfg(feature = "toml")]
+#[derive(::serde::Deserialize)]
+struct Win32OffsetFileMultiArch {
+    header: Win32OffsetHeader,
+    offsets: Win32OffsetTable,
+    secondary: Option<Win32OffsetSecondary>,
+}
+
+impl Win32OffsetFile {
+    /// Parses and validates an offset file in the TOML format.
+    ///
+    /// A `[secondary]` block is ignored, use `from_toml_all` to load it as well.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(content: &str) -> memflow::error::Result<Self> {
+        let file = toml::from_str::<Self>(content)
+            .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::Configuration).log_warn(err))?;
+        file.validate()?;
+        Ok(file)
+    }
+
+    /// Parses and validates an offset file in the TOML format including its `[secondary]` block.
+    ///
+    /// Returns one file per architecture, the primary architecture comes first.
+    /// The secondary file shares the version of the primary header.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_all(content: &str) -> memflow::error::Result<Vec<Self>> {
+        let file = toml::from_str::<Win32OffsetFileMultiArch>(content)
+            .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::Configuration).log_warn(err))?;
+
+        let mut files = vec![Self {
+            header: file.header.clone(),
+            offsets: file.offsets,
+        }];
+        if let Some(secondary) = file.secondary {
+            if secondary.arch == file.header.arch {
+                return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Configuration)
+                    .log_warn("secondary offsets must use a different architecture"));
+            }
+
+            files.push(Self {
+                header: Win32OffsetHeader {
+                    pdb_file_name: secondary.pdb_file_name,
+                    pdb_guid: secondary.pdb_guid,
+                    arch: secondary.arch,
+                    ..file.header
+                },
+                offsets: secondary.offsets,
+            });
+        }
+
+        for file in files.iter() {
+            file.validate()?;
+        }
+        Ok(files)
+    }
+
+    /// Returns the file matching the given architecture.
+    pub fn select_arch(files: &[Self], arch: Win32OffsetsArchitecture) -> Option<&Self> {
+        files.iter().find(|file| file.header.arch == arch)
+    }
+
+    /// Serializes the offset file into the TOML format.
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> memflow::error::Result<String> {
+        toml::to_string_pretty(self)
+            .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::Configuration).log_warn(err))
+    }
+
+    /// Validates the schema version and the fields that are required to use the offsets.
+    pub fn validate(&self) -> memflow::error::Result<()> {
+        self.header.validate_schema()?;
+
+        let missing = if self.header.nt_major_version == 0 {
+            Some("header.nt_major_version")
+        } else if self.offsets.eproc_link == 0 {
+            Some("offsets.eproc_link")
+        } else if self.offsets.eproc_pid == 0 {
+            Some("offsets.eproc_pid")
+        } else if self.offsets.eproc_name == 0 {
+            Some("offsets.eproc_name")
+        } else if self.offsets.kproc_dtb == 0 {
+            Some("offsets.kproc_dtb")
+        } else {
+            None
+        };
+
+        match missing {
+            Some(field) => Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::Configuration).log_warn(format!(
+                    "offset file is missing the required field {}",
+                    field
+                )),
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
 #[repr(C, align(4))]
 #[derive(Debug, Clone, Pod)]
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
@@ -46,6 +188,26 @@ pub struct Win32OffsetHeader {
 
     // Architecture
     pub arch: Win32OffsetsArchitecture,
+
+    // Schema
+    #[cfg_attr(feature = "serde", serde(default = "default_schema_version"))]
+    pub schema_version: u32,
+}
+
+impl Win32OffsetHeader {
+    /// Returns an error if the file was written with a different schema version than this crate uses.
+    pub fn validate_schema(&self) -> memflow::error::Result<()> {
+        if self.schema_version == WIN32_OFFSET_SCHEMA_VERSION {
+            Ok(())
+        } else {
+            Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::VersionMismatch).log_warn(format!(
+                    "offset file schema version {} does not match the supported version {}",
+                    self.schema_version, WIN32_OFFSET_SCHEMA_VERSION
+                )),
+            )
+        }
+    }
 }
 
 const _: [(); std::mem::size_of::<[Win32OffsetHeader; 16]>()] =
@@ -212,6 +374,153 @@ pub struct Win32OffsetTable {
     /// Since version x.x
     pub teb_peb_x86: u32,
 
+    /// Since version 6.2
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kproc_flags: u32,
+    /// Since version 6.2
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kproc_deep_freeze_bit: u32,
+
+    /// Since version 5.0
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kldr_time_date_stamp: u32,
+
+    /// Since version 6.1
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ob_type_index_table: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub object_type_name: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub obp_root_directory_object: u32,
+    /// Since version 10.0
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ob_header_cookie: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub object_header_body: u32,
+    /// Since version 6.1
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub object_header_type_index: u32,
+    /// Since version 6.1
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub object_header_info_mask: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub object_header_name_info_name: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub symbolic_link_target: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ke_service_descriptor_table: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pool_big_page_table: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pool_big_page_table_size: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub big_pages_key: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub big_pages_number_of_bytes: u32,
+    /// Since version 10.0.18362
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub big_pages_process_billed: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ki_processor_block: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ke_number_processors: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kpcr_prcb: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kpcr_idt_base: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cmp_hive_list_head: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cmhive_hive_list: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cmhive_file_full_path: u32,
+    /// Since version 6.0
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cmhive_hive_root_path: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hhive_base_block: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hhive_storage: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hbase_block_root_cell: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dual_map: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dual_size: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hmap_entry_block_address: u32,
+    /// Since version 10.0
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hmap_entry_block_offset: u32,
+    /// Since version 10.0
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hmap_entry_permanent_bin_address: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hmap_entry_size: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hvl_enlightenments: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hvl_hypervisor_connected: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_working_set_size: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_peak_working_set_size: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_commit_charge: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_commit_charge_peak: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_job: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_job_links: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ejob_process_list_head: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_section_object: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub section_control_area: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub section_object_segment: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub segment_control_area: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub control_area_file_pointer: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file_object_file_name: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ethread_cid: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ethread_win32_start_address: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mmvad_subsection: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mmvad_private_memory_bit: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub subsection_control_area: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_create_time: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kproc_user_dtb: u32,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub eproc_session: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mm_session_space_session_id: u32,
+
     pub mmvad: MmVadOffsetTable,
 }
 
@@ -227,3 +536,88 @@ pub struct MmVadOffsetTable {
     pub u: u32,
     pub protection_bit: u32,
 }
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+    use super::*;
+
+    use memflow::dataview::PodMethods;
+
+    fn offset_file() -> Win32OffsetFile {
+        let mut offsets = Win32OffsetTable::zeroed();
+        offsets.list_blink = 8;
+        offsets.eproc_link = 752;
+        offsets.eproc_pid = 744;
+        offsets.eproc_name = 1104;
+        offsets.kproc_dtb = 40;
+        offsets.mmvad.starting_vpn = 24;
+
+        Win32OffsetFile {
+            header: Win32OffsetHeader {
+                pdb_file_name: "ntkrnlmp.pdb".into(),
+                pdb_guid: "0AFB69F5FD264D54673570E37B38A3181".into(),
+                nt_major_version: 10,
+                nt_minor_version: 0,
+                nt_build_number: 18362,
+                arch: Win32OffsetsArchitecture::X64,
+                schema_version: WIN32_OFFSET_SCHEMA_VERSION,
+            },
+            offsets,
+        }
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let file = offset_file();
+        let parsed = Win32OffsetFile::from_toml(&file.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed.as_bytes(), file.as_bytes());
+    }
+
+    #[test]
+    fn toml_secondary_arch() {
+        let primary = offset_file();
+        let mut secondary = offset_file();
+        secondary.header.arch = Win32OffsetsArchitecture::X86;
+        secondary.header.pdb_file_name = "ntkrpamp.pdb".into();
+        secondary.offsets.eproc_link = 184;
+
+        let mut content = toml::Table::try_from(&primary).unwrap();
+        content.insert(
+            "secondary".into(),
+            toml::Value::try_from(Win32OffsetSecondary {
+                pdb_file_name: secondary.header.pdb_file_name.clone(),
+                pdb_guid: secondary.header.pdb_guid.clone(),
+                arch: secondary.header.arch,
+                offsets: secondary.offsets,
+            })
+            .unwrap(),
+        );
+        let content = content.to_string();
+
+        let files = Win32OffsetFile::from_toml_all(&content).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].as_bytes(), primary.as_bytes());
+        assert_eq!(files[1].as_bytes(), secondary.as_bytes());
+
+        let x86 = Win32OffsetFile::select_arch(&files, Win32OffsetsArchitecture::X86).unwrap();
+        assert_eq!(x86.offsets.eproc_link, 184);
+        assert!(Win32OffsetFile::select_arch(&files, Win32OffsetsArchitecture::AArch64).is_none());
+
+        // the secondary block does not affect the primary file
+        assert_eq!(
+            Win32OffsetFile::from_toml(&content).unwrap().as_bytes(),
+            primary.as_bytes()
+        );
+    }
+
+    #[test]
+    fn toml_missing_fields() {
+        let mut file = offset_file();
+        file.offsets.eproc_pid = 0;
+        assert!(Win32OffsetFile::from_toml(&file.to_toml().unwrap()).is_err());
+
+        let mut file = offset_file();
+        file.header.schema_version = WIN32_OFFSET_SCHEMA_VERSION + 1;
+        assert!(Win32OffsetFile::from_toml(&file.to_toml().unwrap()).is_err());
+    }
+}
@@ -50,6 +50,7 @@ pub struct PdbField {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PdbStruct {
     field_map: HashMap<String, PdbField>,
+    size: usize,
 }
 
 impl PdbStruct {
@@ -63,6 +64,7 @@ impl PdbStruct {
         let mut needed_types = TypeSet::new();
         let mut data = data::Data::new();
 
+        let mut size = 0;
         let mut type_iter = type_information.iter();
         while let Some(typ) = type_iter.next()? {
             // keep building the index
@@ -72,6 +74,7 @@ impl PdbStruct {
                 if class.name.as_bytes() == class_name.as_bytes()
                     && !class.properties.forward_reference()
                 {
+                    size = class.size as usize;
                     data.add(&type_finder, typ.index(), &mut needed_types)?;
                     break;
                 }
@@ -108,12 +111,17 @@ impl PdbStruct {
             });
         }
 
-        Ok(Self { field_map })
+        Ok(Self { field_map, size })
     }
 
     pub fn find_field(&self, name: &str) -> Option<&PdbField> {
         self.field_map.get(name)
     }
+
+    /// Returns the size of the struct in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
 }
 
 pub struct PdbSourceBuffer<'a> {
@@ -54,7 +54,8 @@ fn embed_offsets() -> Result<(), Box<dyn Error>> {
 
         std::mem::drop(file);
 
-        let offsets = match toml::from_str::<Win32OffsetFile>(&tomlstr) {
+        // the primary file comes first, followed by the file of a secondary architecture (if any)
+        let files = match Win32OffsetFile::from_toml_all(&tomlstr) {
             x if regenerate_offsets == 2 || (regenerate_offsets == 1 && x.is_err()) => {
                 let regenerate = || -> Result<Win32OffsetFile, Box<dyn Error>> {
                     let mut header = match toml::from_str::<Win32OffsetFileStart>(&tomlstr) {
@@ -90,6 +91,7 @@ fn embed_offsets() -> Result<(), Box<dyn Error>> {
                                     nt_major_version,
                                     nt_minor_version,
                                     nt_build_number,
+                                    schema_version: WIN32_OFFSET_SCHEMA_VERSION,
                                 },
                             }
                         }
@@ -132,11 +134,23 @@ fn embed_offsets() -> Result<(), Box<dyn Error>> {
                 };
 
                 match regenerate() {
-                    Ok(x) => {
+                    Ok(primary) => {
+                        let mut content = toml::Table::try_from(&primary)?;
+
+                        // only the primary architecture is regenerated, the secondary block is kept as is
+                        if let Some(secondary) = tomlstr
+                            .parse::<toml::Table>()
+                            .ok()
+                            .and_then(|mut table| table.remove("secondary"))
+                        {
+                            content.insert("secondary".into(), secondary);
+                        }
+                        let content = content.to_string();
+
                         let mut file = File::create(&fp)?;
-                        file.write_all(toml::to_string(&x)?.as_bytes())?;
+                        file.write_all(content.as_bytes())?;
 
-                        x
+                        Win32OffsetFile::from_toml_all(&content)?
                     }
                     Err(e) => {
                         // Do not report failure if forced, but already contains correct configuration
@@ -148,10 +162,13 @@ fn embed_offsets() -> Result<(), Box<dyn Error>> {
                     }
                 }
             }
-            x => x?,
+            x => x.map_err(|err| format!("{}: {}", fp.display(), err))?,
         };
 
-        all_the_files.write_all(offsets.as_bytes())?;
+        // offsets of a secondary architecture are embedded as a separate file
+        for file in files.iter() {
+            all_the_files.write_all(file.as_bytes())?;
+        }
     }
 
     Ok(())
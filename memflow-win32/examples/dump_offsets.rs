@@ -46,6 +46,8 @@ pub fn main() -> Result<()> {
                 nt_major_version: winver.major_version(),
                 nt_minor_version: winver.minor_version(),
                 nt_build_number: winver.build_number(),
+
+                schema_version: WIN32_OFFSET_SCHEMA_VERSION,
             },
             offsets: os.offsets.into(),
         };
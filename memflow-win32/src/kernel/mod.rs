@@ -1,6 +1,8 @@
 pub mod ntos;
+pub(crate) mod retry;
 pub mod start_block;
 pub mod sysproc;
 
 pub use memflow_win32_defs::kernel::*;
+pub use ntos::Win32VersionSource;
 pub use start_block::StartBlock;
@@ -1,16 +1,18 @@
 pub(crate) mod pehelper;
 
+mod aarch64;
 mod x64;
 mod x86;
 
 use super::{StartBlock, Win32Guid, Win32Version};
+use crate::win32::Win32SharedUserData;
 
 use std::convert::TryInto;
 use std::prelude::v1::*;
 
 use log::{info, warn};
 
-use memflow::architecture::ArchitectureObj;
+use memflow::architecture::{ArchitectureIdent, ArchitectureObj};
 use memflow::error::{Error, ErrorKind, ErrorOrigin, PartialResultExt, Result};
 use memflow::mem::{MemoryView, VirtualTranslate};
 use memflow::types::{umem, Address};
@@ -22,7 +24,12 @@ pub fn find<T: MemoryView + VirtualTranslate>(
     start_block: &StartBlock,
 ) -> Result<(Address, umem)> {
     let arch_obj = ArchitectureObj::from(start_block.arch);
-    if arch_obj.bits() == 64 {
+    if let ArchitectureIdent::AArch64(_) = start_block.arch {
+        match aarch64::find_with_va_hint(virt_mem, start_block) {
+            Ok(b) => return Ok(b),
+            Err(e) => warn!("aarch64::find_with_va_hint() error: {}", e),
+        }
+    } else if arch_obj.bits() == 64 {
         if !start_block.kernel_hint.is_null() {
             match x64::find_with_va_hint(virt_mem, start_block) {
                 Ok(b) => return Ok(b),
@@ -106,14 +113,116 @@ fn get_export(pe: &PeView, name: &str) -> Result<umem> {
     Ok(export)
 }
 
-pub fn find_winver<T: MemoryView>(mem: &mut T, kernel_base: Address) -> Result<Win32Version> {
+/// Method that was used to determine the kernel version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum Win32VersionSource {
+    /// NtBuildNumber combined with the major/minor version from KUSER_SHARED_DATA or RtlGetVersion
+    RtlGetVersion,
+    /// NtBuildNumber only, the major/minor version is derived from the build number
+    NtBuildNumber,
+    /// _KUSER_SHARED_DATA::NtBuildNumber and the major/minor version from KUSER_SHARED_DATA
+    SharedUserData,
+    /// VS_VERSIONINFO resource of the ntoskrnl image
+    VersionResource,
+    /// The version was supplied by the user
+    UserSupplied,
+    /// The version could not be determined
+    #[default]
+    Unknown,
+}
+
+impl Win32VersionSource {
+    /// Returns true if the version was read from the target and not guessed
+    pub fn is_confident(&self) -> bool {
+        matches!(
+            self,
+            Win32VersionSource::RtlGetVersion
+                | Win32VersionSource::SharedUserData
+                | Win32VersionSource::VersionResource
+                | Win32VersionSource::UserSupplied
+        )
+    }
+}
+
+/// Determines the kernel version, see `find_winver_with_source`.
+///
+/// `arch` is the architecture of the kernel as detected in the start block.
+pub fn find_winver<T: MemoryView>(
+    mem: &mut T,
+    kernel_base: Address,
+    arch: ArchitectureIdent,
+) -> Result<Win32Version> {
+    find_winver_with_source(mem, kernel_base, arch).map(|(version, _)| version)
+}
+
+/// Determines the kernel version and the method that was used to retrieve it.
+///
+/// The following methods are tried in order:
+/// - NtBuildNumber with the major/minor version from KUSER_SHARED_DATA or the RtlGetVersion code
+/// - NtBuildNumber with the major/minor version derived from the build number
+/// - the version fields of KUSER_SHARED_DATA (Windows 10 and later)
+/// - the VS_VERSIONINFO resource of the ntoskrnl image
+///
+/// The ntoskrnl image is read once and each method only performs a fixed number of reads.
+pub fn find_winver_with_source<T: MemoryView>(
+    mem: &mut T,
+    kernel_base: Address,
+    arch: ArchitectureIdent,
+) -> Result<(Win32Version, Win32VersionSource)> {
     let image = pehelper::try_get_pe_image(mem, kernel_base)?;
     let pe = PeView::from_bytes(&image)
         .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_info(err))?;
 
+    match find_nt_build_number(mem, &pe, kernel_base) {
+        Ok(nt_build_number) => {
+            if let Some((nt_major_version, nt_minor_version)) = find_nt_version(mem, &pe) {
+                // construct Win32BuildNumber object (major and minor version might be null but build number should be set)
+                let version =
+                    Win32Version::new(nt_major_version, nt_minor_version, nt_build_number);
+                info!("kernel version: {}", version);
+                return Ok((version, Win32VersionSource::RtlGetVersion));
+            }
+
+            if let Some((nt_major_version, nt_minor_version)) =
+                nt_version_from_build_number(nt_build_number & 0xFFFF)
+            {
+                let version =
+                    Win32Version::new(nt_major_version, nt_minor_version, nt_build_number);
+                warn!("kernel version derived from the build number: {}", version);
+                return Ok((version, Win32VersionSource::NtBuildNumber));
+            }
+        }
+        Err(err) => warn!("unable to read NtBuildNumber: {}", err),
+    }
+
+    match Win32SharedUserData::read(mem, arch) {
+        Ok(Win32SharedUserData {
+            nt_build_number: Some(nt_build_number),
+            nt_major_version,
+            nt_minor_version,
+            ..
+        }) => {
+            let version = Win32Version::new(nt_major_version, nt_minor_version, nt_build_number);
+            info!("kernel version from KUSER_SHARED_DATA: {}", version);
+            return Ok((version, Win32VersionSource::SharedUserData));
+        }
+        Ok(_) => warn!("KUSER_SHARED_DATA does not contain NtBuildNumber"),
+        Err(err) => warn!("unable to read KUSER_SHARED_DATA: {}", err),
+    }
+
+    let version = find_winver_resource(&pe)?;
+    info!("kernel version from version resource: {}", version);
+    Ok((version, Win32VersionSource::VersionResource))
+}
+
+fn find_nt_build_number<T: MemoryView>(
+    mem: &mut T,
+    pe: &PeView,
+    kernel_base: Address,
+) -> Result<u32> {
     // NtBuildNumber
-    let nt_build_number_ref = get_export(&pe, "NtBuildNumber")?;
-    let rtl_get_version_ref = get_export(&pe, "RtlGetVersion");
+    let nt_build_number_ref = get_export(pe, "NtBuildNumber")?;
 
     let nt_build_number: u32 = mem.read(kernel_base + nt_build_number_ref)?;
     info!("nt_build_number: {}", nt_build_number);
@@ -122,48 +231,119 @@ pub fn find_winver<T: MemoryView>(mem: &mut T, kernel_base: Address) -> Result<W
             .log_info("unable to fetch nt build number"));
     }
 
-    // TODO: these reads should be optional
+    Ok(nt_build_number)
+}
+
+fn find_nt_version<T: MemoryView>(mem: &mut T, pe: &PeView) -> Option<(u32, u32)> {
     // try to find major/minor version
     // read from KUSER_SHARED_DATA. these fields exist since nt 4.0 so they have to exist in case NtBuildNumber exists.
-    let mut nt_major_version: u32 = mem.read((0x7ffe0000 + 0x026C).into()).data_part()?;
-    let mut nt_minor_version: u32 = mem.read((0x7ffe0000 + 0x0270).into()).data_part()?;
+    let nt_major_version: u32 = mem
+        .read((0x7ffe0000 + 0x026C).into())
+        .data_part()
+        .unwrap_or_default();
+    let nt_minor_version: u32 = mem
+        .read((0x7ffe0000 + 0x0270).into())
+        .data_part()
+        .unwrap_or_default();
+    if nt_major_version != 0 {
+        return Some((nt_major_version, nt_minor_version));
+    }
 
     // fallback on x64: try to parse RtlGetVersion assembly
-    if nt_major_version == 0 && rtl_get_version_ref.is_ok() {
-        let mut buf = [0u8; 0x100];
-        mem.read_into(kernel_base + rtl_get_version_ref.unwrap(), &mut buf)
-            .data_part()?;
-
-        nt_major_version = 0;
-        nt_minor_version = 0;
-
-        for i in 0..0xf0 {
-            if nt_major_version == 0
-                && nt_minor_version == 0
-                && u32::from_le_bytes(buf[i..i + 4].try_into().unwrap()) == 0x441c748
-            {
-                nt_major_version =
-                    u16::from_le_bytes(buf[i + 4..i + 4 + 2].try_into().unwrap()) as u32;
-                nt_minor_version = (buf[i + 5] & 0xF) as u32;
-            }
+    let rtl_get_version_ref = get_export(pe, "RtlGetVersion").ok()?;
+    let buf = pe.image().get(rtl_get_version_ref as usize..)?;
+    let buf = &buf[..buf.len().min(0x100)];
 
-            if nt_major_version == 0
-                && u32::from_le_bytes(buf[i..i + 4].try_into().unwrap()) & 0xFFFFF == 0x441c7
-            {
-                nt_major_version = buf[i + 3] as u32;
-            }
+    let mut nt_major_version = 0;
+    let mut nt_minor_version = 0;
 
-            if nt_minor_version == 0
-                && u32::from_le_bytes(buf[i..i + 4].try_into().unwrap()) & 0xFFFFF == 0x841c7
-            {
-                nt_major_version = buf[i + 3] as u32;
-            }
+    for i in 0..buf.len().saturating_sub(0x10) {
+        if nt_major_version == 0
+            && nt_minor_version == 0
+            && u32::from_le_bytes(buf[i..i + 4].try_into().unwrap()) == 0x441c748
+        {
+            nt_major_version = u16::from_le_bytes(buf[i + 4..i + 4 + 2].try_into().unwrap()) as u32;
+            nt_minor_version = (buf[i + 5] & 0xF) as u32;
+        }
+
+        if nt_major_version == 0
+            && u32::from_le_bytes(buf[i..i + 4].try_into().unwrap()) & 0xFFFFF == 0x441c7
+        {
+            nt_major_version = buf[i + 3] as u32;
+        }
+
+        if nt_minor_version == 0
+            && u32::from_le_bytes(buf[i..i + 4].try_into().unwrap()) & 0xFFFFF == 0x841c7
+        {
+            nt_minor_version = buf[i + 3] as u32;
         }
     }
 
-    // construct Win32BuildNumber object (major and minor version might be null but build number should be set)
-    let version = Win32Version::new(nt_major_version, nt_minor_version, nt_build_number);
-    info!("kernel version: {}", version);
+    if nt_major_version != 0 {
+        Some((nt_major_version, nt_minor_version))
+    } else {
+        None
+    }
+}
+
+/// Maps a build number to the nt major/minor version of the corresponding release
+fn nt_version_from_build_number(build_number: u32) -> Option<(u32, u32)> {
+    match build_number {
+        1381 => Some((4, 0)),
+        2195 => Some((5, 0)),
+        2600 => Some((5, 1)),
+        3790 => Some((5, 2)),
+        6000..=6003 => Some((6, 0)),
+        7600..=7601 => Some((6, 1)),
+        9200 => Some((6, 2)),
+        9600 => Some((6, 3)),
+        10240.. => Some((10, 0)),
+        _ => None,
+    }
+}
+
+/// Parses the kernel version from the VS_VERSIONINFO resource of the given ntoskrnl image.
+///
+/// The FileVersion of the VS_FIXEDFILEINFO is used, if it is not set the ProductVersion is used instead.
+pub fn find_winver_from_resource(image: &[u8]) -> Result<Win32Version> {
+    let pe = PeView::from_bytes(image)
+        .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_info(err))?;
+    find_winver_resource(&pe)
+}
+
+#[cfg(feature = "std")]
+fn find_winver_resource(pe: &PeView) -> Result<Win32Version> {
+    let version_info = pe
+        .resources()
+        .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_info(err))?
+        .version_info()
+        .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::NotFound).log_info(err))?;
+    let fixed = version_info.fixed().ok_or_else(|| {
+        Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+            .log_info("version resource does not contain a VS_FIXEDFILEINFO")
+    })?;
+
+    let version = if fixed.dwFileVersion.Major != 0 {
+        fixed.dwFileVersion
+    } else {
+        fixed.dwProductVersion
+    };
+    if version.Major == 0 {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+            .log_info("version resource does not contain a valid version"));
+    }
+
+    Ok(Win32Version::new(
+        version.Major as u32,
+        version.Minor as u32,
+        version.Build as u32,
+    ))
+}
 
-    Ok(version)
+#[cfg(not(feature = "std"))]
+fn find_winver_resource(_pe: &PeView) -> Result<Win32Version> {
+    Err(
+        Error(ErrorOrigin::OsLayer, ErrorKind::UnsupportedOptionalFeature)
+            .log_error("parsing the version resource requires std"),
+    )
 }
@@ -0,0 +1,77 @@
+use std::prelude::v1::*;
+
+use super::pehelper;
+use crate::kernel::StartBlock;
+
+use log::{debug, trace};
+
+use memflow::architecture::arm::aarch64;
+use memflow::dataview::PodMethods;
+use memflow::error::{Error, ErrorKind, ErrorOrigin, PartialResultExt, Result};
+use memflow::mem::{MemoryView, VirtualTranslate};
+use memflow::types::{mem, size, umem, Address};
+
+use pelite::image::IMAGE_DOS_HEADER;
+
+// There is no reliable way of locating the arm64 kernel by scanning the low memory stub.
+// Instead we rely on a va hint (e.g. supplied by the user or the connector) and walk
+// backwards from there until we find the ntoskrnl.exe pe header.
+pub fn find_with_va_hint<T: MemoryView + VirtualTranslate>(
+    virt_mem: &mut T,
+    start_block: &StartBlock,
+) -> Result<(Address, umem)> {
+    if start_block.kernel_hint.is_null() {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::ProcessNotFound)
+            .log_warn("aarch64::find_with_va_hint: a kernel hint is required on aarch64 targets"));
+    }
+
+    debug!(
+        "aarch64::find_with_va_hint: trying to find ntoskrnl.exe with va hint at {:x}",
+        start_block.kernel_hint.to_umem()
+    );
+
+    let mut va_base = start_block.kernel_hint.to_umem() & !0x0001_ffff;
+    while va_base + mem::mb(16) > start_block.kernel_hint.to_umem() {
+        trace!("aarch64::find_with_va_hint: probing at {:x}", va_base);
+
+        match find_with_va(virt_mem, va_base) {
+            Ok(a) => {
+                let addr = Address::from(a);
+                let size_of_image = pehelper::try_get_pe_size(virt_mem, addr)?;
+                return Ok((addr, size_of_image));
+            }
+            Err(e) => trace!("aarch64::find_with_va_hint: probe error {:?}", e),
+        }
+
+        va_base -= mem::mb(2);
+    }
+
+    Err(Error(ErrorOrigin::OsLayer, ErrorKind::ProcessNotFound)
+        .log_trace("aarch64::find_with_va_hint: unable to locate ntoskrnl.exe via va hint"))
+}
+
+fn find_with_va<T: MemoryView + VirtualTranslate>(virt_mem: &mut T, va_base: umem) -> Result<umem> {
+    let mut buf = vec![0; size::mb(2)];
+    virt_mem
+        .read_raw_into(Address::from(va_base), &mut buf)
+        .data_part()?;
+
+    buf.chunks_exact(aarch64::ARCH.page_size())
+        .enumerate()
+        .map(|(i, c)| {
+            let view = PodMethods::as_data_view(c);
+            (i, view.read::<IMAGE_DOS_HEADER>(0))
+        })
+        .filter(|(_, p)| p.e_magic == 0x5a4d) // MZ
+        .filter(|(_, p)| p.e_lfanew <= 0x800)
+        .map(|(i, _)| va_base + i as umem * aarch64::ARCH.page_size() as umem)
+        .find(|&probe_addr| {
+            let name =
+                pehelper::try_get_pe_name(virt_mem, Address::from(probe_addr)).unwrap_or_default();
+            name == "ntoskrnl.exe"
+        })
+        .ok_or_else(|| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::ProcessNotFound)
+                .log_trace("unable to locate ntoskrnl.exe")
+        })
+}
@@ -33,6 +33,29 @@ pub fn try_get_pe_size<T: MemoryView>(mem: &mut T, probe_addr: Address) -> Resul
     }
 }
 
+pub fn try_get_pe_timestamp<T: MemoryView>(mem: &mut T, probe_addr: Address) -> Result<u32> {
+    let mut probe_buf = vec![0; size::kb(4)];
+    mem.read_raw_into(probe_addr, &mut probe_buf)?;
+
+    let pe_probe = PeView::from_bytes(&probe_buf)
+        .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_trace(err))?;
+
+    Ok(pe_probe.file_header().TimeDateStamp)
+}
+
+pub fn try_get_pe_checksum<T: MemoryView>(mem: &mut T, probe_addr: Address) -> Result<u32> {
+    let mut probe_buf = vec![0; size::kb(4)];
+    mem.read_raw_into(probe_addr, &mut probe_buf)?;
+
+    let pe_probe = PeView::from_bytes(&probe_buf)
+        .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_trace(err))?;
+
+    Ok(match pe_probe.optional_header() {
+        pelite::Wrap::T32(opt32) => opt32.CheckSum,
+        pelite::Wrap::T64(opt64) => opt64.CheckSum,
+    })
+}
+
 pub fn try_get_pe_image<T: MemoryView>(mem: &mut T, probe_addr: Address) -> Result<Vec<u8>> {
     let size_of_image = try_get_pe_size(mem, probe_addr)?;
     mem.read_raw(probe_addr, size_of_image.try_into().unwrap())
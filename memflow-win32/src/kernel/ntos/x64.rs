@@ -24,6 +24,15 @@ pub fn find_with_va_hint<T: MemoryView + VirtualTranslate>(
         start_block.kernel_hint.to_umem()
     );
 
+    // the hint usually points directly at the kernel base, validate it before scanning
+    match find_exact(virt_mem, start_block.kernel_hint) {
+        Ok(b) => return Ok(b),
+        Err(e) => trace!(
+            "x64::find_with_va_hint: kernel hint is not the image base {:?}",
+            e
+        ),
+    }
+
     // va was found previously
     let mut va_base = start_block.kernel_hint.to_umem() & !0x0001_ffff;
     while va_base + mem::mb(16) > start_block.kernel_hint.to_umem() {
@@ -45,6 +54,27 @@ pub fn find_with_va_hint<T: MemoryView + VirtualTranslate>(
         .log_trace("x64::find_with_va_hint: unable to locate ntoskrnl.exe via va hint"))
 }
 
+/// Checks if the given address is the base of ntoskrnl.exe without scanning.
+fn find_exact<T: MemoryView>(virt_mem: &mut T, kernel_hint: Address) -> Result<(Address, umem)> {
+    if kernel_hint.to_umem() & (x64::ARCH.page_size() as umem - 1) != 0 {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+            .log_trace("kernel hint is not page aligned"));
+    }
+
+    let name = pehelper::try_get_pe_name(virt_mem, kernel_hint)?;
+    if name != "ntoskrnl.exe" {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::ProcessNotFound)
+            .log_trace("kernel hint does not point to ntoskrnl.exe"));
+    }
+
+    debug!(
+        "x64::find_exact: found ntoskrnl.exe at kernel hint {:x}",
+        kernel_hint.to_umem()
+    );
+    let size_of_image = pehelper::try_get_pe_size(virt_mem, kernel_hint)?;
+    Ok((kernel_hint, size_of_image))
+}
+
 fn find_with_va<T: MemoryView + VirtualTranslate>(virt_mem: &mut T, va_base: umem) -> Result<umem> {
     let mut buf = vec![0; size::mb(2)];
     virt_mem
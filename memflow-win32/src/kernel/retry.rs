@@ -0,0 +1,124 @@
+use std::prelude::v1::*;
+
+use log::{debug, trace};
+
+use memflow::cglue::callback::OpaqueCallback;
+use memflow::cglue::tuple::*;
+use memflow::error::Result;
+use memflow::mem::mem_data::opt_call;
+use memflow::mem::{
+    MemOps, PhysicalMemory, PhysicalMemoryMapping, PhysicalMemoryMetadata, PhysicalReadData,
+    PhysicalReadMemOps, PhysicalWriteMemOps, ReadData,
+};
+use memflow::types::PhysicalAddress;
+
+/// Delay before the first retry, doubled after every failed attempt.
+#[cfg(feature = "std")]
+const INITIAL_BACKOFF_MS: u64 = 1;
+
+/// Upper bound for the delay between two retries.
+#[cfg(feature = "std")]
+const MAX_BACKOFF_MS: u64 = 64;
+
+/// Physical memory wrapper that retries failed reads.
+///
+/// This is only used while detecting the kernel where a single failed read
+/// on unreliable hardware (e.g. DMA devices) would otherwise abort the whole scan.
+/// When `retries` is 0 all reads are forwarded to the underlying connector as-is.
+pub(crate) struct RetryPhysicalMemory<T> {
+    mem: T,
+    retries: usize,
+}
+
+impl<T: PhysicalMemory> RetryPhysicalMemory<T> {
+    pub fn new(mem: T, retries: usize) -> Self {
+        Self { mem, retries }
+    }
+
+    fn read_with_retries(&mut self, addr: PhysicalAddress, buf: &mut [u8]) -> bool {
+        #[cfg(feature = "std")]
+        let mut backoff = INITIAL_BACKOFF_MS;
+
+        for attempt in 0..=self.retries {
+            let mut failed = false;
+            let res = {
+                let mut on_fail = |_: ReadData| {
+                    failed = true;
+                    true
+                };
+                let mut out_fail: OpaqueCallback<ReadData> = (&mut on_fail).into();
+                let op: PhysicalReadData = CTup3(addr, addr.address(), buf.into());
+                MemOps::with_raw(Some(op).into_iter(), None, Some(&mut out_fail), |data| {
+                    self.mem.phys_read_raw_iter(data)
+                })
+            };
+
+            match res {
+                Ok(()) if !failed => return true,
+                Ok(()) => trace!(
+                    "read at {:x} failed (attempt {}/{})",
+                    addr.address(),
+                    attempt + 1,
+                    self.retries + 1
+                ),
+                Err(err) => trace!(
+                    "read at {:x} failed with {:?} (attempt {}/{})",
+                    addr.address(),
+                    err,
+                    attempt + 1,
+                    self.retries + 1
+                ),
+            }
+
+            #[cfg(feature = "std")]
+            if attempt < self.retries {
+                std::thread::sleep(std::time::Duration::from_millis(backoff));
+                backoff = (backoff * 2).min(MAX_BACKOFF_MS);
+            }
+        }
+
+        debug!(
+            "read at {:x} failed after {} attempts",
+            addr.address(),
+            self.retries + 1
+        );
+        false
+    }
+}
+
+impl<T: PhysicalMemory> PhysicalMemory for RetryPhysicalMemory<T> {
+    fn phys_read_raw_iter(
+        &mut self,
+        MemOps {
+            inp,
+            mut out,
+            mut out_fail,
+        }: PhysicalReadMemOps,
+    ) -> Result<()> {
+        if self.retries == 0 {
+            return self.mem.phys_read_raw_iter(MemOps { inp, out, out_fail });
+        }
+
+        for CTup3(addr, meta_addr, mut buf) in inp {
+            if self.read_with_retries(addr, &mut buf) {
+                opt_call(out.as_deref_mut(), CTup2(meta_addr, buf));
+            } else {
+                opt_call(out_fail.as_deref_mut(), CTup2(meta_addr, buf));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn phys_write_raw_iter(&mut self, data: PhysicalWriteMemOps) -> Result<()> {
+        self.mem.phys_write_raw_iter(data)
+    }
+
+    fn metadata(&self) -> PhysicalMemoryMetadata {
+        self.mem.metadata()
+    }
+
+    fn set_mem_map(&mut self, mem_map: &[PhysicalMemoryMapping]) {
+        self.mem.set_mem_map(mem_map)
+    }
+}
@@ -47,22 +47,30 @@ pub fn find_fallback<T: PhysicalMemory>(
     }
 }
 
+/// Recovers the x64 kernel dtb from the low stub (`_PROCESSOR_START_BLOCK`) in the first megabyte of physical memory.
+///
+/// This only works on x64 targets where the first megabyte is not reserved by the firmware.
+pub fn find_lowstub<T: PhysicalMemory>(mem: &mut T) -> Result<StartBlock> {
+    // read low 1mb stub
+    let mut low1m = vec![0; size::mb(1)];
+    mem.phys_read_into(PhysicalAddress::NULL, low1m.as_mut_slice())?;
+
+    let start_block = x64::find_lowstub(&low1m)?;
+    if start_block.dtb.is_null() {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+            .log_warn("x64 lowstub contains an empty dtb"));
+    }
+    Ok(start_block)
+}
+
 // bcdedit /set firstmegabytepolicyuseall
 pub fn find<T: PhysicalMemory>(mem: &mut T, arch: Option<ArchitectureIdent>) -> Result<StartBlock> {
     if let Some(arch) = arch {
         match arch {
             ArchitectureIdent::X86(64, _) => {
-                // read low 1mb stub
-                let mut low1m = vec![0; size::mb(1)];
-                mem.phys_read_into(PhysicalAddress::NULL, low1m.as_mut_slice())?;
-
                 // find x64 dtb in low stub < 1M
-                match x64::find_lowstub(&low1m) {
-                    Ok(d) => {
-                        if d.dtb.to_umem() != 0 {
-                            return Ok(d);
-                        }
-                    }
+                match find_lowstub(mem) {
+                    Ok(d) => return Ok(d),
                     Err(e) => warn!("x64::find_lowstub() error: {}", e),
                 }
 
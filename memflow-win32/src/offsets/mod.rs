@@ -2,6 +2,11 @@ pub use memflow_win32_defs::offsets::*;
 
 use crate::prelude::v1::*;
 
+#[cfg(feature = "offset_store")]
+pub mod store;
+#[cfg(feature = "offset_store")]
+pub use store::OffsetStore;
+
 #[repr(align(16))]
 struct Align16<T>(pub T);
 
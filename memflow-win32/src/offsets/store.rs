@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+use std::prelude::v1::*;
+
+use log::{info, warn};
+
+use memflow::error::{Error, ErrorKind, ErrorOrigin, Result};
+
+use super::{Win32OffsetFile, Win32Offsets, Win32OffsetsArchitecture};
+use crate::kernel::Win32Guid;
+
+/// In-memory collection of offset files loaded at runtime.
+///
+/// This allows offsets to be maintained outside of the embedded offset database.
+/// Files are indexed by their pdb guid and by their architecture and build number.
+/// When multiple files share a guid or build the file that was added last wins,
+/// so directories can be layered on top of each other.
+#[derive(Debug, Clone, Default)]
+pub struct OffsetStore {
+    files: Vec<Win32OffsetFile>,
+    by_guid: BTreeMap<String, usize>,
+    by_build: BTreeMap<(u32, u32), usize>,
+}
+
+impl OffsetStore {
+    /// Creates a new empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new store containing all offset files of the given directory.
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut store = Self::new();
+        store.load_dir(path)?;
+        Ok(store)
+    }
+
+    /// Loads all `.toml` offset files of the given directory into the store.
+    ///
+    /// Files that cannot be parsed are skipped with a warning.
+    /// Returns the number of files that were loaded.
+    pub fn load_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        let mut paths = fs::read_dir(path.as_ref())
+            .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::UnableToReadDir).log_error(err))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().map_or(false, |ext| ext == "toml"))
+            .collect::<Vec<_>>();
+        // load files in a deterministic order so layering is reproducible
+        paths.sort();
+
+        let mut count = 0;
+        for path in paths.iter() {
+            let loaded = fs::read_to_string(path)
+                .map_err(|err| {
+                    Error(ErrorOrigin::OsLayer, ErrorKind::UnableToReadFile).log_warn(err)
+                })
+                .and_then(|content| self.load_str(&content));
+            match loaded {
+                Ok(()) => count += 1,
+                Err(err) => warn!("unable to load offset file {:?}: {}", path, err),
+            }
+        }
+
+        info!(
+            "loaded {} offset files from {:?}",
+            count,
+            path.as_ref().display()
+        );
+        Ok(count)
+    }
+
+    /// Parses a single offset file in the TOML format and adds it to the store.
+    ///
+    /// If the file contains a `[secondary]` block the offsets of both architectures are added.
+    /// Returns `ErrorKind::VersionMismatch` if the file uses a different schema version.
+    pub fn load_str(&mut self, content: &str) -> Result<()> {
+        for file in Win32OffsetFile::from_toml_all(content)? {
+            self.insert(file);
+        }
+        Ok(())
+    }
+
+    /// Adds an offset file to the store, overriding entries with the same guid or build.
+    pub fn insert(&mut self, file: Win32OffsetFile) {
+        let idx = self.files.len();
+
+        if let Ok(guid) = <&str>::try_from(&file.header.pdb_guid) {
+            if !guid.is_empty() {
+                self.by_guid.insert(guid.to_uppercase(), idx);
+            }
+        }
+        self.by_build
+            .insert((file.header.arch as u32, file.header.nt_build_number), idx);
+
+        self.files.push(file);
+    }
+
+    /// Returns the offsets for the given pdb guid.
+    pub fn lookup(&self, guid: &Win32Guid) -> Option<Win32Offsets> {
+        self.by_guid
+            .get(&guid.guid.to_uppercase())
+            .map(|&idx| Win32Offsets(self.files[idx].offsets))
+    }
+
+    /// Returns the offsets for the exact build number and architecture.
+    pub fn lookup_by_build(
+        &self,
+        arch: Win32OffsetsArchitecture,
+        build: u32,
+    ) -> Option<Win32Offsets> {
+        self.by_build
+            .get(&(arch as u32, build))
+            .map(|&idx| Win32Offsets(self.files[idx].offsets))
+    }
+
+    /// Returns all offset files of the store in the order they were added.
+    pub fn files(&self) -> &[Win32OffsetFile] {
+        &self.files
+    }
+
+    /// Returns the number of offset files in the store.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if the store does not contain any offset files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFSET_FILE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/offsets/10_0_18362_X64_0AFB69F5FD264D54673570E37B38A3181.toml"
+    ));
+
+    #[test]
+    fn lookup_offsets() {
+        let mut store = OffsetStore::new();
+        store.load_str(OFFSET_FILE).unwrap();
+        assert_eq!(store.len(), 1);
+
+        let guid = Win32Guid::new("ntkrnlmp.pdb", "0afb69f5fd264d54673570e37b38a3181");
+        assert_eq!(store.lookup(&guid).unwrap().0.eproc_pid, 744);
+        assert!(store.lookup(&Win32Guid::new("ntkrnlmp.pdb", "0")).is_none());
+
+        assert!(store
+            .lookup_by_build(Win32OffsetsArchitecture::X64, 18362)
+            .is_some());
+        assert!(store
+            .lookup_by_build(Win32OffsetsArchitecture::X86, 18362)
+            .is_none());
+
+        // later files override earlier ones
+        store
+            .load_str(&OFFSET_FILE.replace("eproc_pid = 744", "eproc_pid = 1"))
+            .unwrap();
+        assert_eq!(store.lookup(&guid).unwrap().0.eproc_pid, 1);
+
+        // files with a different schema are rejected
+        assert!(store
+            .load_str(&OFFSET_FILE.replace("[offsets]", "schema_version = 99\n\n[offsets]"))
+            .is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::kernel::Win32Version;
 use crate::offsets::SymbolStore;
 use crate::win32::{Win32Kernel, Win32KernelBuilder};
 
@@ -28,14 +29,36 @@ fn build_final<
     C: 'static + VirtualTranslate2 + Clone,
 >(
     kernel_builder: Win32KernelBuilder<A, B, C>,
-    _: &Args,
+    args: &Args,
     lib: LibArc,
 ) -> Result<OsInstanceArcBox<'static>> {
     log::info!(
         "Building kernel of type {}",
         std::any::type_name::<Win32KernelBuilder<A, B, C>>()
     );
-    let kernel = kernel_builder.build()?;
+    let mut kernel = kernel_builder.build()?;
+
+    // keyboard session and proxy process selection
+    if let Some(session_id) = args.get("kbd_session") {
+        let session_id = session_id.parse::<u32>().map_err(|_| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::ArgValidation)
+                .log_error("kbd_session must be a session id")
+        })?;
+        kernel.keyboard_source = kernel.keyboard_source.session_id(session_id);
+    }
+    if let Some(proxy) = args.get("kbd_proxy") {
+        kernel.keyboard_source = kernel.keyboard_source.proxy_processes(&[proxy]);
+    }
+
+    // memory regions reported by Process::mapped_mem_range
+    match args.get("mapped_mem") {
+        Some("vad") => kernel.set_mapped_mem_vad(true),
+        Some("pages") | None => {}
+        Some(_) => {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::ArgValidation)
+                .log_error("mapped_mem must be either vad or pages"))
+        }
+    }
     Ok(group_obj!((kernel, lib) as OsInstance))
 }
 
@@ -94,11 +117,57 @@ fn build_kernel_hint<
         .get("kernel_hint")
         .and_then(|d| u64::from_str_radix(d, 16).ok())
     {
-        Some(dtb) => build_symstore(builder.kernel_hint(Address::from(dtb)), args, lib),
+        Some(dtb) => build_kernel_base(builder.kernel_hint(Address::from(dtb)), args, lib),
+        _ => build_kernel_base(builder, args, lib),
+    }
+}
+
+fn build_kernel_base<
+    A: 'static + PhysicalMemory + Clone,
+    B: 'static + PhysicalMemory + Clone,
+    C: 'static + VirtualTranslate2 + Clone,
+>(
+    builder: Win32KernelBuilder<A, B, C>,
+    args: &Args,
+    lib: LibArc,
+) -> Result<OsInstanceArcBox<'static>> {
+    match args
+        .get("kernel_base")
+        .and_then(|b| u64::from_str_radix(b, 16).ok())
+    {
+        Some(base) => build_winver(builder.kernel_base(Address::from(base)), args, lib),
+        _ => build_winver(builder, args, lib),
+    }
+}
+
+fn build_winver<
+    A: 'static + PhysicalMemory + Clone,
+    B: 'static + PhysicalMemory + Clone,
+    C: 'static + VirtualTranslate2 + Clone,
+>(
+    builder: Win32KernelBuilder<A, B, C>,
+    args: &Args,
+    lib: LibArc,
+) -> Result<OsInstanceArcBox<'static>> {
+    match args.get("winver").map(parse_winver).transpose()? {
+        Some(winver) => build_symstore(builder.winver(winver), args, lib),
         _ => build_symstore(builder, args, lib),
     }
 }
 
+/// Parses a windows version in the form of `major.minor.build` (e.g. `10.0.19041`)
+fn parse_winver(winver: &str) -> Result<Win32Version> {
+    let parts = winver
+        .split('.')
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>();
+    match parts.as_deref() {
+        Some([major, minor, build]) => Ok(Win32Version::new(*major, *minor, *build)),
+        _ => Err(Error(ErrorOrigin::OsLayer, ErrorKind::ArgValidation)
+            .log_error("winver must be in the form of major.minor.build")),
+    }
+}
+
 fn build_vat<
     A: 'static + PhysicalMemory + Clone,
     B: 'static + PhysicalMemory + Clone,
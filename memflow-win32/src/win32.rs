@@ -4,16 +4,28 @@ pub mod kernel_info;
 
 pub use kernel::Win32Kernel;
 pub use kernel_builder::Win32KernelBuilder;
-pub use kernel_info::Win32KernelInfo;
+pub use kernel_info::{DetectionStage, DetectionStageCallback, Win32KernelInfo};
 
+pub mod env;
 pub mod keyboard;
 pub mod module;
+pub mod object;
+pub mod pool;
 pub mod process;
+pub mod registry;
+pub mod scan;
+pub mod shared_user_data;
+pub mod sigscan;
 pub mod unicode_string;
 pub mod vat;
 
+pub use env::*;
 pub use keyboard::*;
 pub use module::*;
+pub use object::*;
+pub use pool::*;
 pub use process::*;
+pub use registry::*;
+pub use shared_user_data::*;
 pub use unicode_string::*;
 pub use vat::*;
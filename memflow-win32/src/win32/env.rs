@@ -0,0 +1,211 @@
+use std::prelude::v1::*;
+
+use crate::offsets::Win32ArchOffsets;
+
+use log::trace;
+
+use memflow::architecture::ArchitectureIdent;
+use memflow::cglue::callback::OpaqueCallback;
+use memflow::error::{Error, ErrorKind, ErrorOrigin, PartialResultExt, Result};
+use memflow::mem::MemoryView;
+use memflow::types::{mem, umem, Address};
+
+/// Default upper bound for the size of an environment block
+pub const MAX_ENV_BLOCK_SIZE: umem = mem::mb(1);
+
+/// A single environment variable of a process
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct EnvVarInfo {
+    pub name: String,
+    pub value: String,
+}
+
+pub type EnvVarCallback<'a> = OpaqueCallback<'a, EnvVarInfo>;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32EnvListInfo {
+    env_block: Address,
+    env_size: umem,
+}
+
+impl Win32EnvListInfo {
+    pub fn with_peb(
+        mem: &mut impl MemoryView,
+        peb: Address,
+        arch: ArchitectureIdent,
+    ) -> Result<Self> {
+        Self::with_peb_max_size(mem, peb, arch, MAX_ENV_BLOCK_SIZE)
+    }
+
+    /// Reads the environment block information from the peb.
+    ///
+    /// Returns an error if the environment block is larger than `max_env_size`.
+    pub fn with_peb_max_size(
+        mem: &mut impl MemoryView,
+        peb: Address,
+        arch: ArchitectureIdent,
+        max_env_size: umem,
+    ) -> Result<Self> {
+        let offsets = Win32ArchOffsets::from(arch);
+        let arch_obj = arch.into();
+
+        let process_params = mem.read_addr_arch(arch_obj, peb + offsets.peb_process_params)?;
+        trace!("peb_process_params={:x}", process_params);
+
+        let env_block = mem.read_addr_arch(arch_obj, process_params + offsets.ppm_environment)?;
+        trace!("env_block={:x}", env_block);
+
+        let env_size = mem
+            .read_addr_arch(arch_obj, process_params + offsets.ppm_environment_size)?
+            .to_umem();
+        trace!("env_size={:x}", env_size);
+
+        if env_size > max_env_size {
+            return Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument).log_info(format!(
+                    "environment block size {:x} exceeds the maximum of {:x}",
+                    env_size, max_env_size
+                )),
+            );
+        }
+
+        Ok(Win32EnvListInfo {
+            env_block,
+            env_size,
+        })
+    }
+
+    pub fn env_block(&self) -> Address {
+        self.env_block
+    }
+
+    pub fn env_size(&self) -> umem {
+        self.env_size
+    }
+
+    pub fn envar_list(&self, mem: &mut impl MemoryView) -> Result<Vec<EnvVarInfo>> {
+        let mut out = vec![];
+        self.envar_list_callback(mem, (&mut out).into())?;
+        Ok(out)
+    }
+
+    pub fn envar_list_callback(
+        &self,
+        mem: &mut impl MemoryView,
+        mut callback: EnvVarCallback,
+    ) -> Result<()> {
+        let buf = mem
+            .read_raw(self.env_block, self.env_size as usize)
+            .data_part()?;
+        env_block_list_utf16_callback(&buf, &mut callback);
+        Ok(())
+    }
+
+    /// Retrieves the value of the environment variable with the given name
+    ///
+    /// Variable names are compared case-insensitively. The environment block is only parsed
+    /// up to the matching variable.
+    pub fn envar(&self, mem: &mut impl MemoryView, name: &str) -> Result<Option<String>> {
+        let buf = mem
+            .read_raw(self.env_block, self.env_size as usize)
+            .data_part()?;
+        Ok(env_block_find_utf16(&buf, name))
+    }
+}
+
+/// Returns the value of the first variable in a utf16 encoded environment block matching `name`
+fn env_block_find_utf16(buf: &[u8], name: &str) -> Option<String> {
+    let mut value = None;
+    let callback = &mut |envar: EnvVarInfo| {
+        if envar.name.eq_ignore_ascii_case(name) {
+            value = Some(envar.value);
+            false
+        } else {
+            true
+        }
+    };
+    env_block_list_utf16_callback(buf, &mut callback.into());
+    value
+}
+
+/// Parses a utf16 encoded environment block (`NAME=VALUE\0NAME=VALUE\0\0`)
+fn env_block_list_utf16_callback(buf: &[u8], callback: &mut EnvVarCallback) {
+    let wide = buf
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect::<Vec<_>>();
+
+    for entry in wide.split(|&c| c == 0) {
+        // the block is terminated by an empty entry
+        if entry.is_empty() {
+            break;
+        }
+
+        let entry = String::from_utf16_lossy(entry);
+        // hidden variables (e.g. `=C:=C:\`) start with an equal sign that is part of the name
+        let (name, value) = match entry.char_indices().skip(1).find(|(_, c)| *c == '=') {
+            Some((i, _)) => (&entry[..i], &entry[i + 1..]),
+            None => (entry.as_str(), ""),
+        };
+
+        let envar = EnvVarInfo {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        if !callback.call(envar) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_block(entries: &[&str]) -> Vec<u8> {
+        let mut block = entries.join("\0");
+        block.push_str("\0\0");
+        block.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn envar_lookup() {
+        let buf = env_block(&["=C:=C:\\Windows", "Path=C:\\Windows", "TEMP=C:\\Temp"]);
+
+        assert_eq!(
+            env_block_find_utf16(&buf, "TEMP").as_deref(),
+            Some("C:\\Temp")
+        );
+        assert_eq!(
+            env_block_find_utf16(&buf, "PATH").as_deref(),
+            Some("C:\\Windows")
+        );
+        assert_eq!(
+            env_block_find_utf16(&buf, "=c:").as_deref(),
+            Some("C:\\Windows")
+        );
+        assert_eq!(env_block_find_utf16(&buf, "TMP"), None);
+        assert_eq!(env_block_find_utf16(&[], "TEMP"), None);
+    }
+
+    #[test]
+    fn envar_early_termination() {
+        // the first match wins and nothing after the terminating empty entry is parsed
+        let mut buf = env_block(&["A=1", "a=2"]);
+        buf.extend(env_block(&["B=3"]));
+
+        assert_eq!(env_block_find_utf16(&buf, "A").as_deref(), Some("1"));
+        assert_eq!(env_block_find_utf16(&buf, "B"), None);
+
+        let mut visited = 0;
+        let callback = &mut |envar: EnvVarInfo| {
+            visited += 1;
+            envar.name != "A"
+        };
+        env_block_list_utf16_callback(&buf, &mut callback.into());
+        assert_eq!(visited, 1);
+    }
+}
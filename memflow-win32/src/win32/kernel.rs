@@ -1,13 +1,25 @@
 mod mem_map;
 
 use crate::{
+    kernel::Win32Version,
     offsets::{Win32ArchOffsets, Win32Offsets},
     prelude::{VirtualReadUnicodeString, Win32ExitStatus, EXIT_STATUS_STILL_ACTIVE},
 };
 
 use super::{
-    process::IMAGE_FILE_NAME_LENGTH, Win32KernelBuilder, Win32KernelInfo, Win32Keyboard,
-    Win32ModuleListInfo, Win32Process, Win32ProcessInfo, Win32VirtualTranslate,
+    module::resolve_driver_path,
+    process::IMAGE_FILE_NAME_LENGTH,
+    registry::{
+        parse_big_data, parse_cell_list, KeyNode, KeyValue, SubkeyList, CM_KEY_VALUE_BIG,
+        CM_KEY_VALUE_SPECIAL_SIZE,
+    },
+    scan::{scan_rip_relative, PlainScanner},
+    shared_user_data::kuser_shared_data_address,
+    RegValue, Win32Driver, Win32Hive, Win32KernelBuilder, Win32KernelInfo, Win32KernelModule,
+    Win32KernelModuleCallback, Win32KeyStateSource, Win32Keyboard, Win32ModuleListInfo,
+    Win32ObjectDirEntry, Win32PoolEntry, Win32PoolEntryCallback, Win32Process,
+    Win32ProcessBasicInfo, Win32ProcessInfo, Win32ProcessSortBy, Win32RegKey, Win32SharedUserData,
+    Win32VirtualTranslate,
 };
 
 use memflow::mem::virt_translate::*;
@@ -20,7 +32,8 @@ use memflow::mem::{memory_view::*, phys_mem::*};
 #[cfg(feature = "plugins")]
 use memflow::os::keyboard::*;
 
-use log::{info, trace};
+use log::{info, trace, warn};
+use std::collections::BTreeSet;
 use std::convert::TryInto;
 use std::fmt;
 use std::prelude::v1::*;
@@ -29,6 +42,29 @@ use pelite::{self, pe64::exports::Export, PeView};
 
 const MAX_ITER_COUNT: usize = 65536;
 
+/// Offset of KUSER_SHARED_DATA::NtSystemRoot
+const KUSER_NT_SYSTEM_ROOT: umem = 0x30;
+const NT_SYSTEM_ROOT_LENGTH: usize = 260;
+
+/// Upper bound for the size of a single registry cell
+const MAX_REGISTRY_CELL_SIZE: usize = 0x100000;
+
+/// Number of gate descriptors in the interrupt descriptor table
+const IDT_ENTRY_COUNT: usize = 256;
+
+/// Number of hash buckets in an _OBJECT_DIRECTORY
+const OBJECT_DIRECTORY_BUCKETS: usize = 37;
+
+/// _OBJECT_HEADER::InfoMask flags
+const OB_INFO_MASK_CREATOR_INFO: u8 = 0x1;
+const OB_INFO_MASK_NAME_INFO: u8 = 0x2;
+
+/// Upper bound for the number of entries in the PoolBigPageTable
+const MAX_BIG_POOL_TABLE_SIZE: usize = 0x20_0000;
+
+/// Number of PoolBigPageTable entries that are read at once
+const BIG_POOL_CHUNK_ENTRIES: usize = 0x1000;
+
 #[cfg(feature = "plugins")]
 cglue_impl_group!(Win32Kernel<T, V>, OsInstance<'a>, { PhysicalMemory, MemoryView, VirtualTranslate, OsKeyboard });
 
@@ -41,6 +77,21 @@ pub struct Win32Kernel<T, V> {
     pub sysproc_dtb: Address,
 
     pub kernel_modules: Option<Win32ModuleListInfo>,
+
+    /// Cached image of ntoskrnl.exe used for export lookups
+    kernel_image: Option<Vec<u8>>,
+
+    /// Location of the key state used by `keyboard()` and `into_keyboard()`
+    pub keyboard_source: Win32KeyStateSource,
+
+    /// Maximum number of entries visited when walking the process list
+    max_process_iter: usize,
+
+    /// Abort the process info enumeration on the first entry that cannot be read
+    strict_process_list: bool,
+
+    /// Report the VAD regions of processes in `Process::mapped_mem_range`
+    pub(crate) mapped_mem_vad: bool,
 }
 
 impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone>
@@ -55,42 +106,33 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
         );
 
         if offsets.phys_mem_block() != 0 {
-            match kernel_info.os_info.arch.into_obj().bits() {
-                32 => {
-                    if let Some(mem_map) = mem_map::parse::<_, u32>(
-                        &mut virt_mem,
-                        kernel_info.os_info.base + offsets.phys_mem_block(),
-                    ) {
-                        // update mem mapping in connector
-                        info!("updating connector mem_map={:?}", mem_map);
-                        let (mut phys_mem, vat) = virt_mem.into_inner();
-                        phys_mem.set_mem_map(mem_map.into_vec().as_slice());
-                        virt_mem = VirtualDma::with_vat(
-                            phys_mem,
-                            kernel_info.os_info.arch,
-                            Win32VirtualTranslate::new(kernel_info.os_info.arch, kernel_info.dtb),
-                            vat,
-                        );
-                    }
+            let descriptor_ptr_ptr = kernel_info.os_info.base + offsets.phys_mem_block();
+            let mem_map = match kernel_info.os_info.arch.into_obj().bits() {
+                32 => Some(mem_map::parse::<_, u32>(&mut virt_mem, descriptor_ptr_ptr)),
+                64 => Some(mem_map::parse::<_, u64>(&mut virt_mem, descriptor_ptr_ptr)),
+                _ => None,
+            };
+
+            match mem_map {
+                Some(Ok(mem_map)) => {
+                    // update mem mapping in connector
+                    info!("updating connector mem_map={:?}", mem_map);
+                    let (mut phys_mem, vat) = virt_mem.into_inner();
+                    phys_mem.set_mem_map(mem_map.into_vec().as_slice());
+                    virt_mem = VirtualDma::with_vat(
+                        phys_mem,
+                        kernel_info.os_info.arch,
+                        Win32VirtualTranslate::new(kernel_info.os_info.arch, kernel_info.dtb),
+                        vat,
+                    );
                 }
-                64 => {
-                    if let Some(mem_map) = mem_map::parse::<_, u64>(
-                        &mut virt_mem,
-                        kernel_info.os_info.base + offsets.phys_mem_block(),
-                    ) {
-                        // update mem mapping in connector
-                        info!("updating connector mem_map={:?}", mem_map);
-                        let (mut phys_mem, vat) = virt_mem.into_inner();
-                        phys_mem.set_mem_map(mem_map.into_vec().as_slice());
-                        virt_mem = VirtualDma::with_vat(
-                            phys_mem,
-                            kernel_info.os_info.arch,
-                            Win32VirtualTranslate::new(kernel_info.os_info.arch, kernel_info.dtb),
-                            vat,
-                        );
-                    }
+                Some(Err(err)) => {
+                    warn!(
+                        "unable to parse phys_mem_block, using fallback memory mappings: {}",
+                        err
+                    );
                 }
-                _ => {}
+                None => {}
             }
         }
 
@@ -126,6 +168,13 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
             kernel_info,
             sysproc_dtb,
             kernel_modules: None,
+            kernel_image: None,
+
+            keyboard_source: Win32KeyStateSource::default(),
+
+            max_process_iter: MAX_ITER_COUNT,
+            strict_process_list: false,
+            mapped_mem_vad: false,
         }
     }
 
@@ -133,23 +182,7 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
         if let Some(info) = self.kernel_modules {
             Ok(info)
         } else {
-            let image = self.virt_mem.read_raw(
-                self.kernel_info.os_info.base,
-                self.kernel_info.os_info.size.try_into().unwrap(),
-            )?;
-            let pe = PeView::from_bytes(&image).map_err(|err| {
-                Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_info(err)
-            })?;
-            let addr = match pe.get_export_by_name("PsLoadedModuleList").map_err(|err| {
-                Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound).log_info(err)
-            })? {
-                Export::Symbol(s) => self.kernel_info.os_info.base + *s as umem,
-                Export::Forward(_) => {
-                    return Err(Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound)
-                        .log_info("PsLoadedModuleList found but it was a forwarded export"))
-                }
-            };
-
+            let addr = self.kernel_export("PsLoadedModuleList")?;
             let addr = self
                 .virt_mem
                 .read_addr_arch(self.kernel_info.os_info.arch.into(), addr)?;
@@ -161,6 +194,1328 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
         }
     }
 
+    /// Walks the PsLoadedModuleList and calls the provided callback for each kernel module
+    ///
+    /// In contrast to `module_list_callback` this also yields the load order index
+    /// and the `_KLDR_DATA_TABLE_ENTRY::TimeDateStamp` of each module.
+    /// If the TimeDateStamp offset is not available the timestamp is read from the module's pe header instead.
+    pub fn kernel_module_list_callback(
+        &mut self,
+        mut callback: Win32KernelModuleCallback,
+    ) -> Result<()> {
+        let kernel_modules = self.kernel_modules()?;
+        let arch = self.kernel_info.os_info.arch;
+
+        let mut entries = vec![];
+        kernel_modules
+            .module_entry_list_callback::<Self, VirtualDma<T, V, Win32VirtualTranslate>>(
+                self,
+                arch,
+                (&mut entries).into(),
+            )?;
+
+        for (load_order, entry) in entries.into_iter().enumerate() {
+            let info = kernel_modules.module_info_from_entry(
+                entry,
+                self.kernel_info.eprocess_base,
+                &mut self.virt_mem,
+                arch,
+            )?;
+
+            let time_date_stamp = if self.offsets.kldr_time_date_stamp() != 0 {
+                self.virt_mem
+                    .read::<u32>(entry + self.offsets.kldr_time_date_stamp())?
+            } else {
+                crate::kernel::ntos::pehelper::try_get_pe_timestamp(&mut self.virt_mem, info.base)
+                    .unwrap_or_default()
+            };
+            trace!("time_date_stamp={:x}", time_date_stamp);
+
+            let module = Win32KernelModule {
+                load_order,
+                time_date_stamp,
+                info,
+            };
+            if !callback.call(module) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a list of all kernel modules in the PsLoadedModuleList
+    ///
+    /// See `kernel_module_list_callback` for details.
+    pub fn kernel_module_list(&mut self) -> Result<Vec<Win32KernelModule>> {
+        let mut out = vec![];
+        self.kernel_module_list_callback((&mut out).into())?;
+        Ok(out)
+    }
+
+    /// Returns the windows directory of the target (e.g. `C:\Windows`)
+    ///
+    /// The directory is read from KUSER_SHARED_DATA::NtSystemRoot.
+    pub fn system_root(&mut self) -> Result<String> {
+        let system_root_addr =
+            kuser_shared_data_address(self.kernel_info.os_info.arch) + KUSER_NT_SYSTEM_ROOT;
+        let mut buf = [0u8; NT_SYSTEM_ROOT_LENGTH * 2];
+        self.virt_mem
+            .read_raw_into(system_root_addr, &mut buf)
+            .data_part()?;
+
+        let wide = buf
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect::<Vec<_>>();
+        let system_root = String::from_utf16(&wide).map_err(|_| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::Encoding)
+                .log_info("unable to decode KUSER_SHARED_DATA::NtSystemRoot")
+        })?;
+
+        if system_root.is_empty() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("KUSER_SHARED_DATA::NtSystemRoot is empty"));
+        }
+
+        Ok(system_root)
+    }
+
+    /// Returns a list of all loaded kernel drivers with their resolved file paths
+    ///
+    /// The paths are read from `_KLDR_DATA_TABLE_ENTRY::FullDllName` and `\SystemRoot`
+    /// is replaced by the windows directory of the target.
+    /// If the windows directory cannot be read `C:\Windows` is assumed.
+    pub fn driver_list(&mut self) -> Result<Vec<Win32Driver>> {
+        let system_root = self.system_root().unwrap_or_else(|err| {
+            warn!("unable to read system root, assuming C:\\Windows: {}", err);
+            "C:\\Windows".to_string()
+        });
+
+        Ok(self
+            .kernel_module_list()?
+            .into_iter()
+            .map(|module| {
+                let raw_path = module.info.path.to_string();
+                Win32Driver {
+                    base: module.info.base,
+                    size: module.info.size,
+                    name: module.info.name.to_string(),
+                    path: resolve_driver_path(&raw_path, &system_root),
+                    raw_path,
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the names of all object types registered in the ObTypeIndexTable
+    ///
+    /// The returned list is indexed by the object type index (e.g. the TypeIndex of a handle).
+    /// The first two entries of the table are reserved and are returned as empty strings.
+    pub fn object_types(&mut self) -> Result<Vec<String>> {
+        if self.offsets.ob_type_index_table() == 0 || self.offsets.object_type_name() == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("ObTypeIndexTable or _OBJECT_TYPE::Name offset not available"));
+        }
+
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let ptr_size = ArchitectureObj::from(self.kernel_info.os_info.arch).size_addr();
+        let table = self.kernel_info.os_info.base + self.offsets.ob_type_index_table();
+        trace!("ob_type_index_table={:x}", table);
+
+        let mut out = vec![String::new(), String::new()];
+        // the type index is stored as a single byte in the object header
+        for i in 2..256 {
+            let object_type = self
+                .virt_mem
+                .read_addr_arch(arch_obj, table + i * ptr_size)?;
+            if object_type.is_null() {
+                break;
+            }
+
+            let name = self
+                .virt_mem
+                .read_unicode_string(arch_obj, object_type + self.offsets.object_type_name())?;
+            trace!("object_type[{}]={:x} {}", i, object_type, name);
+            out.push(name);
+        }
+
+        Ok(out)
+    }
+
+    /// Returns the image of ntoskrnl.exe
+    ///
+    /// The image is read on the first call and cached until `invalidate_kernel_image` is called.
+    fn kernel_image(&mut self) -> Result<&[u8]> {
+        if self.kernel_image.is_none() {
+            // discarded and paged out sections are zero filled
+            let image = self
+                .virt_mem
+                .read_raw(
+                    self.kernel_info.os_info.base,
+                    self.kernel_info.os_info.size.try_into().unwrap(),
+                )
+                .data_part()?;
+            self.kernel_image = Some(image);
+        }
+        Ok(self.kernel_image.as_deref().unwrap_or_default())
+    }
+
+    /// Drops the cached image of ntoskrnl.exe
+    ///
+    /// The image will be read again on the next export lookup.
+    pub fn invalidate_kernel_image(&mut self) {
+        self.kernel_image = None;
+    }
+
+    /// Returns the kernel version stored in the VS_VERSIONINFO resource of ntoskrnl.exe
+    ///
+    /// In contrast to the version in `kernel_info` this does not rely on NtBuildNumber
+    /// or KUSER_SHARED_DATA and only requires the (cached) kernel image.
+    pub fn version_from_resource(&mut self) -> Result<Win32Version> {
+        let image = self.kernel_image()?;
+        crate::kernel::ntos::find_winver_from_resource(image)
+    }
+
+    /// Resolves an export of ntoskrnl.exe and returns its virtual address
+    ///
+    /// The kernel image is read once and cached for subsequent lookups.
+    /// Forwarded exports cannot be resolved and result in `ErrorKind::ExportNotFound`.
+    pub fn kernel_export(&mut self, name: &str) -> Result<Address> {
+        let base = self.kernel_info.os_info.base;
+        let image = self.kernel_image()?;
+
+        let pe = PeView::from_bytes(image)
+            .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_info(err))?;
+        match pe
+            .get_export_by_name(name)
+            .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound).log_info(err))?
+        {
+            Export::Symbol(s) => Ok(base + *s as umem),
+            Export::Forward(_) => Err(Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound)
+                .log_info(format!("{} found but it was a forwarded export", name))),
+        }
+    }
+
+    /// Returns the mapping of DOS device names to NT device paths
+    ///
+    /// The symbolic links in the `\GLOBAL??` object directory are resolved,
+    /// e.g. `C:` is mapped to `\Device\HarddiskVolume3`.
+    pub fn dos_device_map(&mut self) -> Result<Vec<(String, String)>> {
+        if self.offsets.symbolic_link_target() == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("_OBJECT_SYMBOLIC_LINK::LinkTarget offset not available"));
+        }
+
+        let object_types = self.object_types()?;
+        let directory = self.find_object_directory("\\GLOBAL??")?;
+
+        let mut links = vec![];
+        self.object_directory_walk(directory, &mut |entry| {
+            if object_types
+                .get(entry.type_index as usize)
+                .map(String::as_str)
+                == Some("SymbolicLink")
+            {
+                links.push((entry.name, entry.object_address));
+            }
+            true
+        })?;
+
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        Ok(links
+            .into_iter()
+            .filter_map(|(name, object)| {
+                self.virt_mem
+                    .read_unicode_string(arch_obj, object + self.offsets.symbolic_link_target())
+                    .ok()
+                    .map(|target| (name, target))
+            })
+            .collect())
+    }
+
+    /// Returns the names of the window stations in the `\Windows\WindowStations` object directory
+    ///
+    /// This directory contains the window stations of session 0 (e.g. `WinSta0` and `Service-0x0-3e7$`).
+    /// Window stations of interactive sessions are located in `\Sessions\<id>\Windows\WindowStations`
+    /// and can be listed with `object_directory`.
+    pub fn window_stations(&mut self) -> Result<Vec<String>> {
+        let object_types = self.object_types()?;
+        Ok(self
+            .object_directory("\\Windows\\WindowStations")?
+            .into_iter()
+            .filter(|entry| {
+                object_types
+                    .get(entry.type_index as usize)
+                    .map(String::as_str)
+                    == Some("WindowStation")
+            })
+            .map(|entry| entry.name)
+            .collect())
+    }
+
+    /// Resolves an object directory by its path (e.g. `\GLOBAL??`) starting at the ObpRootDirectoryObject
+    fn find_object_directory(&mut self, path: &str) -> Result<Address> {
+        if self.offsets.obp_root_directory_object() == 0 || !self.object_header_offsets_valid() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("ObpRootDirectoryObject or _OBJECT_HEADER offsets not available"));
+        }
+
+        let mut directory = self.virt_mem.read_addr_arch(
+            self.kernel_info.os_info.arch.into(),
+            self.kernel_info.os_info.base + self.offsets.obp_root_directory_object(),
+        )?;
+        trace!("obp_root_directory_object={:x}", directory);
+
+        for component in path.split('\\').filter(|c| !c.is_empty()) {
+            let mut next = None;
+            self.object_directory_walk(directory, &mut |entry| {
+                if entry.name.eq_ignore_ascii_case(component) {
+                    next = Some(entry.object_address);
+                    false
+                } else {
+                    true
+                }
+            })?;
+            directory = next.ok_or_else(|| {
+                Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                    .log_info(format!("unable to find object directory {}", path))
+            })?;
+        }
+
+        Ok(directory)
+    }
+
+    /// Returns all objects in the object directory with the given path
+    ///
+    /// The path is resolved starting at the ObpRootDirectoryObject, e.g. `\Driver` or `\GLOBAL??`.
+    /// An empty path or `\` returns the entries of the root directory.
+    pub fn object_directory(&mut self, path: &str) -> Result<Vec<Win32ObjectDirEntry>> {
+        let directory = self.find_object_directory(path)?;
+        let mut out = vec![];
+        self.object_directory_walk(directory, &mut |entry| {
+            out.push(entry);
+            true
+        })?;
+        Ok(out)
+    }
+
+    /// Walks all hash buckets of an _OBJECT_DIRECTORY and calls the callback for each object
+    fn object_directory_walk(
+        &mut self,
+        directory: Address,
+        callback: &mut dyn FnMut(Win32ObjectDirEntry) -> bool,
+    ) -> Result<()> {
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let ptr_size = ArchitectureObj::from(self.kernel_info.os_info.arch).size_addr();
+
+        for bucket in 0..OBJECT_DIRECTORY_BUCKETS {
+            // _OBJECT_DIRECTORY_ENTRY { ChainLink, Object, HashValue }
+            let mut entry = self
+                .virt_mem
+                .read_addr_arch(arch_obj, directory + bucket * ptr_size)?;
+            for _ in 0..MAX_ITER_COUNT {
+                if entry.is_null() {
+                    break;
+                }
+
+                let object = self.virt_mem.read_addr_arch(arch_obj, entry + ptr_size)?;
+                if !object.is_null() {
+                    match self.object_header_info(object) {
+                        Ok((name, type_index)) => {
+                            let entry = Win32ObjectDirEntry {
+                                name,
+                                type_index,
+                                object_address: object,
+                            };
+                            if !callback(entry) {
+                                return Ok(());
+                            }
+                        }
+                        Err(err) => {
+                            trace!(
+                                "skipping object {:x} with unreadable header: {:?}",
+                                object,
+                                err
+                            );
+                        }
+                    }
+                }
+
+                entry = self.virt_mem.read_addr_arch(arch_obj, entry)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the _OBJECT_HEADER offsets required to decode an object are available
+    fn object_header_offsets_valid(&self) -> bool {
+        self.offsets.object_header_body() != 0
+            && self.offsets.object_header_type_index() != 0
+            && self.offsets.object_header_info_mask() != 0
+    }
+
+    /// Reads the name and the decoded type index of an object from its _OBJECT_HEADER
+    fn object_header_info(&mut self, object: Address) -> Result<(String, u8)> {
+        // TypeIndex and InfoMask only exist since windows 7
+        if !self.object_header_offsets_valid() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("_OBJECT_HEADER offsets not available"));
+        }
+
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let ptr_size = ArchitectureObj::from(self.kernel_info.os_info.arch).size_addr();
+        let header = object - self.offsets.object_header_body();
+
+        let mut type_index: u8 = self
+            .virt_mem
+            .read(header + self.offsets.object_header_type_index())?;
+        if self.offsets.ob_header_cookie() != 0 {
+            // since windows 10 the type index is xored with the cookie and the second byte of the header address
+            let cookie: u8 = self
+                .virt_mem
+                .read(self.kernel_info.os_info.base + self.offsets.ob_header_cookie())?;
+            type_index ^= cookie ^ (header.to_umem() >> 8) as u8;
+        }
+
+        // the optional headers are stored in front of the object header, the creator info being the closest one.
+        // both the _OBJECT_HEADER_CREATOR_INFO and the _OBJECT_HEADER_NAME_INFO are 4 pointers in size.
+        let info_mask: u8 = self
+            .virt_mem
+            .read(header + self.offsets.object_header_info_mask())?;
+        let name = if info_mask & OB_INFO_MASK_NAME_INFO != 0 {
+            let name_info = if info_mask & OB_INFO_MASK_CREATOR_INFO != 0 {
+                header - 8 * ptr_size
+            } else {
+                header - 4 * ptr_size
+            };
+            self.virt_mem
+                .read_unicode_string(
+                    arch_obj,
+                    name_info + self.offsets.object_header_name_info_name(),
+                )
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
+
+        Ok((name, type_index))
+    }
+
+    /// Returns the resolved function addresses of the System Service Descriptor Table (KiServiceTable)
+    ///
+    /// The KeServiceDescriptorTable is resolved via symbols, the ntoskrnl.exe export (x86 only)
+    /// or a signature scan of KiSystemServiceRepeat (x64 only).
+    /// Returns `ErrorKind::Offset` if none of them succeeds.
+    pub fn ssdt(&mut self) -> Result<Vec<Address>> {
+        let descriptor_table = if self.offsets.ke_service_descriptor_table() != 0 {
+            self.kernel_info.os_info.base + self.offsets.ke_service_descriptor_table()
+        } else {
+            match self.kernel_export("KeServiceDescriptorTable") {
+                Ok(addr) => addr,
+                Err(_) => self.find_service_descriptor_table_sig().map_err(|_| {
+                    Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                        .log_info("KeServiceDescriptorTable offset not available")
+                })?,
+            }
+        };
+        trace!("ke_service_descriptor_table={:x}", descriptor_table);
+
+        // _KSERVICE_TABLE_DESCRIPTOR { Base, Count, Limit, Number }
+        let arch = self.kernel_info.os_info.arch;
+        let ptr_size = ArchitectureObj::from(arch).size_addr();
+        let service_table = self
+            .virt_mem
+            .read_addr_arch(arch.into(), descriptor_table)?;
+        let limit = self.virt_mem.read::<u32>(descriptor_table + 2 * ptr_size)?;
+        trace!("ki_service_table={:x} limit={}", service_table, limit);
+
+        if service_table.is_null() || limit == 0 || limit > 0x1000 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_info("invalid KeServiceDescriptorTable"));
+        }
+
+        let mut entries = vec![0u32; limit as usize];
+        self.virt_mem
+            .read_into(service_table, entries.as_mut_slice())?;
+
+        Ok(match arch {
+            // on x64 each entry holds the offset relative to KiServiceTable in the upper 28 bits
+            ArchitectureIdent::X86(64, _) => entries
+                .into_iter()
+                .map(|e| ssdt_entry_address(service_table, e))
+                .collect(),
+            _ => entries.into_iter().map(Address::from).collect(),
+        })
+    }
+
+    fn find_service_descriptor_table_sig(&mut self) -> Result<Address> {
+        if self.kernel_info.os_info.arch != ArchitectureIdent::X86(64, false) {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotImplemented)
+                .log_info("KeServiceDescriptorTable signature is only available on x64"));
+        }
+
+        // KiSystemServiceRepeat: 4C 8D 15 ? ? ? ? 4C 8D 1D
+        let scanner = PlainScanner::from(crate::ida_signature!("4C 8D 15 [?;4] 4C 8D 1D"));
+
+        let base = self.kernel_info.os_info.base;
+        let image = self.kernel_image()?;
+
+        let table_offs = scan_rip_relative(&scanner, image, 0x3).ok_or_else(|| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("unable to find KeServiceDescriptorTable signature")
+        })?;
+        trace!("KeServiceDescriptorTable found at: {:x}", table_offs);
+        Ok(base + table_offs)
+    }
+
+    /// Walks the PoolBigPageTable and calls the provided callback for each big pool allocation
+    ///
+    /// Empty table slots are skipped, freed allocations are reported with `freed` set.
+    pub fn big_pool_list_callback(&mut self, mut callback: Win32PoolEntryCallback) -> Result<()> {
+        if self.offsets.pool_big_page_table() == 0
+            || self.offsets.pool_big_page_table_size() == 0
+            || self.offsets.big_pages_number_of_bytes() == 0
+        {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("PoolBigPageTable offsets not available"));
+        }
+
+        let arch = self.kernel_info.os_info.arch;
+        let arch_obj = ArchitectureObj::from(arch);
+        let ptr_size = arch_obj.size_addr();
+
+        let table = self.virt_mem.read_addr_arch(
+            arch.into(),
+            self.kernel_info.os_info.base + self.offsets.pool_big_page_table(),
+        )?;
+        let table_size = self
+            .virt_mem
+            .read_addr_arch(
+                arch.into(),
+                self.kernel_info.os_info.base + self.offsets.pool_big_page_table_size(),
+            )?
+            .to_umem() as usize;
+        trace!("pool_big_page_table={:x} size={}", table, table_size);
+
+        if table.is_null() || table_size == 0 || table_size > MAX_BIG_POOL_TABLE_SIZE {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_info("invalid PoolBigPageTable"));
+        }
+
+        // ProcessBilled is the last field of the structure when it is present, NumberOfBytes otherwise
+        let entry_size = if self.offsets.big_pages_process_billed() != 0 {
+            self.offsets.big_pages_process_billed() + ptr_size
+        } else {
+            self.offsets.big_pages_number_of_bytes() + ptr_size
+        };
+
+        let read_ptr = |entry: &[u8], offs: usize| -> umem {
+            if ptr_size == 8 {
+                u64::from_le_bytes(entry[offs..offs + 8].try_into().unwrap()) as umem
+            } else {
+                u32::from_le_bytes(entry[offs..offs + 4].try_into().unwrap()) as umem
+            }
+        };
+
+        let key = self.offsets.big_pages_key();
+        let mut buf = vec![0u8; BIG_POOL_CHUNK_ENTRIES * entry_size];
+        for first in (0..table_size).step_by(BIG_POOL_CHUNK_ENTRIES) {
+            let count = BIG_POOL_CHUNK_ENTRIES.min(table_size - first);
+            let chunk = &mut buf[..count * entry_size];
+            self.virt_mem
+                .read_raw_into(table + first * entry_size, chunk)
+                .data_part()?;
+
+            for entry in chunk.chunks_exact(entry_size) {
+                let va = read_ptr(entry, 0);
+                // the lowest bit of the va is set when the allocation has been freed
+                if va & !1 == 0 {
+                    continue;
+                }
+
+                let pool_entry = Win32PoolEntry {
+                    va: Address::from(va & !1),
+                    size: read_ptr(entry, self.offsets.big_pages_number_of_bytes()),
+                    tag: entry[key..key + 4].try_into().unwrap(),
+                    freed: va & 1 != 0,
+                };
+                if !callback.call(pool_entry) {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a list of all allocations in the PoolBigPageTable
+    ///
+    /// See `big_pool_list_callback` for details.
+    pub fn big_pool_list(&mut self) -> Result<Vec<Win32PoolEntry>> {
+        let mut out = vec![];
+        self.big_pool_list_callback((&mut out).into())?;
+        Ok(out)
+    }
+
+    /// Returns all processes whose name matches the given predicate
+    ///
+    /// Like `process_info_by_name` this only returns processes that are not dead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::mem::{PhysicalMemory, VirtualTranslate2};
+    /// use memflow_win32::win32::Win32Kernel;
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone>(
+    ///     kernel: &mut Win32Kernel<T, V>,
+    /// ) {
+    ///     let infos = kernel
+    ///         .process_info_by_name_matching(|name| name.ends_with(".exe"))
+    ///         .unwrap();
+    ///     println!("found {} executables", infos.len());
+    /// }
+    /// ```
+    pub fn process_info_by_name_matching(
+        &mut self,
+        pred: impl Fn(&str) -> bool,
+    ) -> Result<Vec<ProcessInfo>> {
+        let mut out = vec![];
+        let callback = &mut |info: ProcessInfo| {
+            if (info.state == ProcessState::Unknown || info.state == ProcessState::Alive)
+                && pred(info.name.as_ref())
+            {
+                out.push(info);
+            }
+            true
+        };
+        self.process_info_list_callback(callback.into())?;
+        Ok(out)
+    }
+
+    /// Finds the first process whose name contains the given string
+    ///
+    /// The comparison is case-insensitive.
+    pub fn process_by_name_contains(
+        &mut self,
+        substr: &str,
+    ) -> Result<Win32Process<Fwd<&mut T>, Fwd<&mut V>, Win32VirtualTranslate>> {
+        let substr = substr.to_lowercase();
+        let info = self
+            .process_info_by_name_matching(|name| name.to_lowercase().contains(&substr))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                Error(ErrorOrigin::OsLayer, ErrorKind::ProcessNotFound)
+                    .log_info(format!("unable to find a process containing {}", substr))
+            })?;
+        self.process_by_info(info)
+    }
+
+    /// Performs a sanity check of the kernel.
+    ///
+    /// This verifies that the ntoskrnl.exe pe header is readable at the kernel base,
+    /// that the PsLoadedModuleList can be resolved and that the System process (pid 4)
+    /// can be found in the process list.
+    pub fn validate(&mut self) -> Result<()> {
+        let magic: u16 = self
+            .virt_mem
+            .read(self.kernel_info.os_info.base)
+            .map_err(|_| {
+                Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile)
+                    .log_error("validation failed: unable to read the ntoskrnl.exe header")
+            })?;
+        if magic != 0x5a4d {
+            return Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_error(format!(
+                    "validation failed: invalid ntoskrnl.exe header magic {:x} at {:x}",
+                    magic, self.kernel_info.os_info.base
+                )),
+            );
+        }
+
+        self.check_offsets()?;
+
+        self.kernel_modules().map_err(|err| {
+            Error(ErrorOrigin::OsLayer, err.1)
+                .log_error("validation failed: unable to resolve PsLoadedModuleList")
+        })?;
+
+        self.process_info_by_pid(4).map_err(|err| {
+            Error(ErrorOrigin::OsLayer, err.1)
+                .log_error("validation failed: unable to find the system process")
+        })?;
+
+        Ok(())
+    }
+
+    /// Checks that the offsets plausibly match the running kernel.
+    ///
+    /// The System process is read with the resolved offsets and its pid and name are compared
+    /// against the expected values (4 and `System`). Offsets of a close but wrong build
+    /// usually fail this check, while they would otherwise silently produce garbage.
+    ///
+    /// Returns `ErrorKind::VersionMismatch` if the offsets likely do not match the build.
+    pub fn check_offsets(&mut self) -> Result<()> {
+        let eprocess = self.kernel_info.eprocess_base;
+
+        let pid: Pid = self
+            .virt_mem
+            .read(eprocess + self.offsets.eproc_pid())
+            .map_err(|_| {
+                Error(ErrorOrigin::OsLayer, ErrorKind::UnableToReadMemory)
+                    .log_warn("unable to read the pid of the system process")
+            })?;
+        let name = self
+            .virt_mem
+            .read_char_array(eprocess + self.offsets.eproc_name(), IMAGE_FILE_NAME_LENGTH)
+            .unwrap_or_default();
+
+        if pid != 4 || name != "System" {
+            return Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::VersionMismatch).log_warn(format!(
+                    "system process at {:x} has pid={} name={:?}, the offsets likely do not match kernel {}",
+                    eprocess, pid, name, self.kernel_info.kernel_winver
+                )),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Re-derives the kernel dtb from the System process and rebuilds the kernel translator.
+    ///
+    /// The System process is looked up in the process list using the current dtb first,
+    /// falling back to the dtb found in the start block.
+    /// This can be useful when translations start failing mid-session (e.g. after a snapshot was resumed).
+    ///
+    /// Returns the new dtb. If no valid dtb could be found the current translator is kept.
+    pub fn rescan_dtb(&mut self) -> Result<Address> {
+        let arch = self.kernel_info.os_info.arch;
+        let previous = self.sysproc_dtb;
+
+        for dtb in [self.sysproc_dtb, self.kernel_info.dtb] {
+            self.virt_mem
+                .set_translator(Win32VirtualTranslate::new(arch, dtb));
+
+            let system_dtb = self
+                .process_info_by_pid(4)
+                .ok()
+                .and_then(|info| info.dtb1.as_page_aligned(4096).non_null());
+            if let Some(system_dtb) = system_dtb {
+                info!("updating sysproc_dtb={:x}", system_dtb);
+                self.virt_mem
+                    .set_translator(Win32VirtualTranslate::new(arch, system_dtb));
+                self.sysproc_dtb = system_dtb;
+                self.kernel_modules = None;
+                return Ok(system_dtb);
+            }
+        }
+
+        self.virt_mem
+            .set_translator(Win32VirtualTranslate::new(arch, previous));
+        Err(Error(ErrorOrigin::OsLayer, ErrorKind::ProcessNotFound)
+            .log_warn("unable to find the system process to rescan the dtb"))
+    }
+
+    /// Returns the number of processors of the target
+    ///
+    /// The value is read from `nt!KeNumberProcessors`.
+    pub fn processor_count(&mut self) -> Result<u32> {
+        let number_processors = if self.offsets.ke_number_processors() != 0 {
+            self.kernel_info.os_info.base + self.offsets.ke_number_processors()
+        } else {
+            self.kernel_export("KeNumberProcessors")?
+        };
+        trace!("ke_number_processors={:x}", number_processors);
+
+        let count = self.virt_mem.read::<u32>(number_processors)?;
+        // KeNumberProcessors is a CCHAR prior to windows vista
+        if self.kernel_info.kernel_winver < (6, 0).into() {
+            Ok(count & 0xff)
+        } else {
+            Ok(count)
+        }
+    }
+
+    /// Returns the address of the _KPCR of the given processor
+    ///
+    /// The _KPCR is derived from the _KPRCB pointers stored in `nt!KiProcessorBlock`.
+    pub fn kpcr(&mut self, cpu: u32) -> Result<Address> {
+        if self.offsets.ki_processor_block() == 0 || self.offsets.kpcr_prcb() == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("KiProcessorBlock or _KPCR::Prcb offset not available"));
+        }
+
+        let processor_count = self.processor_count()?;
+        if cpu >= processor_count {
+            return Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds).log_info(format!(
+                    "processor {} requested but the target only has {} processors",
+                    cpu, processor_count
+                )),
+            );
+        }
+
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let ptr_size = ArchitectureObj::from(self.kernel_info.os_info.arch).size_addr();
+        let processor_block = self.kernel_info.os_info.base + self.offsets.ki_processor_block();
+
+        let prcb = self
+            .virt_mem
+            .read_addr_arch(arch_obj, processor_block + cpu as usize * ptr_size)?;
+        if prcb.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info(format!("no _KPRCB found for processor {}", cpu)));
+        }
+        trace!("prcb[{}]={:x}", cpu, prcb);
+
+        Ok(prcb - self.offsets.kpcr_prcb())
+    }
+
+    /// Returns the handler addresses of the interrupt descriptor table of the given processor
+    ///
+    /// The table is located via `_KPCR::IdtBase` and all 256 gate descriptors are decoded.
+    /// The returned list is indexed by the interrupt vector.
+    pub fn idt(&mut self, cpu: u32) -> Result<Vec<Address>> {
+        if self.offsets.kpcr_idt_base() == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("_KPCR::IdtBase offset not available"));
+        }
+
+        let gate_size = match self.kernel_info.os_info.arch {
+            ArchitectureIdent::X86(64, _) => 16,
+            ArchitectureIdent::X86(32, _) => 8,
+            _ => {
+                return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotSupported)
+                    .log_info("the idt can only be read on x86 targets"))
+            }
+        };
+
+        let kpcr = self.kpcr(cpu)?;
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let idt_base = self
+            .virt_mem
+            .read_addr_arch(arch_obj, kpcr + self.offsets.kpcr_idt_base())?;
+        trace!("idt_base[{}]={:x}", cpu, idt_base);
+
+        let mut idt = vec![0u8; IDT_ENTRY_COUNT * gate_size];
+        self.virt_mem.read_raw_into(idt_base, &mut idt)?;
+
+        Ok(idt
+            .chunks_exact(gate_size)
+            .map(|gate| {
+                // OffsetLow, OffsetMiddle and (on x64) OffsetHigh
+                let low = u16::from_le_bytes([gate[0], gate[1]]) as u64;
+                let middle = u16::from_le_bytes([gate[6], gate[7]]) as u64;
+                let high = if gate_size == 16 {
+                    u32::from_le_bytes([gate[8], gate[9], gate[10], gate[11]]) as u64
+                } else {
+                    0
+                };
+                Address::from(low | (middle << 16) | (high << 32))
+            })
+            .collect())
+    }
+
+    /// Returns all registry hives loaded by the configuration manager
+    ///
+    /// The hives are read from the CmpHiveListHead list.
+    pub fn hive_list(&mut self) -> Result<Vec<Win32Hive>> {
+        if self.offsets.cmp_hive_list_head() == 0
+            || self.offsets.cmhive_hive_list() == 0
+            || self.offsets.hhive_base_block() == 0
+        {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("CmpHiveListHead or _CMHIVE offsets not available"));
+        }
+
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let list_head = self.kernel_info.os_info.base + self.offsets.cmp_hive_list_head();
+        trace!("cmp_hive_list_head={:x}", list_head);
+
+        let mut hives = vec![];
+        let mut list_entry = self.virt_mem.read_addr_arch(arch_obj, list_head)?;
+        for _ in 0..MAX_ITER_COUNT {
+            if list_entry.is_null() || list_entry == list_head {
+                break;
+            }
+
+            let address = list_entry - self.offsets.cmhive_hive_list();
+            let file_path = self
+                .virt_mem
+                .read_unicode_string(arch_obj, address + self.offsets.cmhive_file_full_path())
+                .unwrap_or_default();
+            let root_path = if self.offsets.cmhive_hive_root_path() != 0 {
+                self.virt_mem
+                    .read_unicode_string(arch_obj, address + self.offsets.cmhive_hive_root_path())
+                    .unwrap_or_default()
+            } else {
+                String::new()
+            };
+            let base_block = self
+                .virt_mem
+                .read_addr_arch(arch_obj, address + self.offsets.hhive_base_block())?;
+            let root_cell = self
+                .virt_mem
+                .read::<u32>(base_block + self.offsets.hbase_block_root_cell())?;
+            trace!(
+                "hive={:x} file_path={} root_path={} root_cell={:x}",
+                address,
+                file_path,
+                root_path,
+                root_cell
+            );
+
+            hives.push(Win32Hive {
+                address,
+                file_path,
+                root_path,
+                root_cell,
+            });
+
+            list_entry = self.virt_mem.read_addr_arch(arch_obj, list_entry)?;
+        }
+
+        Ok(hives)
+    }
+
+    /// Returns the _EPROCESS addresses of all processes assigned to the given _EJOB.
+    ///
+    /// The job of a process can be retrieved with `Win32Process::job`.
+    pub fn job_processes(&mut self, job: Address) -> Result<Vec<Address>> {
+        if self.offsets.ejob_process_list_head() == 0 || self.offsets.eproc_job_links() == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset).log_info(
+                "_EJOB::ProcessListHead or _EPROCESS::JobLinks offset is not available",
+            ));
+        }
+
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let list_head = job + self.offsets.ejob_process_list_head();
+
+        let mut processes = vec![];
+        let mut list_entry = self.virt_mem.read_addr_arch(arch_obj, list_head)?;
+        for _ in 0..MAX_ITER_COUNT {
+            if list_entry.is_null() || list_entry == list_head {
+                break;
+            }
+
+            let eprocess = list_entry - self.offsets.eproc_job_links();
+            trace!("job={:x} eprocess={:x}", job, eprocess);
+            processes.push(eprocess);
+
+            list_entry = self.virt_mem.read_addr_arch(arch_obj, list_entry)?;
+        }
+
+        Ok(processes)
+    }
+
+    /// Returns the id of the session the given _EPROCESS belongs to.
+    ///
+    /// Processes without a session (e.g. System) return an error.
+    pub fn process_session_id(&mut self, eprocess: Address) -> Result<u32> {
+        if self.offsets.eproc_session() == 0 || self.offsets.mm_session_space_session_id() == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset).log_info(
+                "_EPROCESS::Session or _MM_SESSION_SPACE::SessionId offset is not available",
+            ));
+        }
+
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let session = self
+            .virt_mem
+            .read_addr_arch(arch_obj, eprocess + self.offsets.eproc_session())?;
+        if session.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("process is not attached to a session"));
+        }
+
+        let session_id = self
+            .virt_mem
+            .read::<u32>(session + self.offsets.mm_session_space_session_id())?;
+        trace!("eprocess={:x} session_id={}", eprocess, session_id);
+        Ok(session_id)
+    }
+
+    /// Opens the registry key at `path` in the given hive
+    ///
+    /// The path is relative to the root key of the hive and its components are separated by backslashes,
+    /// e.g. `ControlSet001\Services`. Key names are compared case-insensitively.
+    /// An empty path opens the root key of the hive.
+    pub fn registry_open(&mut self, hive: &Win32Hive, path: &str) -> Result<Win32RegKey> {
+        if self.offsets.hhive_storage() == 0
+            || self.offsets.hmap_entry_size() == 0
+            || self.offsets.dual_size() == 0
+        {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("_HHIVE::Storage, _DUAL or _HMAP_ENTRY offsets not available"));
+        }
+
+        let mut cell = hive.root_cell;
+        for name in path.split('\\').filter(|name| !name.is_empty()) {
+            let node = KeyNode::parse(&self.hive_read_cell(hive, cell)?)?;
+            cell = self
+                .hive_subkeys(hive, &node)?
+                .into_iter()
+                .find(|(_, subkey)| subkey.name.eq_ignore_ascii_case(name))
+                .map(|(subkey_cell, _)| subkey_cell)
+                .ok_or_else(|| {
+                    Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                        .log_info(format!("registry key {} not found", name))
+                })?;
+        }
+
+        let node = KeyNode::parse(&self.hive_read_cell(hive, cell)?)?;
+
+        let subkeys = self
+            .hive_subkeys(hive, &node)?
+            .into_iter()
+            .map(|(_, subkey)| subkey.name)
+            .collect();
+
+        let mut values = vec![];
+        if node.value_count > 0 {
+            let value_list = self.hive_read_cell(hive, node.value_list)?;
+            for value_cell in parse_cell_list(&value_list, node.value_count as usize)? {
+                let value = KeyValue::parse(&self.hive_read_cell(hive, value_cell)?)?;
+                let data = self.hive_value_data(hive, &value)?;
+                values.push((value.name, RegValue::from_raw(value.value_type, &data)));
+            }
+        }
+
+        Ok(Win32RegKey {
+            name: node.name,
+            cell,
+            last_write_time: node.last_write_time,
+            values,
+            subkeys,
+        })
+    }
+
+    /// Returns the cell indices and key nodes of all subkeys (stable and volatile) of the given key node
+    fn hive_subkeys(&mut self, hive: &Win32Hive, node: &KeyNode) -> Result<Vec<(u32, KeyNode)>> {
+        let mut lists = node
+            .subkey_lists
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(_, list)| *list)
+            .collect::<Vec<_>>();
+
+        let mut subkeys = vec![];
+        while let Some(list) = lists.pop() {
+            if subkeys.len() >= MAX_ITER_COUNT {
+                break;
+            }
+            match SubkeyList::parse(&self.hive_read_cell(hive, list)?)? {
+                SubkeyList::Keys(cells) => {
+                    for cell in cells {
+                        let subkey = KeyNode::parse(&self.hive_read_cell(hive, cell)?)?;
+                        subkeys.push((cell, subkey));
+                    }
+                }
+                SubkeyList::Lists(cells) => lists.extend(cells),
+            }
+        }
+
+        Ok(subkeys)
+    }
+
+    /// Reads the data of the given value
+    fn hive_value_data(&mut self, hive: &Win32Hive, value: &KeyValue) -> Result<Vec<u8>> {
+        let length = (value.data_length & !CM_KEY_VALUE_SPECIAL_SIZE) as usize;
+
+        // small values are stored in the data field itself
+        if value.data_length & CM_KEY_VALUE_SPECIAL_SIZE != 0 {
+            return Ok(value.data.to_le_bytes()[..length.min(4)].to_vec());
+        }
+        if length == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut data = self.hive_read_cell(hive, value.data)?;
+        if length > CM_KEY_VALUE_BIG as usize {
+            if let Ok((segment_list, count)) = parse_big_data(&data) {
+                let segment_list = self.hive_read_cell(hive, segment_list)?;
+                data = vec![];
+                for segment in parse_cell_list(&segment_list, count)? {
+                    let segment = self.hive_read_cell(hive, segment)?;
+                    data.extend_from_slice(
+                        &segment[..segment.len().min(CM_KEY_VALUE_BIG as usize)],
+                    );
+                }
+            }
+        }
+
+        data.truncate(length);
+        Ok(data)
+    }
+
+    /// Reads the data of the cell with the given index
+    fn hive_read_cell(&mut self, hive: &Win32Hive, cell: u32) -> Result<Vec<u8>> {
+        let address = self.hive_cell_address(hive, cell)?;
+
+        // allocated cells have a negative size which includes the size field itself
+        let size = self.virt_mem.read::<i32>(address)?;
+        let size = (size.unsigned_abs() as usize).saturating_sub(4);
+        if size > MAX_REGISTRY_CELL_SIZE {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_info(format!("registry cell {:x} is too large", cell)));
+        }
+
+        let mut data = vec![0u8; size];
+        self.virt_mem.read_raw_into(address + 4, &mut data)?;
+        Ok(data)
+    }
+
+    /// Translates a cell index into the virtual address of the cell
+    fn hive_cell_address(&mut self, hive: &Win32Hive, cell: u32) -> Result<Address> {
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let ptr_size = ArchitectureObj::from(self.kernel_info.os_info.arch).size_addr();
+
+        let storage = (cell >> 31) as usize;
+        let directory_index = ((cell >> 21) & 0x3ff) as usize;
+        let table_index = ((cell >> 12) & 0x1ff) as usize;
+        let block_offset = (cell & 0xfff) as usize;
+
+        let map = self.virt_mem.read_addr_arch(
+            arch_obj,
+            hive.address
+                + self.offsets.hhive_storage()
+                + storage * self.offsets.dual_size()
+                + self.offsets.dual_map(),
+        )?;
+        let table = self
+            .virt_mem
+            .read_addr_arch(arch_obj, map + directory_index * ptr_size)?;
+        if table.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info(format!("registry cell {:x} is not mapped", cell)));
+        }
+        let entry = table + table_index * self.offsets.hmap_entry_size();
+
+        let block = if self.offsets.hmap_entry_permanent_bin_address() != 0 {
+            // the lower bits of PermanentBinAddress are used as flags
+            let bin = self.virt_mem.read_addr_arch(
+                arch_obj,
+                entry + self.offsets.hmap_entry_permanent_bin_address(),
+            )?;
+            let offset = self
+                .virt_mem
+                .read_addr_arch(arch_obj, entry + self.offsets.hmap_entry_block_offset())?;
+            Address::from(bin.to_umem() & !0xf) + offset.to_umem()
+        } else {
+            self.virt_mem
+                .read_addr_arch(arch_obj, entry + self.offsets.hmap_entry_block_address())?
+        };
+        if block.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info(format!("registry cell {:x} is not mapped", cell)));
+        }
+
+        Ok(block + block_offset)
+    }
+
+    /// Returns true if the kernel is running on top of a hypervisor (e.g. Hyper-V or VBS)
+    ///
+    /// This reads `nt!HvlHypervisorConnected` or, if not available, checks if any
+    /// hypervisor enlightenments are set in `nt!HvlEnlightenments`.
+    /// If neither symbol can be read, `_KUSER_SHARED_DATA` is checked for
+    /// QueryPerformanceCounter using the reference time page of the hypervisor.
+    /// Note that this last check can miss hypervisors that do not provide the page.
+    pub fn hypervisor_present(&mut self) -> Result<bool> {
+        let base = self.kernel_info.os_info.base;
+        if self.offsets.hvl_hypervisor_connected() != 0 {
+            match self
+                .virt_mem
+                .read::<u8>(base + self.offsets.hvl_hypervisor_connected())
+            {
+                Ok(connected) => return Ok(connected != 0),
+                Err(err) => trace!("unable to read HvlHypervisorConnected: {}", err),
+            }
+        }
+        if self.offsets.hvl_enlightenments() != 0 {
+            match self
+                .virt_mem
+                .read::<u32>(base + self.offsets.hvl_enlightenments())
+            {
+                Ok(enlightenments) => return Ok(enlightenments != 0),
+                Err(err) => trace!("unable to read HvlEnlightenments: {}", err),
+            }
+        }
+
+        self.shared_user_data()?
+            .qpc_uses_hypervisor_page()
+            .ok_or_else(|| {
+                Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                    .log_info("unable to determine whether a hypervisor is present")
+            })
+    }
+
+    /// Reads the `_KUSER_SHARED_DATA` page of the target.
+    ///
+    /// The page is mapped at a fixed address and contains the version of the running kernel
+    /// as well as the current system and interrupt time.
+    pub fn shared_user_data(&mut self) -> Result<Win32SharedUserData> {
+        Win32SharedUserData::read(&mut self.virt_mem, self.kernel_info.os_info.arch)
+    }
+
+    /// Returns the current system time of the target in seconds since the UNIX epoch.
+    ///
+    /// The time is read from `_KUSER_SHARED_DATA::SystemTime`.
+    pub fn system_time_unix(&mut self) -> Result<u64> {
+        Ok(self.shared_user_data()?.system_time_unix())
+    }
+
+    /// Limits the number of entries visited when walking the process list.
+    ///
+    /// Lowering the limit prevents excessive reads on corrupted memory snapshots.
+    /// The limit is capped at 65536 entries.
+    pub fn set_max_process_iter(&mut self, max_iter: usize) {
+        self.max_process_iter = max_iter.min(MAX_ITER_COUNT);
+    }
+
+    /// Controls how `process_info_list` handles processes that cannot be read.
+    ///
+    /// By default such processes are skipped and logged so that a few torn
+    /// `_EPROCESS` entries do not prevent the rest of the list from being read.
+    /// In strict mode the enumeration is aborted with the error instead.
+    pub fn set_strict_process_list(&mut self, strict: bool) {
+        self.strict_process_list = strict;
+    }
+
+    /// Controls which memory regions processes report in `Process::mapped_mem_range`.
+    ///
+    /// By default the page tables of the process are walked.
+    /// When enabled the regions of the VAD tree are reported instead, see `Win32Process::vad_region_list_callback`.
+    /// This also applies to processes accessed through the plugin interface.
+    pub fn set_mapped_mem_vad(&mut self, enabled: bool) {
+        self.mapped_mem_vad = enabled;
+    }
+
+    /// Returns the pid and dtb of all processes.
+    ///
+    /// Only the pid and dtb are read from each `_EPROCESS` which makes this considerably cheaper
+    /// than `process_info_list` for callers that just need to set up translation contexts.
+    /// Processes that cannot be read are skipped unless strict mode is enabled
+    /// via `set_strict_process_list`.
+    pub fn process_dtbs(&mut self) -> Result<Vec<(Pid, Address)>> {
+        let arch_obj = self.kernel_info.os_info.arch.into();
+
+        let mut dtbs = vec![];
+        for address in self.process_address_list()? {
+            let entry = (|| -> Result<(Pid, Address)> {
+                let pid: Pid = self.virt_mem.read(address + self.offsets.eproc_pid())?;
+                let dtb = self
+                    .virt_mem
+                    .read_addr_arch(arch_obj, address + self.offsets.kproc_dtb())?;
+                Ok((pid, dtb))
+            })();
+            match entry {
+                Ok(entry) => dtbs.push(entry),
+                Err(err) if self.strict_process_list => return Err(err),
+                Err(err) => warn!("skipping process at {:x}: {}", address, err),
+            }
+        }
+
+        Ok(dtbs)
+    }
+
+    /// Returns the address, pid, name and state of all processes.
+    ///
+    /// Unlike `process_info_list` this never reads the peb, teb or module lists of a process,
+    /// which makes it a lot faster on slow connectors.
+    /// Processes that cannot be read are skipped unless strict mode is enabled
+    /// via `set_strict_process_list`.
+    pub fn process_info_list_basic(&mut self) -> Result<Vec<Win32ProcessBasicInfo>> {
+        let mut list = vec![];
+        for address in self.process_address_list()? {
+            match self.process_info_base_by_address(address) {
+                Ok(info) => list.push(info.into()),
+                Err(err) if self.strict_process_list => return Err(err),
+                Err(err) => warn!("skipping process at {:x}: {}", address, err),
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Returns the creation time of the process with the given _EPROCESS address.
+    ///
+    /// The time is returned in 100ns intervals since January 1, 1601 (UTC).
+    pub fn process_create_time(&mut self, address: Address) -> Result<u64> {
+        if self.offsets.eproc_create_time() == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("_EPROCESS::CreateTime offset is not available"));
+        }
+
+        Ok(self
+            .virt_mem
+            .read::<u64>(address + self.offsets.eproc_create_time())?)
+    }
+
+    /// Returns the list of all processes sorted by the given key.
+    ///
+    /// The sort is stable, processes with an equal key keep the order of the process list.
+    /// When sorting by creation time, processes whose creation time cannot be read are sorted last.
+    /// Sorting by creation time fails if the `_EPROCESS::CreateTime` offset is not available.
+    pub fn process_info_list_sorted(
+        &mut self,
+        sort_by: Win32ProcessSortBy,
+    ) -> Result<Vec<ProcessInfo>> {
+        let mut list = self.process_info_list()?;
+        match sort_by {
+            Win32ProcessSortBy::Pid => list.sort_by_key(|info| info.pid),
+            Win32ProcessSortBy::Name => list.sort_by_cached_key(|info| info.name.to_lowercase()),
+            Win32ProcessSortBy::CreateTime => {
+                if self.offsets.eproc_create_time() == 0 {
+                    return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                        .log_info("_EPROCESS::CreateTime offset is not available"));
+                }
+
+                let mut keyed = list
+                    .into_iter()
+                    .map(|info| {
+                        let create_time =
+                            self.process_create_time(info.address).unwrap_or(u64::MAX);
+                        (create_time, info)
+                    })
+                    .collect::<Vec<_>>();
+                keyed.sort_by_key(|(create_time, _)| *create_time);
+                list = keyed.into_iter().map(|(_, info)| info).collect();
+            }
+        }
+        Ok(list)
+    }
+
+    /// Returns true if kernel page table isolation (KPTI / KVA shadowing) is active.
+    ///
+    /// Page table isolation is considered active if any process has a separate user mode dtb.
+    /// In that case `ProcessInfo::dtb2` contains the user mode dtb of the process.
+    pub fn kpti_enabled(&mut self) -> bool {
+        if self.offsets.kproc_user_dtb() == 0 {
+            return false;
+        }
+
+        let arch_obj = self.kernel_info.os_info.arch.into();
+        let addresses = self.process_address_list().unwrap_or_default();
+        addresses.into_iter().any(|address| {
+            self.virt_mem
+                .read_addr_arch(arch_obj, address + self.offsets.kproc_dtb())
+                .map(|dtb| self.process_user_dtb(address, dtb).is_valid())
+                .unwrap_or(false)
+        })
+    }
+
+    /// Returns a copy of the kernel info that was used to construct this kernel.
+    ///
+    /// The returned info can be stored and passed to `Win32KernelBuilder::with_kernel_info`
+    /// to construct a new kernel without scanning for it again.
+    pub fn save_info(&self) -> Win32KernelInfo {
+        self.kernel_info.clone()
+    }
+
     /// Consumes this kernel and return the underlying owned memory and vat objects
     pub fn into_inner(self) -> (T, V) {
         self.virt_mem.into_inner()
@@ -201,6 +1556,8 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
             module_info_wow64: None,
 
             vad_root,
+
+            frozen: false,
         })
     }
 
@@ -277,6 +1634,16 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
             base_info.address + self.offsets.eproc_vad_root(),
         )?;
 
+        let frozen = if self.offsets.kproc_flags() != 0 {
+            self.virt_mem
+                .read::<u32>(base_info.address + self.offsets.kproc_flags())
+                .map(|flags| flags & (1 << self.offsets.kproc_deep_freeze_bit()) != 0)
+                .unwrap_or(false)
+        } else {
+            false
+        };
+        trace!("frozen={}", frozen);
+
         // construct reader with process dtb - win32 only uses/requires one dtb so we always store it in `dtb1`
         // TODO: can tlb be used here already?
         let (phys_mem, vat) = self.virt_mem.mem_vat_pair();
@@ -327,6 +1694,8 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
             module_info_wow64,
 
             vad_root,
+
+            frozen,
         })
     }
 
@@ -392,6 +1761,9 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
         )?;
         trace!("dtb={:x}", dtb);
 
+        let user_dtb = self.process_user_dtb(address, dtb);
+        trace!("user_dtb={:x}", user_dtb);
+
         let pid: Pid = self.virt_mem.read(address + self.offsets.eproc_pid())?;
         trace!("pid={}", pid);
 
@@ -455,9 +1827,28 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
             sys_arch,
             proc_arch,
             dtb1: dtb,
-            dtb2: Address::invalid(),
+            dtb2: user_dtb,
         })
     }
+
+    /// Reads the user mode dtb (_KPROCESS::UserDirectoryTableBase) of the process at the given address.
+    ///
+    /// Returns `Address::invalid()` if page table isolation is not active for the process.
+    /// The kernel side dtb always maps the user address space as well, so it is the one used for translation.
+    fn process_user_dtb(&mut self, address: Address, dtb: Address) -> Address {
+        if self.offsets.kproc_user_dtb() == 0 {
+            return Address::invalid();
+        }
+
+        match self.virt_mem.read_addr_arch(
+            self.kernel_info.os_info.arch.into(),
+            address + self.offsets.kproc_user_dtb(),
+        ) {
+            // a value of 1 marks processes that are exempt from page table isolation
+            Ok(user_dtb) if user_dtb.to_umem() > 1 && user_dtb != dtb => user_dtb,
+            _ => Address::invalid(),
+        }
+    }
 }
 
 impl<T: PhysicalMemory> Win32Kernel<T, DirectTranslate> {
@@ -538,11 +1929,17 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
     ) -> memflow::error::Result<()> {
         let list_start = self.kernel_info.eprocess_base + self.offsets.eproc_link();
         let mut list_entry = list_start;
+        let mut visited = BTreeSet::new();
 
-        for _ in 0..MAX_ITER_COUNT {
+        for _ in 0..self.max_process_iter {
             let eprocess = list_entry - self.offsets.eproc_link();
             trace!("eprocess={}", eprocess);
 
+            if !visited.insert(list_entry) {
+                warn!("process list contains a cycle at eprocess {:x}", eprocess);
+                break;
+            }
+
             // test flink + blink before adding the process
             let flink_entry = self
                 .virt_mem
@@ -575,6 +1972,29 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
         Ok(())
     }
 
+    /// Walks the process list and calls the provided callback for each process
+    ///
+    /// Processes that cannot be read are skipped unless strict mode is enabled
+    /// via `set_strict_process_list`.
+    fn process_info_list_callback(
+        &mut self,
+        mut callback: ProcessInfoCallback,
+    ) -> memflow::error::Result<()> {
+        for address in self.process_address_list()? {
+            match self.process_info_by_address(address) {
+                Ok(info) => {
+                    if !callback.call(info) {
+                        break;
+                    }
+                }
+                Err(err) if self.strict_process_list => return Err(err),
+                Err(err) => warn!("skipping process at {:x}: {}", address, err),
+            }
+        }
+
+        Ok(())
+    }
+
     /// Find process information by its internal address
     fn process_info_by_address(&mut self, address: Address) -> memflow::error::Result<ProcessInfo> {
         let base_info = self.process_info_base_by_address(address)?;
@@ -626,7 +2046,6 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
                 self.kernel_info.os_info.arch,
                 callback,
             )
-            .map_err(From::from)
     }
 
     /// Retrieves a module by its structure address
@@ -634,14 +2053,12 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
     /// # Arguments
     /// * `address` - address where module's information resides in
     fn module_by_address(&mut self, address: Address) -> memflow::error::Result<ModuleInfo> {
-        self.kernel_modules()?
-            .module_info_from_entry(
-                address,
-                self.kernel_info.eprocess_base,
-                &mut self.virt_mem,
-                self.kernel_info.os_info.arch,
-            )
-            .map_err(From::from)
+        self.kernel_modules()?.module_info_from_entry(
+            address,
+            self.kernel_info.eprocess_base,
+            &mut self.virt_mem,
+            self.kernel_info.os_info.arch,
+        )
     }
 
     /// Retrieves address of the primary module structure of the process
@@ -699,11 +2116,13 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
     type IntoKeyboardType = Win32Keyboard<VirtualDma<T, V, Win32VirtualTranslate>>;
 
     fn keyboard(&mut self) -> memflow::error::Result<Self::KeyboardType<'_>> {
-        Win32Keyboard::with_kernel_ref(self)
+        let source = self.keyboard_source.clone();
+        Ok(Win32Keyboard::try_with_kernel_ref_source(self, &source)?)
     }
 
     fn into_keyboard(self) -> memflow::error::Result<Self::IntoKeyboardType> {
-        Win32Keyboard::with_kernel(self)
+        let source = self.keyboard_source.clone();
+        Win32Keyboard::with_kernel_source(self, &source)
     }
 }
 
@@ -712,3 +2131,31 @@ impl<T: PhysicalMemory, V: VirtualTranslate2> fmt::Debug for Win32Kernel<T, V> {
         write!(f, "{:?}", self.kernel_info)
     }
 }
+
+/// Decodes an x64 KiServiceTable entry into the address of the service routine.
+///
+/// The upper 28 bits hold the signed offset relative to KiServiceTable,
+/// it is negative for routines placed in front of the table.
+fn ssdt_entry_address(service_table: Address, entry: u32) -> Address {
+    let offset = (entry as i32 >> 4) as i64;
+    Address::from(service_table.to_umem().wrapping_add(offset as umem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssdt_entry_decode() {
+        let service_table = Address::from(0xfffff800_12345000u64);
+        assert_eq!(
+            ssdt_entry_address(service_table, 0x0012_3400),
+            Address::from(0xfffff800_12357340u64)
+        );
+        // -0x1000 relative to the table, the low nibble holds the argument count
+        assert_eq!(
+            ssdt_entry_address(service_table, (-0x1000i32 << 4) as u32 | 0x3),
+            Address::from(0xfffff800_12344000u64)
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use std::prelude::v1::*;
 
-use log::{info, trace};
+use log::{debug, info, trace};
 use std::fmt;
 
+use memflow::error::{Error, ErrorKind, ErrorOrigin, Result};
 use memflow::mem::{MemoryMap, MemoryView};
 use memflow::types::{mem, umem, Address};
 
@@ -36,31 +37,79 @@ const _: [(); std::mem::size_of::<PhysicalMemoryDescriptor<u64>>()] = [(); 0x210
 pub fn parse<T: MemoryView, U: Pod + Copy + fmt::Debug + fmt::LowerHex + Into<u64>>(
     virt_mem: &mut T,
     descriptor_ptr_ptr: Address,
-) -> Option<MemoryMap<(Address, umem)>> {
-    let descriptor_ptr = virt_mem.read_addr64(descriptor_ptr_ptr).ok()?;
+) -> Result<MemoryMap<(Address, umem)>> {
+    // the pointer width matches the width of the descriptor fields
+    let descriptor_ptr = Address::from(virt_mem.read::<U>(descriptor_ptr_ptr)?.into());
+    if descriptor_ptr.is_null() {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+            .log_info("phys_mem_block pointer is null"));
+    }
 
     trace!("found phys_mem_block pointer at: {}", descriptor_ptr);
-    let descriptor: PhysicalMemoryDescriptor<U> = virt_mem.read(descriptor_ptr).ok()?;
+    let descriptor: PhysicalMemoryDescriptor<U> = virt_mem.read(descriptor_ptr)?;
 
     trace!("found phys_mem_block: {:?}", descriptor);
-    if descriptor.number_of_runs.into() <= PHYSICAL_MEMORY_MAX_RUNS as u64 {
-        let mut mem_map = MemoryMap::new();
+    let number_of_runs = descriptor.number_of_runs.into();
+    if number_of_runs == 0 || number_of_runs > PHYSICAL_MEMORY_MAX_RUNS as u64 {
+        return Err(
+            Error(ErrorOrigin::OsLayer, ErrorKind::InvalidMemorySize).log_info(format!(
+                "invalid number of memory segments in phys_mem_block: {} found, at most {} expected",
+                number_of_runs, PHYSICAL_MEMORY_MAX_RUNS
+            )),
+        );
+    }
+
+    let mut mem_map = MemoryMap::new();
+    let mut total_pages = 0u64;
+    let mut prev_end = 0u64;
+
+    for run in descriptor.runs.iter().take(number_of_runs as usize) {
+        let base_page = run.base_page.into();
+        let page_count = run.page_count.into();
 
-        for i in 0..descriptor.number_of_runs.into() {
-            let base = descriptor.runs[i as usize].base_page.into() * SIZE_4KB;
-            let size = descriptor.runs[i as usize].page_count.into() * SIZE_4KB;
+        let (base, size) = match (
+            base_page.checked_mul(SIZE_4KB),
+            page_count.checked_mul(SIZE_4KB),
+        ) {
+            (Some(base), Some(size)) if base.checked_add(size).is_some() => (base, size),
+            _ => {
+                return Err(
+                    Error(ErrorOrigin::OsLayer, ErrorKind::InvalidMemorySize).log_info(format!(
+                    "memory run in phys_mem_block is out of range: base_page={:x} page_count={:x}",
+                    base_page, page_count
+                )),
+                )
+            }
+        };
 
-            trace!("adding memory mapping: base={:x} size={:x}", base, size);
-            mem_map.push_remap(base.into(), size as umem, Address::from(base));
+        // runs are sorted by the kernel, overlapping runs indicate a torn or invalid descriptor
+        if base < prev_end {
+            return Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::InvalidMemorySize).log_info(format!(
+                "memory run in phys_mem_block overlaps with previous run: base={:x} prev_end={:x}",
+                base, prev_end
+            )),
+            );
         }
+        prev_end = base + size;
+        total_pages += page_count;
 
-        Some(mem_map)
-    } else {
+        if size == 0 {
+            debug!("skipping empty memory run at base={:x}", base);
+            continue;
+        }
+
+        debug!("adding memory mapping: base={:x} size={:x}", base, size);
+        mem_map.push_remap(base.into(), size as umem, Address::from(base));
+    }
+
+    if total_pages != descriptor.number_of_pages.into() {
         info!(
-            "too many memory segments in phys_mem_block: {} found, {} expected",
-            descriptor.number_of_runs.into(),
-            PHYSICAL_MEMORY_MAX_RUNS
+            "page count mismatch in phys_mem_block: {} pages in runs, {} pages expected",
+            total_pages,
+            descriptor.number_of_pages.into()
         );
-        None
     }
+
+    Ok(mem_map)
 }
@@ -1,13 +1,24 @@
 use std::prelude::v1::*;
 
-use super::{Win32Kernel, Win32KernelInfo};
+use core::time::Duration;
+
+use super::kernel_info::report_stage;
+use super::{DetectionStage, DetectionStageCallback, Win32Kernel, Win32KernelInfo};
+use crate::kernel::Win32Version;
 use crate::offsets::Win32Offsets;
 
 #[cfg(feature = "symstore")]
 use crate::offsets::SymbolStore;
 
+#[cfg(feature = "offset_store")]
+use crate::offsets::{offset_builder, OffsetStore};
+#[cfg(feature = "offset_store")]
+use log::info;
+
 use crate::offsets::offset_builder_with_kernel_info;
 
+use log::{debug, warn};
+
 use memflow::architecture::ArchitectureIdent;
 use memflow::cglue::forward::ForwardMut;
 use memflow::error::Result;
@@ -123,10 +134,19 @@ pub struct Win32KernelBuilder<T, TK, VK> {
     arch: Option<ArchitectureIdent>,
     kernel_hint: Option<Address>,
     dtb: Option<Address>,
+    kernel_base: Option<Address>,
+    winver: Option<Win32Version>,
+    kernel_info: Option<Win32KernelInfo>,
+    validate: bool,
+    read_retries: usize,
+    on_stage: Option<DetectionStageCallback>,
 
     #[cfg(feature = "symstore")]
     symbol_store: Option<SymbolStore>,
 
+    #[cfg(feature = "offset_store")]
+    offset_store: Option<OffsetStore>,
+
     build_page_cache: Box<dyn FnOnce(T, ArchitectureIdent) -> TK>,
     build_vat_cache: Box<dyn FnOnce(DirectTranslate, ArchitectureIdent) -> VK>,
 }
@@ -142,10 +162,19 @@ where
             arch: None,
             kernel_hint: None,
             dtb: None,
+            kernel_base: None,
+            winver: None,
+            kernel_info: None,
+            validate: false,
+            read_retries: 0,
+            on_stage: None,
 
             #[cfg(feature = "symstore")]
             symbol_store: Some(SymbolStore::default()),
 
+            #[cfg(feature = "offset_store")]
+            offset_store: None,
+
             build_page_cache: Box::new(|connector, _| connector),
             build_vat_cache: Box::new(|vat, _| vat),
         }
@@ -159,21 +188,43 @@ where
     VK: 'static + VirtualTranslate2 + Clone,
 {
     pub fn build(mut self) -> Result<Win32Kernel<TK, VK>> {
+        let mut on_stage = self.on_stage.take();
+        let mut report = |stage: DetectionStage, elapsed: Duration| {
+            debug!("detection stage {:?} took {:?}", stage, elapsed);
+            if let Some(on_stage) = on_stage.as_mut() {
+                on_stage(stage, elapsed);
+            }
+        };
+
         // find kernel_info
-        let mut kernel_scanner = Win32KernelInfo::scanner(self.connector.forward_mut());
-        if let Some(arch) = self.arch {
-            kernel_scanner = kernel_scanner.arch(arch);
-        }
-        if let Some(kernel_hint) = self.kernel_hint {
-            kernel_scanner = kernel_scanner.kernel_hint(kernel_hint);
-        }
-        if let Some(dtb) = self.dtb {
-            kernel_scanner = kernel_scanner.dtb(dtb);
-        }
-        let kernel_info = kernel_scanner.scan()?;
+        let kernel_info = if let Some(kernel_info) = self.kernel_info.take() {
+            kernel_info
+        } else {
+            let mut kernel_scanner = Win32KernelInfo::scanner(self.connector.forward_mut());
+            if let Some(arch) = self.arch {
+                kernel_scanner = kernel_scanner.arch(arch);
+            }
+            if let Some(kernel_hint) = self.kernel_hint {
+                kernel_scanner = kernel_scanner.kernel_hint(kernel_hint);
+            }
+            if let Some(dtb) = self.dtb {
+                kernel_scanner = kernel_scanner.dtb(dtb);
+            }
+            if let Some(kernel_base) = self.kernel_base {
+                kernel_scanner = kernel_scanner.kernel_base(kernel_base);
+            }
+            if let Some(winver) = self.winver {
+                kernel_scanner = kernel_scanner.winver(winver);
+            }
+            kernel_scanner
+                .read_retries(self.read_retries)
+                .scan_reporting(&mut report)?
+        };
 
         // acquire offsets from the symbol store
-        let offsets = self.build_offsets(&kernel_info)?;
+        let offsets = report_stage(&mut report, DetectionStage::OffsetsResolve, || {
+            self.build_offsets(&kernel_info)
+        })?;
 
         // TODO: parse memory maps
 
@@ -185,16 +236,64 @@ where
         let kernel_vat = (self.build_vat_cache)(vat, kernel_info.os_info.arch);
 
         // create the final kernel object
-        Ok(Win32Kernel::new(
-            kernel_connector,
-            kernel_vat,
-            offsets,
-            kernel_info,
-        ))
+        let mut kernel = Win32Kernel::new(kernel_connector, kernel_vat, offsets, kernel_info);
+
+        // offsets of a wrong build would otherwise silently produce garbage
+        if self.validate {
+            kernel.validate()?;
+        } else if kernel.check_offsets().is_err() {
+            warn!("the resolved offsets likely do not match the running kernel");
+        }
+
+        Ok(kernel)
+    }
+
+    /// Resolves the offsets from the user supplied offset store.
+    ///
+    /// If a store is configured the offsets are resolved in the following order:
+    /// - embedded offsets with the exact guid of the kernel
+    /// - offset store (exact guid, then exact build number)
+    /// - symbol store and the closest embedded build (see `build_offsets`)
+    #[cfg(feature = "offset_store")]
+    fn build_offsets_with_offset_store(
+        &self,
+        kernel_info: &Win32KernelInfo,
+    ) -> Option<Win32Offsets> {
+        let store = self.offset_store.as_ref()?;
+
+        if let Some(guid) = &kernel_info.kernel_guid {
+            // without a winver the offset list is only matched by guid
+            let builder = offset_builder().guid(guid.clone());
+            #[cfg(feature = "symstore")]
+            let builder = builder.no_symbol_store();
+            if let Ok(offsets) = builder.build() {
+                info!("using embedded offsets for guid {}", guid.guid);
+                return Some(offsets);
+            }
+
+            if let Some(offsets) = store.lookup(guid) {
+                info!("using offsets from the offset store for guid {}", guid.guid);
+                return Some(offsets);
+            }
+        }
+
+        let build = kernel_info.kernel_winver.build_number();
+        if let Some(offsets) = store.lookup_by_build(kernel_info.os_info.arch.into(), build) {
+            info!("using offsets from the offset store for build {}", build);
+            return Some(offsets);
+        }
+
+        info!("offset store does not contain offsets for this kernel");
+        None
     }
 
     #[cfg(feature = "symstore")]
     fn build_offsets(&self, kernel_info: &Win32KernelInfo) -> Result<Win32Offsets> {
+        #[cfg(feature = "offset_store")]
+        if let Some(offsets) = self.build_offsets_with_offset_store(kernel_info) {
+            return Ok(offsets);
+        }
+
         let mut builder = offset_builder_with_kernel_info(kernel_info);
         if let Some(store) = &self.symbol_store {
             builder = builder.symbol_store(store.clone());
@@ -206,9 +305,37 @@ where
 
     #[cfg(not(feature = "symstore"))]
     fn build_offsets(&self, kernel_info: &Win32KernelInfo) -> Result<Win32Offsets> {
+        #[cfg(feature = "offset_store")]
+        if let Some(offsets) = self.build_offsets_with_offset_store(kernel_info) {
+            return Ok(offsets);
+        }
+
         offset_builder_with_kernel_info(&kernel_info).build()
     }
 
+    /// Forces the architecture of the kernel, bypassing the architecture detection.
+    ///
+    /// The given architecture is used directly for the virtual address translation
+    /// and for the selection of the kernel offsets.
+    /// If the hint does not match the target `build()` fails fast instead of returning
+    /// a kernel object that silently misbehaves.
+    ///
+    /// This option is ignored when `with_kernel_info` is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::architecture::ArchitectureIdent;
+    /// use memflow::mem::PhysicalMemory;
+    /// use memflow_win32::win32::Win32Kernel;
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone>(connector: T) {
+    ///     let _kernel = Win32Kernel::builder(connector)
+    ///         .arch(ArchitectureIdent::X86(64, false))
+    ///         .build()
+    ///         .unwrap();
+    /// }
+    /// ```
     pub fn arch(mut self, arch: ArchitectureIdent) -> Self {
         self.arch = Some(arch);
         self
@@ -224,6 +351,163 @@ where
         self
     }
 
+    /// Uses the given address as the ntoskrnl.exe base instead of searching for it.
+    ///
+    /// This skips the (potentially slow) search for the kernel image
+    /// when the base address is already known.
+    /// Building the kernel fails if no PE image is found at the given address.
+    ///
+    /// This option is ignored when `with_kernel_info` is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::mem::PhysicalMemory;
+    /// use memflow::types::Address;
+    /// use memflow_win32::win32::Win32Kernel;
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone>(connector: T) {
+    ///     let _kernel = Win32Kernel::builder(connector)
+    ///         .kernel_base(Address::from(0xfffff80072200000u64))
+    ///         .build()
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn kernel_base(mut self, kernel_base: Address) -> Self {
+        self.kernel_base = Some(kernel_base);
+        self
+    }
+
+    /// Uses the given kernel version instead of detecting it.
+    ///
+    /// The version is also used to select the offsets
+    /// if the kernel guid is not found in the embedded offsets.
+    ///
+    /// This option is ignored when `with_kernel_info` is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::mem::PhysicalMemory;
+    /// use memflow_win32::kernel::Win32Version;
+    /// use memflow_win32::win32::Win32Kernel;
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone>(connector: T) {
+    ///     let _kernel = Win32Kernel::builder(connector)
+    ///         .winver(Win32Version::new(10, 0, 19041))
+    ///         .build()
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn winver(mut self, winver: Win32Version) -> Self {
+        self.winver = Some(winver);
+        self
+    }
+
+    /// Registers a callback that is invoked after each kernel detection stage.
+    ///
+    /// The callback receives the stage and the time spent in it.
+    /// Stages are reported even if they fail, skipped stages are not reported.
+    /// Without the `std` feature the elapsed time is always zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::mem::PhysicalMemory;
+    /// use memflow_win32::win32::Win32Kernel;
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone>(connector: T) {
+    ///     let _kernel = Win32Kernel::builder(connector)
+    ///         .on_stage(|stage, elapsed| println!("{:?} took {:?}", stage, elapsed))
+    ///         .build()
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn on_stage(
+        mut self,
+        callback: impl FnMut(DetectionStage, Duration) + Send + 'static,
+    ) -> Self {
+        self.on_stage = Some(Box::new(callback));
+        self
+    }
+
+    /// Uses a previously retrieved `Win32KernelInfo` instead of scanning for the kernel.
+    ///
+    /// This allows the (potentially slow) detection phase to be skipped entirely
+    /// when attaching to a target whose kernel info has been obtained before,
+    /// e.g. via `Win32Kernel::save_info()` and a serialized copy of it.
+    ///
+    /// When this option is set the `arch`, `kernel_hint`, `dtb`, `kernel_base` and `winver` options are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::mem::PhysicalMemory;
+    /// use memflow_win32::win32::{Win32Kernel, Win32KernelInfo};
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone>(connector: T, kernel_info: Win32KernelInfo) {
+    ///     let _kernel = Win32Kernel::builder(connector)
+    ///         .with_kernel_info(kernel_info)
+    ///         .build()
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn with_kernel_info(mut self, kernel_info: Win32KernelInfo) -> Self {
+        self.kernel_info = Some(kernel_info);
+        self
+    }
+
+    /// Retries failed physical reads up to `read_retries` times while scanning for the kernel.
+    ///
+    /// This makes the detection phase more resilient on unreliable hardware
+    /// (e.g. DMA devices) where single reads may fail sporadically.
+    /// Reads issued after `build()` has returned are not affected.
+    ///
+    /// This option has no effect when `with_kernel_info` is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::mem::PhysicalMemory;
+    /// use memflow_win32::win32::Win32Kernel;
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone>(connector: T) {
+    ///     let _kernel = Win32Kernel::builder(connector)
+    ///         .read_retries(3)
+    ///         .build()
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn read_retries(mut self, read_retries: usize) -> Self {
+        self.read_retries = read_retries;
+        self
+    }
+
+    /// Validates the kernel after it has been constructed.
+    ///
+    /// When enabled `build()` runs `Win32Kernel::validate` and returns its error
+    /// if the kernel is not usable, instead of failing on the first read later on.
+    ///
+    /// Without validation `build()` only logs a warning if the offsets fail `Win32Kernel::check_offsets`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::mem::PhysicalMemory;
+    /// use memflow_win32::win32::Win32Kernel;
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone>(connector: T) {
+    ///     let _kernel = Win32Kernel::builder(connector)
+    ///         .validate(true)
+    ///         .build()
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
     /// Configures the symbol store to be used when constructing the Kernel.
     /// This will override the default symbol store that is being used if no other setting is configured.
     ///
@@ -271,6 +555,37 @@ where
         self
     }
 
+    /// Configures an offset store that is consulted when the embedded offsets
+    /// do not contain the guid of the target kernel.
+    ///
+    /// The offsets are resolved in the following order:
+    /// - embedded offsets with the exact guid of the kernel
+    /// - the given offset store (exact guid, then exact build number)
+    /// - the symbol store (pdb download)
+    /// - the closest embedded build
+    ///
+    /// The source that was used is logged at info level.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::mem::PhysicalMemory;
+    /// use memflow_win32::win32::Win32Kernel;
+    /// use memflow_win32::offsets::OffsetStore;
+    ///
+    /// fn test<T: 'static + PhysicalMemory + Clone>(connector: T) {
+    ///     let _kernel = Win32Kernel::builder(connector)
+    ///         .offset_store(OffsetStore::from_dir("./offsets").unwrap())
+    ///         .build()
+    ///         .unwrap();
+    /// }
+    /// ```
+    #[cfg(feature = "offset_store")]
+    pub fn offset_store(mut self, offset_store: OffsetStore) -> Self {
+        self.offset_store = Some(offset_store);
+        self
+    }
+
     /// Creates the Kernel structure with default caching enabled.
     ///
     /// If this option is specified, the Kernel structure is generated
@@ -303,10 +618,19 @@ where
             arch: self.arch,
             kernel_hint: self.kernel_hint,
             dtb: self.dtb,
+            kernel_base: self.kernel_base,
+            winver: self.winver,
+            kernel_info: self.kernel_info,
+            validate: self.validate,
+            read_retries: self.read_retries,
+            on_stage: self.on_stage,
 
             #[cfg(feature = "symstore")]
             symbol_store: self.symbol_store,
 
+            #[cfg(feature = "offset_store")]
+            offset_store: self.offset_store,
+
             build_page_cache: Box::new(|connector, arch| {
                 CachedPhysicalMemory::builder(connector)
                     .arch(arch)
@@ -358,10 +682,19 @@ where
             arch: self.arch,
             kernel_hint: self.kernel_hint,
             dtb: self.dtb,
+            kernel_base: self.kernel_base,
+            winver: self.winver,
+            kernel_info: self.kernel_info,
+            validate: self.validate,
+            read_retries: self.read_retries,
+            on_stage: self.on_stage,
 
             #[cfg(feature = "symstore")]
             symbol_store: self.symbol_store,
 
+            #[cfg(feature = "offset_store")]
+            offset_store: self.offset_store,
+
             build_page_cache: Box::new(func),
             build_vat_cache: self.build_vat_cache,
         }
@@ -403,10 +736,19 @@ where
             arch: self.arch,
             kernel_hint: self.kernel_hint,
             dtb: self.dtb,
+            kernel_base: self.kernel_base,
+            winver: self.winver,
+            kernel_info: self.kernel_info,
+            validate: self.validate,
+            read_retries: self.read_retries,
+            on_stage: self.on_stage,
 
             #[cfg(feature = "symstore")]
             symbol_store: self.symbol_store,
 
+            #[cfg(feature = "offset_store")]
+            offset_store: self.offset_store,
+
             build_page_cache: self.build_page_cache,
             build_vat_cache: Box::new(func),
         }
@@ -1,5 +1,10 @@
+use std::prelude::v1::*;
+
+use crate::kernel::retry::RetryPhysicalMemory;
 use crate::kernel::{self, StartBlock};
-use crate::kernel::{Win32Guid, Win32Version};
+use crate::kernel::{Win32Guid, Win32Version, Win32VersionSource};
+
+use core::time::Duration;
 
 use log::{info, warn};
 
@@ -15,13 +20,16 @@ use super::Win32VirtualTranslate;
 use crate::offsets::Win32OffsetBuilder;
 
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub struct Win32KernelInfo {
     pub os_info: OsInfo,
     pub dtb: Address,
 
     pub kernel_guid: Option<Win32Guid>,
     pub kernel_winver: Win32Version,
+    /// Method that was used to determine `kernel_winver`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kernel_winver_source: Win32VersionSource,
 
     pub eprocess_base: Address,
 }
@@ -51,11 +59,50 @@ impl Win32KernelInfo {
     }
 }
 
+/// Stage of the kernel detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectionStage {
+    /// Search for the start block containing the architecture and the initial dtb
+    ArchDetect,
+    /// Search for the ntoskrnl.exe base address
+    KernelScan,
+    /// Fallback scan of the physical memory for the dtb
+    DtbScan,
+    /// Detection of the kernel version
+    WinverDetect,
+    /// Resolution of the kernel offsets
+    OffsetsResolve,
+}
+
+/// Callback that is invoked after each detection stage with the time spent in it
+pub type DetectionStageCallback = Box<dyn FnMut(DetectionStage, Duration) + Send>;
+
+/// Runs `f` and reports the elapsed time of the given stage, regardless of the result.
+///
+/// The elapsed time is always zero without the `std` feature.
+pub(crate) fn report_stage<R>(
+    on_stage: &mut dyn FnMut(DetectionStage, Duration),
+    stage: DetectionStage,
+    f: impl FnOnce() -> R,
+) -> R {
+    #[cfg(feature = "std")]
+    let start = std::time::Instant::now();
+    let result = f();
+    #[cfg(feature = "std")]
+    on_stage(stage, start.elapsed());
+    #[cfg(not(feature = "std"))]
+    on_stage(stage, Duration::default());
+    result
+}
+
 pub struct KernelInfoScanner<T> {
     mem: T,
     arch: Option<ArchitectureIdent>,
     kernel_hint: Option<Address>,
     dtb: Option<Address>,
+    kernel_base: Option<Address>,
+    winver: Option<Win32Version>,
+    read_retries: usize,
 }
 
 impl<T: PhysicalMemory> KernelInfoScanner<T> {
@@ -65,10 +112,42 @@ impl<T: PhysicalMemory> KernelInfoScanner<T> {
             arch: None,
             kernel_hint: None,
             dtb: None,
+            kernel_base: None,
+            winver: None,
+            read_retries: 0,
         }
     }
 
-    pub fn scan(mut self) -> Result<Win32KernelInfo> {
+    /// Scans for the kernel and returns the resulting `Win32KernelInfo`.
+    ///
+    /// If an architecture has been set via `arch()` no other architectures are probed.
+    /// The scan fails if the kernel cannot be found with the given architecture.
+    pub fn scan(self) -> Result<Win32KernelInfo> {
+        self.scan_reporting(&mut |_, _| {})
+    }
+
+    /// Scans for the kernel and reports the time spent in each detection stage.
+    pub(crate) fn scan_reporting(
+        mut self,
+        on_stage: &mut dyn FnMut(DetectionStage, Duration),
+    ) -> Result<Win32KernelInfo> {
+        let arch = self.arch;
+        self.scan_start_blocks(on_stage).map_err(|err| match arch {
+            Some(arch) => err.log_error(format!(
+                "unable to find the kernel with the given architecture {:?}",
+                arch
+            )),
+            None => err,
+        })
+    }
+
+    fn scan_start_blocks(
+        &mut self,
+        on_stage: &mut dyn FnMut(DetectionStage, Duration),
+    ) -> Result<Win32KernelInfo> {
+        // failed reads are only retried during detection, the connector itself stays untouched
+        let mut mem = RetryPhysicalMemory::new(self.mem.forward_mut(), self.read_retries);
+
         let start_block = if let (Some(arch), Some(dtb), Some(kernel_hint)) =
             (self.arch, self.dtb, self.kernel_hint)
         {
@@ -79,21 +158,70 @@ impl<T: PhysicalMemory> KernelInfoScanner<T> {
                 dtb,
             }
         } else {
-            let mut sb = kernel::start_block::find(&mut self.mem, self.arch)?;
-            if self.kernel_hint.is_some() && sb.kernel_hint.is_null() {
-                sb.kernel_hint = self.kernel_hint.unwrap()
+            let arch = self.arch;
+            let mut sb = report_stage(on_stage, DetectionStage::ArchDetect, || {
+                kernel::start_block::find(&mut mem, arch)
+            })?;
+            if let (Some(kernel_hint), true) = (self.kernel_hint, sb.kernel_hint.is_null()) {
+                sb.kernel_hint = kernel_hint
             }
             // dtb is always set in start_block::find()
             sb
         };
+        // a user supplied arch always takes precedence over the detected one
+        let start_block = StartBlock {
+            arch: self.arch.unwrap_or(start_block.arch),
+            ..start_block
+        };
 
-        self.scan_block(start_block).or_else(|_| {
-            let start_block = kernel::start_block::find_fallback(&mut self.mem, start_block.arch)?;
-            self.scan_block(start_block)
-        })
+        let (kernel_base, winver) = (self.kernel_base, self.winver);
+        Self::scan_block(&mut mem, start_block, kernel_base, winver, on_stage)
+            .or_else(|err| {
+                // the supplied or detected start block might be missing or stale,
+                // try to recover the kernel dtb from the x64 low stub first
+                if !matches!(start_block.arch, ArchitectureIdent::X86(64, _)) {
+                    return Err(err);
+                }
+                let lowstub = kernel::start_block::find_lowstub(&mut mem)?;
+                if lowstub.dtb == start_block.dtb {
+                    return Err(err);
+                }
+                info!(
+                    "start block unusable, recovered kernel dtb {:x} from the low stub",
+                    lowstub.dtb
+                );
+                let start_block = StartBlock {
+                    kernel_hint: if start_block.kernel_hint.is_null() {
+                        lowstub.kernel_hint
+                    } else {
+                        start_block.kernel_hint
+                    },
+                    ..lowstub
+                };
+                Self::scan_block(&mut mem, start_block, kernel_base, winver, on_stage)
+            })
+            .or_else(|_| {
+                let mut start_block = report_stage(on_stage, DetectionStage::DtbScan, || {
+                    kernel::start_block::find_fallback(&mut mem, start_block.arch)
+                })?;
+                start_block.arch = self.arch.unwrap_or(start_block.arch);
+                // aarch64 targets can only be scanned with a va hint so we have to preserve it here
+                if let (Some(kernel_hint), true) =
+                    (self.kernel_hint, start_block.kernel_hint.is_null())
+                {
+                    start_block.kernel_hint = kernel_hint
+                }
+                Self::scan_block(&mut mem, start_block, kernel_base, winver, on_stage)
+            })
     }
 
-    fn scan_block(&mut self, start_block: StartBlock) -> Result<Win32KernelInfo> {
+    fn scan_block<M: PhysicalMemory>(
+        mem: &mut M,
+        start_block: StartBlock,
+        kernel_base: Option<Address>,
+        winver: Option<Win32Version>,
+        on_stage: &mut dyn FnMut(DetectionStage, Duration),
+    ) -> Result<Win32KernelInfo> {
         info!(
             "arch={:?} kernel_hint={:x} dtb={:x}",
             start_block.arch, start_block.kernel_hint, start_block.dtb
@@ -101,29 +229,48 @@ impl<T: PhysicalMemory> KernelInfoScanner<T> {
 
         // construct virtual memory object for start_block
         let mut virt_mem = VirtualDma::with_vat(
-            self.mem.forward_mut(),
+            mem.forward_mut(),
             start_block.arch,
             Win32VirtualTranslate::new(start_block.arch, start_block.dtb),
             DirectTranslate::new(),
         );
 
         // find ntoskrnl.exe base
-        let (base, size) = kernel::ntos::find(&mut virt_mem, &start_block)?;
+        let (base, size) = report_stage(on_stage, DetectionStage::KernelScan, || {
+            Result::Ok(match kernel_base {
+                Some(base) => (
+                    base,
+                    kernel::ntos::pehelper::try_get_pe_size(&mut virt_mem, base)?,
+                ),
+                None => kernel::ntos::find(&mut virt_mem, &start_block)?,
+            })
+        })?;
         info!("base={} size={}", base, size);
 
         // get ntoskrnl.exe guid
         let kernel_guid = kernel::ntos::find_guid(&mut virt_mem, base).ok();
         info!("kernel_guid={:?}", kernel_guid);
 
-        let kernel_winver = kernel::ntos::find_winver(&mut virt_mem, base).ok();
-
-        if kernel_winver.is_none() {
-            warn!("Failed to retrieve kernel version! Some features may be disabled.");
-        }
-
-        let kernel_winver = kernel_winver.unwrap_or_else(|| Win32Version::new(3, 10, 511));
+        let (kernel_winver, kernel_winver_source) =
+            report_stage(on_stage, DetectionStage::WinverDetect, || match winver {
+                Some(winver) => (winver, Win32VersionSource::UserSupplied),
+                None => match kernel::ntos::find_winver_with_source(
+                    &mut virt_mem,
+                    base,
+                    start_block.arch,
+                ) {
+                    Ok(winver) => winver,
+                    Err(_) => {
+                        warn!("Failed to retrieve kernel version! Some features may be disabled.");
+                        (Win32Version::new(3, 10, 511), Win32VersionSource::Unknown)
+                    }
+                },
+            });
 
-        info!("kernel_winver={:?}", kernel_winver);
+        info!(
+            "kernel_winver={:?} kernel_winver_source={:?}",
+            kernel_winver, kernel_winver_source
+        );
 
         // find eprocess base
         let eprocess_base = kernel::sysproc::find(&mut virt_mem, &start_block, base)?;
@@ -146,11 +293,16 @@ impl<T: PhysicalMemory> KernelInfoScanner<T> {
 
             kernel_guid,
             kernel_winver,
+            kernel_winver_source,
 
             eprocess_base,
         })
     }
 
+    /// Forces the kernel architecture instead of detecting it.
+    ///
+    /// The given architecture is used directly for the page table walk and the offset selection.
+    /// A wrong architecture causes `scan()` to fail rather than to return a bogus result.
     pub fn arch(mut self, arch: ArchitectureIdent) -> Self {
         self.arch = Some(arch);
         self
@@ -165,4 +317,64 @@ impl<T: PhysicalMemory> KernelInfoScanner<T> {
         self.dtb = Some(dtb);
         self
     }
+
+    /// Uses the given address as the ntoskrnl.exe base instead of searching for it.
+    ///
+    /// The scan fails if no PE image is found at the given address.
+    pub fn kernel_base(mut self, kernel_base: Address) -> Self {
+        self.kernel_base = Some(kernel_base);
+        self
+    }
+
+    /// Uses the given kernel version instead of detecting it.
+    pub fn winver(mut self, winver: Win32Version) -> Self {
+        self.winver = Some(winver);
+        self
+    }
+
+    /// Sets the number of times a failed physical read is retried during the scan.
+    ///
+    /// Each retry is delayed by a short, exponentially increasing backoff.
+    /// A value of 0 (the default) disables retries entirely.
+    pub fn read_retries(mut self, read_retries: usize) -> Self {
+        self.read_retries = read_retries;
+        self
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_round_trip() {
+        let info = Win32KernelInfo {
+            os_info: OsInfo {
+                base: Address::from(0xfffff800_1e400000u64),
+                size: 0x1046000,
+                arch: ArchitectureIdent::X86(64, false),
+            },
+            dtb: Address::from(0x1ad000u64),
+            kernel_guid: Some(Win32Guid::new(
+                "ntkrnlmp.pdb",
+                "3844DBB920174967BE7AA4A2C20430FA1",
+            )),
+            kernel_winver: Win32Version::from((10, 0, 19041)),
+            kernel_winver_source: Win32VersionSource::RtlGetVersion,
+            eprocess_base: Address::from(0xfffff800_1ec1c000u64),
+        };
+
+        let json = serde_json::to_string(&info).unwrap();
+        let parsed: Win32KernelInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", info));
+
+        // snapshots taken before the version source was recorded
+        let mut value = serde_json::to_value(&info).unwrap();
+        value
+            .as_object_mut()
+            .unwrap()
+            .remove("kernel_winver_source");
+        let parsed: Win32KernelInfo = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.kernel_winver_source, Win32VersionSource::Unknown);
+    }
 }
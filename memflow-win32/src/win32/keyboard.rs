@@ -6,6 +6,7 @@ This array will internally be read by the [`GetAsyncKeyState()`](https://docs.mi
 
 Although the gafAsyncKeyState array is exported by the win32kbase.sys kernel module it is only properly mapped into user mode processes.
 Therefor the Keyboard will by default find the winlogon.exe or wininit.exe process and use it as a proxy to read the data.
+Since the key state is resolved for the native architecture of the system, 32-bit (wow64) proxy processes are skipped.
 
 # Examples:
 
@@ -27,37 +28,279 @@ fn test<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Cl
 }
 ```
 */
+use std::fmt;
+use std::prelude::v1::*;
+
+use super::scan::{scan_rip_relative, PlainScanner, Scanner};
+use super::sigscan::Signature;
 use super::{Win32Kernel, Win32ProcessInfo, Win32VirtualTranslate};
 
+use crate::ida_signature;
+use memflow::architecture::ArchitectureObj;
 use memflow::cglue::*;
 use memflow::error::PartialResultExt;
 use memflow::error::{Error, ErrorKind, ErrorOrigin, Result};
 use memflow::mem::{MemoryView, PhysicalMemory, VirtualDma, VirtualTranslate2};
 use memflow::os::keyboard::*;
-use memflow::prelude::{ExportInfo, ModuleInfo, Os, Pid, Process};
+use memflow::prelude::{ModuleInfo, Os, Pid, Process};
 use memflow::types::{umem, Address};
 
+#[cfg(feature = "std")]
+use core::time::Duration;
+
 #[cfg(feature = "plugins")]
 use memflow::cglue;
 
-use log::debug;
-use std::convert::TryInto;
+use log::{debug, warn};
 
 #[cfg(feature = "plugins")]
 cglue_impl_group!(Win32Keyboard<T>, IntoKeyboard);
 
+/// Processes that are used as a proxy to access the session space containing the key state
+///
+/// Proxy processes are expected to run natively, 32-bit (wow64) processes are skipped.
+const PROXY_PROCESS_NAMES: &[&str] = &[
+    "winlogon.exe",
+    "explorer.exe",
+    "taskhostw.exe",
+    "smartscreen.exe",
+    "dwm.exe",
+];
+
+/// Reason why the keyboard state could not be located
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Win32KeyboardError {
+    /// None of the candidate proxy processes could be used to access the key state
+    ProxyNotFound,
+    /// The module holding the key state is not loaded or not mapped in the proxy process
+    ModuleNotMapped,
+    /// The key state was neither exported nor found via its signature
+    SignatureNotFound,
+    /// The session global slots of win32ksgd.sys could not be dereferenced
+    SessionSlotInvalid,
+}
+
+impl Win32KeyboardError {
+    /// Returns a short description of the error
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Win32KeyboardError::ProxyNotFound => {
+                "unable to find any proxy process that contains gafAsyncKeyState"
+            }
+            Win32KeyboardError::ModuleNotMapped => "keyboard module is not mapped",
+            Win32KeyboardError::SignatureNotFound => "unable to find gafAsyncKeyState signature",
+            Win32KeyboardError::SessionSlotInvalid => "invalid gSessionGlobalSlots entry",
+        }
+    }
+}
+
+impl fmt::Display for Win32KeyboardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Win32KeyboardError {}
+
+impl From<Win32KeyboardError> for Error {
+    fn from(err: Win32KeyboardError) -> Self {
+        let kind = match err {
+            Win32KeyboardError::ProxyNotFound => ErrorKind::ProcessNotFound,
+            Win32KeyboardError::ModuleNotMapped => ErrorKind::ModuleNotFound,
+            Win32KeyboardError::SignatureNotFound => ErrorKind::ExportNotFound,
+            Win32KeyboardError::SessionSlotInvalid => ErrorKind::InvalidProcessInfo,
+        };
+        Error(ErrorOrigin::OsLayer, kind).log_info(err.as_str())
+    }
+}
+
+/// Signature that references the key state via a rip relative instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Win32KeyStateSignature {
+    signature: PlainScanner,
+    displacement_offset: usize,
+}
+
+impl Win32KeyStateSignature {
+    /// Creates a new signature from an IDA-style pattern.
+    ///
+    /// The displacement has to be followed by the end of the instruction.
+    pub fn new(ida_pattern: &str, displacement_offset: usize) -> Result<Self> {
+        Self::from_signature(Signature::parse(ida_pattern)?, displacement_offset)
+    }
+
+    /// Creates a new signature from a parsed pattern, e.g. from the `ida_signature!` macro.
+    ///
+    /// The displacement has to be followed by the end of the instruction.
+    pub fn from_signature(signature: Signature, displacement_offset: usize) -> Result<Self> {
+        let signature = PlainScanner::from(signature);
+        if displacement_offset + 4 > signature.pattern_len() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_info("displacement exceeds the signature"));
+        }
+        Ok(Self {
+            signature,
+            displacement_offset,
+        })
+    }
+
+    /// Signature of the gafAsyncKeyState reference in win32kbase.sys
+    pub fn gaf_async_key_state() -> Self {
+        // 48 8B 05 ? ? ? ? 48 89 81 ? ? 00 00 48 8B 8F + 0x3
+        Self::from_signature(
+            ida_signature!("48 8B 05 [?;4] 48 89 81 [?;2] 00 00 48 8B 8F"),
+            0x3,
+        )
+        .unwrap()
+    }
+
+    /// Returns the instruction pattern
+    pub fn signature(&self) -> &PlainScanner {
+        &self.signature
+    }
+
+    /// Returns the offset of the 32-bit rip relative displacement in the pattern
+    pub fn displacement_offset(&self) -> usize {
+        self.displacement_offset
+    }
+
+    /// Finds the signature in the module image and returns the offset
+    /// of the referenced address relative to the module base.
+    pub fn find(&self, module_buf: &[u8]) -> Option<umem> {
+        scan_rip_relative(&self.signature, module_buf, self.displacement_offset)
+    }
+}
+
+/// Describes where the key state is looked up when constructing a `Win32Keyboard`
+#[derive(Debug, Clone)]
+pub struct Win32KeyStateSource {
+    /// Name of the kernel module containing the key state
+    pub module_name: String,
+    /// Signatures that are tried in order if the module does not export `gafAsyncKeyState`
+    pub signatures: Vec<Win32KeyStateSignature>,
+    /// Tries the session global slots of win32ksgd.sys first if the driver is loaded
+    pub session_global_slots: bool,
+    /// Session whose key state is read (starting at 1)
+    pub session_id: u32,
+    /// Processes that are tried in order as a proxy to access the session space
+    ///
+    /// Processes running under wow64 or in a session other than `session_id` are skipped.
+    pub proxy_processes: Vec<String>,
+}
+
+impl Default for Win32KeyStateSource {
+    /// Looks up gafAsyncKeyState in win32kbase.sys
+    fn default() -> Self {
+        Self {
+            module_name: "win32kbase.sys".to_string(),
+            signatures: vec![Win32KeyStateSignature::gaf_async_key_state()],
+            session_global_slots: true,
+            session_id: 1,
+            proxy_processes: PROXY_PROCESS_NAMES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl Win32KeyStateSource {
+    /// Creates a new source for the given module without any signatures.
+    pub fn new(module_name: &str) -> Self {
+        Self {
+            module_name: module_name.to_string(),
+            signatures: Vec::new(),
+            session_global_slots: false,
+            session_id: 1,
+            proxy_processes: PROXY_PROCESS_NAMES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Adds a signature that is tried if the module does not export `gafAsyncKeyState`.
+    pub fn signature(mut self, signature: Win32KeyStateSignature) -> Self {
+        self.signatures.push(signature);
+        self
+    }
+
+    /// Selects the session whose key state is read.
+    ///
+    /// Session ids start at 1 which is the first interactive session.
+    pub fn session_id(mut self, session_id: u32) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    /// Replaces the list of processes that are tried as a proxy.
+    pub fn proxy_processes(mut self, names: &[&str]) -> Self {
+        self.proxy_processes = names.iter().map(|s| s.to_string()).collect();
+        self
+    }
+}
+
 /// Interface for accessing the target's keyboard state.
 #[derive(Clone, Debug)]
 pub struct Win32Keyboard<T> {
     pub virt_mem: T,
     key_state_addr: Address,
+    throttle: KeyStateThrottle,
+}
+
+/// Caches the key state to rate limit re-reads from the target.
+///
+/// Without the `std` feature every call reads from the target.
+#[derive(Clone, Debug, Default)]
+struct KeyStateThrottle {
+    #[cfg(feature = "std")]
+    min_interval: Duration,
+    #[cfg(feature = "std")]
+    last_read: Option<(std::time::Instant, [u8; 256 * 2 / 8])>,
+}
+
+impl KeyStateThrottle {
+    /// Returns the cached key state if it is younger than the minimum interval,
+    /// otherwise the key state is re-read with `read`.
+    fn read(
+        &mut self,
+        read: impl FnOnce() -> Result<[u8; 256 * 2 / 8]>,
+    ) -> Result<[u8; 256 * 2 / 8]> {
+        #[cfg(feature = "std")]
+        {
+            if let Some((time, buffer)) = self.last_read {
+                if time.elapsed() < self.min_interval {
+                    return Ok(buffer);
+                }
+            }
+            let buffer = read()?;
+            if !self.min_interval.is_zero() {
+                self.last_read = Some((std::time::Instant::now(), buffer));
+            }
+            Ok(buffer)
+        }
+        #[cfg(not(feature = "std"))]
+        read()
+    }
+
+    fn invalidate(&mut self) {
+        #[cfg(feature = "std")]
+        {
+            self.last_read = None;
+        }
+    }
 }
 
 impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone>
     Win32Keyboard<VirtualDma<T, V, Win32VirtualTranslate>>
 {
-    pub fn with_kernel(mut kernel: Win32Kernel<T, V>) -> Result<Self> {
-        let (user_process_info, key_state_addr) = Self::find_keystate(&mut kernel)?;
+    pub fn with_kernel(kernel: Win32Kernel<T, V>) -> Result<Self> {
+        Self::with_kernel_source(kernel, &Win32KeyStateSource::default())
+    }
+
+    /// Constructs a new keyboard object which looks up the key state in the given source.
+    ///
+    /// This allows reading the key state from other modules than win32kbase.sys (e.g. win32k.sys).
+    pub fn with_kernel_source(
+        mut kernel: Win32Kernel<T, V>,
+        source: &Win32KeyStateSource,
+    ) -> Result<Self> {
+        let (user_process_info, key_state_addr) = Self::try_find_keystate(&mut kernel, source)?;
 
         let (phys_mem, vat) = kernel.virt_mem.into_inner();
         let virt_mem = VirtualDma::with_vat(
@@ -70,6 +313,7 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
         Ok(Self {
             virt_mem,
             key_state_addr,
+            throttle: KeyStateThrottle::default(),
         })
     }
 
@@ -77,6 +321,43 @@ impl<T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone
     pub fn into_inner(self) -> (T, V) {
         self.virt_mem.into_inner()
     }
+
+    /// Returns all processes that are considered as a proxy for accessing the key state.
+    ///
+    /// Each entry contains the pid and name of the process and whether
+    /// win32kbase.sys is mapped in the address space of the process.
+    /// This is useful to diagnose why the keyboard could not be found.
+    pub fn list_proxy_candidates(kernel: &mut Win32Kernel<T, V>) -> Vec<(Pid, String, bool)> {
+        let win32kbase_module_info = kernel.module_by_name("win32kbase.sys").ok();
+
+        let procs = match kernel.process_info_list() {
+            Ok(procs) => procs,
+            Err(err) => {
+                debug!("unable to retrieve the process list: {}", err);
+                return Vec::new();
+            }
+        };
+
+        procs
+            .into_iter()
+            .filter(|p| PROXY_PROCESS_NAMES.contains(&p.name.as_ref()))
+            .map(|p| {
+                let mapped = win32kbase_module_info
+                    .as_ref()
+                    .map(|module_info| {
+                        kernel
+                            .process_by_info(p.clone())
+                            .and_then(|mut process| {
+                                process.virt_mem.read::<[u8; 2]>(module_info.base).data()
+                            })
+                            .map(|mz| &mz == b"MZ")
+                            .unwrap_or(false)
+                    })
+                    .unwrap_or(false);
+                (p.pid, p.name.to_string(), mapped)
+            })
+            .collect()
+    }
 }
 
 impl<'a, T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + Clone>
@@ -92,7 +373,26 @@ impl<'a, T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + C
     /// When u need a cloneable Process u have to use the `::with_kernel` function
     /// which will move the kernel object.
     pub fn with_kernel_ref(kernel: &'a mut Win32Kernel<T, V>) -> Result<Self> {
-        let (user_process_info, key_state_addr) = Self::find_keystate(kernel)?;
+        Ok(Self::try_with_kernel_ref(kernel)?)
+    }
+
+    /// Constructs a new keyboard object by borrowing a kernel object.
+    ///
+    /// This behaves like `::with_kernel_ref` but returns the reason why the
+    /// key state could not be located, allowing callers to handle each failure separately.
+    pub fn try_with_kernel_ref(
+        kernel: &'a mut Win32Kernel<T, V>,
+    ) -> std::result::Result<Self, Win32KeyboardError> {
+        Self::try_with_kernel_ref_source(kernel, &Win32KeyStateSource::default())
+    }
+
+    /// Constructs a new keyboard object by borrowing a kernel object
+    /// which looks up the key state in the given source.
+    pub fn try_with_kernel_ref_source(
+        kernel: &'a mut Win32Kernel<T, V>,
+        source: &Win32KeyStateSource,
+    ) -> std::result::Result<Self, Win32KeyboardError> {
+        let (user_process_info, key_state_addr) = Self::try_find_keystate(kernel, source)?;
 
         let (phys_mem, vat) = kernel.virt_mem.mem_vat_pair();
         let virt_mem = VirtualDma::with_vat(
@@ -105,17 +405,22 @@ impl<'a, T: 'static + PhysicalMemory + Clone, V: 'static + VirtualTranslate2 + C
         Ok(Self {
             virt_mem,
             key_state_addr,
+            throttle: KeyStateThrottle::default(),
         })
     }
 }
 
 impl<T> Win32Keyboard<T> {
-    fn find_keystate<
+    /// Locates the key state of the target.
+    ///
+    /// If all proxy processes fail the error of the last proxy process is returned.
+    fn try_find_keystate<
         P: 'static + PhysicalMemory + Clone,
         V: 'static + VirtualTranslate2 + Clone,
     >(
         kernel: &mut Win32Kernel<P, V>,
-    ) -> Result<(Win32ProcessInfo, Address)> {
+        source: &Win32KeyStateSource,
+    ) -> std::result::Result<(Win32ProcessInfo, Address), Win32KeyboardError> {
         /*
         ref: https://www.unknowncheats.me/forum/3359384-post23.html
         Since Win11, key state bitmap has been moved into win32ksgd.sys
@@ -156,27 +461,51 @@ impl<T> Win32Keyboard<T> {
         To replicate this via DRM, we need to find our session's gSessionGlobalSlot, dereference the pointer three times, and add the 0x3690 hardcoded offset.
 
         */
-        let win32kbase_module_info = kernel.module_by_name("win32kbase.sys")?;
-        debug!("found win32kbase.sys: {:?}", win32kbase_module_info);
-
-        let procs = kernel.process_info_list()?;
-
-        let gaf = procs
+        let module_info = kernel.module_by_name(&source.module_name).map_err(|err| {
+            debug!("unable to find {}: {}", source.module_name, err);
+            Win32KeyboardError::ModuleNotMapped
+        })?;
+        debug!("found {}: {:?}", source.module_name, module_info);
+
+        let procs = kernel.process_info_list().map_err(|err| {
+            debug!("unable to retrieve the process list: {}", err);
+            Win32KeyboardError::ProxyNotFound
+        })?;
+
+        let mut last_err = Win32KeyboardError::ProxyNotFound;
+        for p in procs
             .iter()
-            .filter(|p| {
-                p.name.as_ref() == "winlogon.exe"
-                    || p.name.as_ref() == "explorer.exe"
-                    || p.name.as_ref() == "taskhostw.exe"
-                    || p.name.as_ref() == "smartscreen.exe"
-                    || p.name.as_ref() == "dwm.exe"
-            })
-            .find_map(|p| Self::find_in_user_process(kernel, &win32kbase_module_info, p.pid).ok())
-            .ok_or_else(|| {
-                Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound)
-                    .log_info("unable to find any proxy process that contains gafAsyncKeyState")
-            })?;
+            .filter(|p| source.proxy_processes.iter().any(|n| n == p.name.as_ref()))
+        {
+            // the session space mapped into the proxy decides which session's key state is read
+            match kernel.process_session_id(p.address) {
+                Ok(session_id) if session_id != source.session_id => {
+                    debug!(
+                        "skipping proxy process `{}` of session {}",
+                        p.name.as_ref(),
+                        session_id
+                    );
+                    continue;
+                }
+                Ok(_) => (),
+                Err(err) => warn!(
+                    "unable to determine the session of proxy process `{}` ({}), session {} might not be honored",
+                    p.name.as_ref(),
+                    err,
+                    source.session_id
+                ),
+            }
+
+            match Self::find_in_user_process(kernel, source, &module_info, p.pid) {
+                Ok(gaf) => return Ok(gaf),
+                Err(err) => {
+                    debug!("proxy process `{}` failed: {:?}", p.name.as_ref(), err);
+                    last_err = err;
+                }
+            }
+        }
 
-        Ok((gaf.0, gaf.1))
+        Err(last_err)
     }
 
     fn find_in_user_process<
@@ -184,158 +513,166 @@ impl<T> Win32Keyboard<T> {
         V: 'static + VirtualTranslate2 + Clone,
     >(
         kernel: &mut Win32Kernel<P, V>,
-        win32kbase_module_info: &ModuleInfo,
+        source: &Win32KeyStateSource,
+        module_info: &ModuleInfo,
         pid: Pid,
-    ) -> Result<(Win32ProcessInfo, Address)> {
-        let user_process_info = kernel.process_info_by_pid(pid)?;
-        let user_process_info_win32 =
-            kernel.process_info_from_base_info(user_process_info.clone())?;
+    ) -> std::result::Result<(Win32ProcessInfo, Address), Win32KeyboardError> {
+        let user_process_info = kernel
+            .process_info_by_pid(pid)
+            .map_err(|_| Win32KeyboardError::ProxyNotFound)?;
+        let user_process_info_win32 = kernel
+            .process_info_from_base_info(user_process_info.clone())
+            .map_err(|_| Win32KeyboardError::ProxyNotFound)?;
+
+        // the key state is only mapped and resolved for the native architecture of the system,
+        // a wow64 process would see a different layout of the session space
+        if user_process_info_win32.is_wow64() {
+            debug!(
+                "skipping wow64 proxy process `{}`",
+                user_process_info.name.as_ref()
+            );
+            return Err(Win32KeyboardError::ProxyNotFound);
+        }
 
         // Win32k temporary session global driver was first introduced in 22H2 (10.0.22621.1) (2022-09-20)
-        // so we cannot be sure it will be active on all Win11 devices
-        if kernel.kernel_info.kernel_winver >= (10, 0, 22621).into() {
-            debug!("Windows 11 detected.");
+        // and is not present on all Win11 devices nor on server builds,
+        // so the decision is based on the presence of the driver rather than the build number
+        if source.session_global_slots {
+            match kernel.module_by_name("WIN32KSGD.SYS") {
+                Ok(win32ksgd_module_info) => {
+                    debug!("Found win32ksgd.sys: {:?}", win32ksgd_module_info);
+
+                    let mut user_process = kernel
+                        .process_by_info(user_process_info.clone())
+                        .map_err(|_| Win32KeyboardError::ProxyNotFound)?;
+                    match Self::find_in_session_global_slots(
+                        &mut user_process.virt_mem,
+                        &win32ksgd_module_info,
+                        source.session_id,
+                    ) {
+                        Ok(key_state_addr) => {
+                            return Ok((user_process_info_win32, key_state_addr))
+                        }
+                        Err(err) => debug!(
+                            "unable to use win32ksgd.sys session slots ({:?}), falling back to gafAsyncKeyState",
+                            err
+                        ),
+                    }
+                }
+                Err(_) => debug!("win32ksgd.sys is not loaded, falling back to gafAsyncKeyState"),
+            }
+        }
+
+        let mut user_process = kernel
+            .process_by_info(user_process_info)
+            .map_err(|_| Win32KeyboardError::ProxyNotFound)?;
+        debug!(
+            "trying to find gaf signature in user proxy process `{}`",
+            user_process.info().name.as_ref()
+        );
 
-            let win32ksgd_module_info = kernel.module_by_name("WIN32KSGD.SYS")?;
-            debug!("Found win32ksgd.sys: {:?}", win32ksgd_module_info);
+        // only the export directory is read, the full image is only read for the signature lookup
+        let export = user_process
+            .module_exports_light(module_info)
+            .ok()
+            .and_then(|exports| {
+                exports
+                    .into_iter()
+                    .find(|export| export.name.as_ref() == "gafAsyncKeyState")
+            });
+        let key_state_addr = match export {
+            Some(export) => module_info.base + export.offset,
+            None => {
+                let module_buf = user_process
+                    .read_module_image(module_info)
+                    .map_err(|_| Win32KeyboardError::ModuleNotMapped)?;
+                module_info.base + Self::find_gaf_sig(&module_buf, &source.signatures)?
+            }
+        };
+        debug!(
+            "found gaf signature in user proxy process `{}` at {:x}",
+            user_process.info().name.as_ref(),
+            key_state_addr
+        );
 
-            let mut user_process = kernel.process_by_info(user_process_info)?;
+        Ok((user_process_info_win32, key_state_addr))
+    }
 
-            let g_session_global_slots_offset = 0x3110;
-            debug!(
-                "gSessionGlobalSlot address: {:?}",
-                win32ksgd_module_info.base + g_session_global_slots_offset
-            );
+    fn find_in_session_global_slots(
+        virt_mem: &mut impl MemoryView,
+        win32ksgd_module_info: &ModuleInfo,
+        session_id: u32,
+    ) -> std::result::Result<Address, Win32KeyboardError> {
+        // session 0 is stored in gLowSessionGlobalSlots which is not supported
+        if session_id == 0 {
+            return Err(Win32KeyboardError::SessionSlotInvalid);
+        }
+
+        let g_session_global_slots_offset = 0x3110;
+        debug!(
+            "gSessionGlobalSlot address: {:?}",
+            win32ksgd_module_info.base + g_session_global_slots_offset
+        );
 
-            let g_session_global_slot_first_deref = user_process.virt_mem.read_addr_arch(
+        let g_session_global_slot_first_deref = virt_mem
+            .read_addr_arch(
                 win32ksgd_module_info.arch.into(),
                 win32ksgd_module_info.base + g_session_global_slots_offset,
-            )?;
-            debug!(
-                "gSessionGlobalSlot 1st deref: {:?}",
-                g_session_global_slot_first_deref
-            );
+            )
+            .ok()
+            .filter(|addr| !addr.is_null())
+            .ok_or(Win32KeyboardError::SessionSlotInvalid)?;
+        debug!(
+            "gSessionGlobalSlot 1st deref: {:?}",
+            g_session_global_slot_first_deref
+        );
 
-            let g_session_global_slot_second_deref = user_process.virt_mem.read_addr_arch(
+        let g_session_global_slot_second_deref = virt_mem
+            .read_addr_arch(
                 win32ksgd_module_info.arch.into(),
-                g_session_global_slot_first_deref,
-            )?;
-            debug!(
-                "gSessionGlobalSlot 2nd deref: {:?}",
-                g_session_global_slot_second_deref
-            );
+                g_session_global_slot_first_deref
+                    + (session_id as umem - 1)
+                        * ArchitectureObj::from(win32ksgd_module_info.arch).size_addr() as umem,
+            )
+            .ok()
+            .filter(|addr| !addr.is_null())
+            .ok_or(Win32KeyboardError::SessionSlotInvalid)?;
+        debug!(
+            "gSessionGlobalSlot 2nd deref: {:?}",
+            g_session_global_slot_second_deref
+        );
 
-            let g_session_global_slot_third_deref = user_process.virt_mem.read_addr_arch(
+        let g_session_global_slot_third_deref = virt_mem
+            .read_addr_arch(
                 win32ksgd_module_info.arch.into(),
                 g_session_global_slot_second_deref,
-            )?;
-            debug!(
-                "gSessionGlobalSlot 3rd deref: {:?}",
-                g_session_global_slot_third_deref
-            );
-
-            debug!(
-                "Key State Buffer Address: {:?}",
-                g_session_global_slot_third_deref + 0x3690
-            );
-
-            Ok((
-                user_process_info_win32,
-                g_session_global_slot_third_deref + 0x3690,
-            ))
-        } else {
-            let mut user_process = kernel.process_by_info(user_process_info)?;
-            debug!(
-                "trying to find gaf signature in user proxy process `{}`",
-                user_process.info().name.as_ref()
-            );
-
-            // TODO: lazy
-            let export_addr = Self::find_gaf_pe(&mut user_process.virt_mem, win32kbase_module_info)
-                .or_else(|_| {
-                    Self::find_gaf_sig(&mut user_process.virt_mem, win32kbase_module_info)
-                })?;
-            debug!(
-                "found gaf signature in user proxy process `{}` at {:x}",
-                user_process.info().name.as_ref(),
-                export_addr
-            );
+            )
+            .ok()
+            .filter(|addr| !addr.is_null())
+            .ok_or(Win32KeyboardError::SessionSlotInvalid)?;
+        debug!(
+            "gSessionGlobalSlot 3rd deref: {:?}",
+            g_session_global_slot_third_deref
+        );
 
-            Ok((
-                user_process_info_win32,
-                win32kbase_module_info.base + export_addr,
-            ))
-        }
-    }
+        debug!(
+            "Key State Buffer Address: {:?}",
+            g_session_global_slot_third_deref + 0x3690
+        );
 
-    fn find_gaf_pe(
-        virt_mem: &mut impl MemoryView,
-        win32kbase_module_info: &ModuleInfo,
-    ) -> Result<umem> {
-        let mut offset = None;
-        let callback = &mut |export: ExportInfo| {
-            if export.name.as_ref() == "gafAsyncKeyState" {
-                offset = Some(export.offset);
-                false
-            } else {
-                true
-            }
-        };
-        memflow::os::util::module_export_list_callback(
-            virt_mem,
-            win32kbase_module_info,
-            callback.into(),
-        )?;
-        offset.ok_or_else(|| {
-            Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound)
-                .log_info("unable to find gafAsyncKeyState")
-        })
+        Ok(g_session_global_slot_third_deref + 0x3690)
     }
 
-    // TODO: replace with a custom signature scanning crate
-    #[cfg(feature = "regex")]
     fn find_gaf_sig(
-        virt_mem: &mut impl MemoryView,
-        win32kbase_module_info: &ModuleInfo,
-    ) -> Result<umem> {
-        use ::regex::bytes::*;
-
-        let module_buf = virt_mem
-            .read_raw(
-                win32kbase_module_info.base,
-                win32kbase_module_info.size.try_into().unwrap(),
-            )
-            .data_part()?;
-
-        // 48 8B 05 ? ? ? ? 48 89 81 ? ? 00 00 48 8B 8F + 0x3
-        let re = Regex::new("(?-u)\\x48\\x8B\\x05(?s:.)(?s:.)(?s:.)(?s:.)\\x48\\x89\\x81(?s:.)(?s:.)\\x00\\x00\\x48\\x8B\\x8F")
-                    .map_err(|_| Error(ErrorOrigin::OsLayer, ErrorKind::Encoding).log_info("malformed gafAsyncKeyState signature"))?;
-        let buf_offs = re
-            .find(module_buf.as_slice())
-            .ok_or_else(|| {
-                Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
-                    .log_info("unable to find gafAsyncKeyState signature")
-            })?
-            .start()
-            + 0x3;
-
-        // compute rip relative addr
-        let export_offs = buf_offs as u32
-            + u32::from_le_bytes(module_buf[buf_offs..buf_offs + 4].try_into().unwrap())
-            + 0x4;
+        module_buf: &[u8],
+        signatures: &[Win32KeyStateSignature],
+    ) -> std::result::Result<umem, Win32KeyboardError> {
+        let export_offs = signatures
+            .iter()
+            .find_map(|sig| sig.find(module_buf))
+            .ok_or(Win32KeyboardError::SignatureNotFound)?;
         debug!("gafAsyncKeyState export found at: {:x}", export_offs);
-        Ok(export_offs as umem)
-    }
-
-    #[cfg(not(feature = "regex"))]
-    fn find_gaf_sig(
-        virt_mem: &mut impl MemoryView,
-        win32kbase_module_info: &ModuleInfo,
-    ) -> Result<umem> {
-        Err(
-            Error(ErrorOrigin::OsLayer, ErrorKind::UnsupportedOptionalFeature)
-                .log_error("signature scanning requires std"),
-        )
+        Ok(export_offs)
     }
 }
 
@@ -379,11 +716,11 @@ impl<T: MemoryView> Keyboard for Win32Keyboard<T> {
     fn is_down(&mut self, vk: i32) -> bool {
         if !(0..=256).contains(&vk) {
             false
-        } else if let Ok(buffer) = self
-            .virt_mem
-            .read::<[u8; 256 * 2 / 8]>(self.key_state_addr)
-            .data_part()
-        {
+        } else if let Ok(buffer) = {
+            let (virt_mem, key_state_addr) = (&mut self.virt_mem, self.key_state_addr);
+            self.throttle
+                .read(|| virt_mem.read(key_state_addr).data_part())
+        } {
             is_key_down!(buffer, vk)
         } else {
             false
@@ -401,17 +738,105 @@ impl<T: MemoryView> Keyboard for Win32Keyboard<T> {
             if let Ok(mut buffer) = self.virt_mem.read::<[u8; 256 * 2 / 8]>(self.key_state_addr) {
                 set_key_down!(buffer, vk, down);
                 self.virt_mem.write(self.key_state_addr, &buffer).ok();
+                self.throttle.invalidate();
             }
         }
     }
 
     /// Reads the gafAsyncKeyState global from the win32kbase.sys kernel module.
     fn state(&mut self) -> memflow::error::Result<Self::KeyboardStateType> {
-        let buffer: [u8; 256 * 2 / 8] = self.virt_mem.read(self.key_state_addr)?;
+        let (virt_mem, key_state_addr) = (&mut self.virt_mem, self.key_state_addr);
+        let buffer = self.throttle.read(|| Ok(virt_mem.read(key_state_addr)?))?;
         Ok(Win32KeyboardState { buffer })
     }
 }
 
+impl<T: MemoryView> Win32Keyboard<T> {
+    /// Rate limits reads of the key state to at most one per `min_interval`.
+    ///
+    /// Calls to `state()` and `is_down()` within the interval return the previously read snapshot.
+    /// This protects slow connectors from being hammered by tight polling loops.
+    #[cfg(feature = "std")]
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.throttle.min_interval = min_interval;
+        self.throttle.invalidate();
+        self
+    }
+
+    /// Reads the current keyboard state and returns it together with
+    /// all key transitions since the given previous state.
+    ///
+    /// The returned state should be passed as `prev` to the next call.
+    pub fn poll_events(
+        &mut self,
+        prev: &Win32KeyboardState,
+    ) -> Result<(Win32KeyboardState, Vec<KeyEvent>)> {
+        let state = self.state()?;
+        let events = state.events_since(prev);
+        Ok((state, events))
+    }
+
+    /// Reads a consolidated snapshot of the keyboard and mouse button state.
+    ///
+    /// Mouse buttons are tracked as virtual keys in gafAsyncKeyState,
+    /// so both are decoded from a single read of the key state.
+    pub fn input_state(&mut self) -> Result<Win32InputState> {
+        self.state().map(Win32InputState::from)
+    }
+}
+
+/// State of the mouse buttons as tracked in gafAsyncKeyState
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32MouseButtons {
+    /// VK_LBUTTON
+    pub left: bool,
+    /// VK_RBUTTON
+    pub right: bool,
+    /// VK_MBUTTON
+    pub middle: bool,
+    /// VK_XBUTTON1
+    pub x1: bool,
+    /// VK_XBUTTON2
+    pub x2: bool,
+}
+
+/// Snapshot of the keyboard and mouse buttons decoded from a single gafAsyncKeyState read
+#[derive(Clone)]
+pub struct Win32InputState {
+    /// State of all virtual keys
+    pub keyboard: Win32KeyboardState,
+    /// State of the mouse buttons
+    pub mouse: Win32MouseButtons,
+}
+
+impl From<Win32KeyboardState> for Win32InputState {
+    fn from(keyboard: Win32KeyboardState) -> Self {
+        let mouse = keyboard.mouse_buttons();
+        Self { keyboard, mouse }
+    }
+}
+
+/// Kind of a key transition between two keyboard states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub enum KeyEventKind {
+    /// The key went down
+    Pressed,
+    /// The key went up
+    Released,
+}
+
+/// A key transition between two keyboard states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct KeyEvent {
+    /// Virtual keycode of the key
+    pub key: i32,
+    /// Direction of the transition
+    pub kind: KeyEventKind,
+}
+
 /// Represents the current Keyboardstate.
 ///
 /// Internally this will hold a 256 * 2 / 8 byte long copy of the gafAsyncKeyState array from the target.
@@ -420,6 +845,45 @@ pub struct Win32KeyboardState {
     buffer: [u8; 256 * 2 / 8],
 }
 
+impl Win32KeyboardState {
+    /// Returns an iterator over the virtual keycodes of all keys that are currently down.
+    ///
+    /// This only decodes the snapshot and does not access the target memory.
+    pub fn pressed_keys(&self) -> impl Iterator<Item = i32> + '_ {
+        (0..256).filter(move |&vk| is_key_down!(self.buffer, vk))
+    }
+
+    /// Returns the state of the mouse buttons contained in this snapshot.
+    pub fn mouse_buttons(&self) -> Win32MouseButtons {
+        Win32MouseButtons {
+            left: is_key_down!(self.buffer, 0x01),
+            right: is_key_down!(self.buffer, 0x02),
+            middle: is_key_down!(self.buffer, 0x04),
+            x1: is_key_down!(self.buffer, 0x05),
+            x2: is_key_down!(self.buffer, 0x06),
+        }
+    }
+
+    /// Returns all key transitions from the given previous state to this state.
+    pub fn events_since(&self, prev: &Win32KeyboardState) -> Vec<KeyEvent> {
+        (0..256)
+            .filter_map(
+                |vk| match (is_key_down!(prev.buffer, vk), is_key_down!(self.buffer, vk)) {
+                    (false, true) => Some(KeyEvent {
+                        key: vk,
+                        kind: KeyEventKind::Pressed,
+                    }),
+                    (true, false) => Some(KeyEvent {
+                        key: vk,
+                        kind: KeyEventKind::Released,
+                    }),
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+}
+
 impl KeyboardState for Win32KeyboardState {
     /// Returns true wether the given key was pressed.
     /// This function accepts a valid microsoft virtual keycode.
@@ -434,3 +898,111 @@ impl KeyboardState for Win32KeyboardState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_state_decode() {
+        let mut buffer = [0u8; 256 * 2 / 8];
+        set_key_down!(buffer, 0x01, true);
+        set_key_down!(buffer, 0x41, true);
+        set_key_down!(buffer, 0xFE, true);
+        set_key_down!(buffer, 0xFE, false);
+
+        let state = Win32KeyboardState { buffer };
+        assert!(state.is_down(0x01));
+        assert!(state.is_down(0x41));
+        assert!(!state.is_down(0x42));
+        assert!(!state.is_down(0xFE));
+        assert!(!state.is_down(-1));
+        assert!(!state.is_down(257));
+        assert_eq!(state.pressed_keys().collect::<Vec<_>>(), vec![0x01, 0x41]);
+    }
+
+    #[test]
+    fn input_state_decode() {
+        let mut buffer = [0u8; 256 * 2 / 8];
+        set_key_down!(buffer, 0x02, true);
+        set_key_down!(buffer, 0x06, true);
+        set_key_down!(buffer, 0x41, true);
+
+        let input = Win32InputState::from(Win32KeyboardState { buffer });
+        assert_eq!(
+            input.mouse,
+            Win32MouseButtons {
+                right: true,
+                x2: true,
+                ..Default::default()
+            }
+        );
+        assert!(input.keyboard.is_down(0x41));
+    }
+
+    #[test]
+    fn keyboard_state_events() {
+        let mut prev = [0u8; 256 * 2 / 8];
+        set_key_down!(prev, 0x01, true);
+        set_key_down!(prev, 0x10, true);
+        let mut cur = prev;
+        set_key_down!(cur, 0x01, false);
+        set_key_down!(cur, 0x41, true);
+
+        let prev = Win32KeyboardState { buffer: prev };
+        let cur = Win32KeyboardState { buffer: cur };
+        assert_eq!(
+            cur.events_since(&prev),
+            vec![
+                KeyEvent {
+                    key: 0x01,
+                    kind: KeyEventKind::Released
+                },
+                KeyEvent {
+                    key: 0x41,
+                    kind: KeyEventKind::Pressed
+                },
+            ]
+        );
+        assert!(cur.events_since(&cur).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn key_state_throttle() {
+        let mut throttle = KeyStateThrottle {
+            min_interval: Duration::from_secs(3600),
+            ..Default::default()
+        };
+        assert_eq!(throttle.read(|| Ok([1; 64])).unwrap(), [1; 64]);
+        assert_eq!(throttle.read(|| Ok([2; 64])).unwrap(), [1; 64]);
+        throttle.invalidate();
+        assert_eq!(throttle.read(|| Ok([2; 64])).unwrap(), [2; 64]);
+
+        // without an interval every call reads from the target
+        let mut throttle = KeyStateThrottle::default();
+        assert_eq!(throttle.read(|| Ok([1; 64])).unwrap(), [1; 64]);
+        assert_eq!(throttle.read(|| Ok([2; 64])).unwrap(), [2; 64]);
+    }
+
+    #[test]
+    fn gaf_sig() {
+        let mut buf = vec![0xCCu8; 0x20];
+        let sig = [
+            0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, 0x48, 0x89, 0x81, 0x12, 0x34, 0x00, 0x00,
+            0x48, 0x8B, 0x8F,
+        ];
+        buf.extend_from_slice(&sig);
+
+        // 0x20 (match) + 0x3 (displacement) + 0x10 + 0x4
+        let sigs = Win32KeyStateSource::default().signatures;
+        assert_eq!(
+            Win32Keyboard::<()>::find_gaf_sig(&buf, &sigs).unwrap(),
+            0x37
+        );
+        assert_eq!(
+            Win32Keyboard::<()>::find_gaf_sig(&buf[..0x20], &sigs),
+            Err(Win32KeyboardError::SignatureNotFound)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::prelude::v1::*;
 
 use crate::offsets::Win32ArchOffsets;
@@ -6,19 +7,119 @@ use crate::win32::VirtualReadUnicodeString;
 use log::trace;
 
 use memflow::architecture::ArchitectureIdent;
-use memflow::error::Result;
+use memflow::error::{Error, ErrorKind, ErrorOrigin, Result};
 use memflow::mem::MemoryView;
 use memflow::os::{AddressCallback, ModuleInfo};
-use memflow::types::Address;
+use memflow::types::{umem, Address};
+
+use memflow::cglue::callback::OpaqueCallback;
 
 const MAX_ITER_COUNT: usize = 65536;
 
-#[derive(Debug, Clone, Copy)]
+/// Kernel module information read from a `_KLDR_DATA_TABLE_ENTRY` in the PsLoadedModuleList
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32KernelModule {
+    /// Position of the module in the PsLoadedModuleList
+    pub load_order: usize,
+    /// _KLDR_DATA_TABLE_ENTRY::TimeDateStamp of the module
+    pub time_date_stamp: u32,
+    /// Generic module information (DllBase, SizeOfImage, names)
+    pub info: ModuleInfo,
+}
+
+pub type Win32KernelModuleCallback<'a> = OpaqueCallback<'a, Win32KernelModule>;
+
+/// User mode module information read from a `_LDR_DATA_TABLE_ENTRY`
+///
+/// The timestamp and size identify the module image on a symbol server.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32Module {
+    /// _LDR_DATA_TABLE_ENTRY::TimeDateStamp of the module
+    pub time_date_stamp: u32,
+    /// CheckSum read from the PE optional header of the module (0 if it could not be read)
+    pub check_sum: u32,
+    /// Generic module information (DllBase, SizeOfImage, names)
+    pub info: ModuleInfo,
+}
+
+/// Loaded kernel driver with its resolved file path
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32Driver {
+    /// Base address of the driver image
+    pub base: Address,
+    /// Size of the driver image
+    pub size: umem,
+    /// _KLDR_DATA_TABLE_ENTRY::BaseDllName of the driver
+    pub name: String,
+    /// _KLDR_DATA_TABLE_ENTRY::FullDllName of the driver as stored in memory
+    pub raw_path: String,
+    /// Absolute file path of the driver (e.g. `C:\Windows\System32\drivers\foo.sys`)
+    pub path: String,
+}
+
+/// Module record with the fields of a `MINIDUMP_MODULE`
+///
+/// This allows module lists to be handed to tools consuming minidumps
+/// without writing out a full minidump file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct MinidumpModule {
+    /// Base address of the module image
+    pub base_of_image: u64,
+    /// Size of the module image in bytes
+    pub size_of_image: u32,
+    /// TimeDateStamp read from the PE file header of the module (0 if it could not be read)
+    pub time_date_stamp: u32,
+    /// Full path of the module
+    pub module_name: String,
+}
+
+/// Converts a kernel module path into an absolute file path.
+///
+/// Paths starting with `\SystemRoot\` as well as paths relative to the windows directory
+/// are resolved against `system_root`, a leading `\??\` prefix is stripped.
+pub fn resolve_driver_path(path: &str, system_root: &str) -> String {
+    let system_root = system_root.trim_end_matches('\\');
+    let prefix_len = "\\SystemRoot\\".len();
+
+    if path.len() >= prefix_len && path[..prefix_len].eq_ignore_ascii_case("\\SystemRoot\\") {
+        format!("{}\\{}", system_root, &path[prefix_len..])
+    } else if let Some(stripped) = path.strip_prefix("\\??\\") {
+        stripped.to_string()
+    } else if !path.is_empty() && !path.starts_with('\\') && !path.contains(':') {
+        // e.g. `System32\drivers\foo.sys`
+        format!("{}\\{}", system_root, path)
+    } else {
+        path.to_string()
+    }
+}
+
+/// Order in which the modules of a `_PEB_LDR_DATA` are walked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[repr(C)]
 #[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub enum Win32ModuleListOrder {
+    /// _PEB_LDR_DATA::InLoadOrderModuleList
+    #[default]
+    Load,
+    /// _PEB_LDR_DATA::InMemoryOrderModuleList
+    Memory,
+    /// _PEB_LDR_DATA::InInitializationOrderModuleList
+    Init,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub struct Win32ModuleListInfo {
     module_base: Address,
     offsets: Win32ArchOffsets,
+    max_iter: usize,
+    /// Offset of the list links inside of the _LDR_DATA_TABLE_ENTRY
+    links_offset: usize,
 }
 
 impl Win32ModuleListInfo {
@@ -26,37 +127,77 @@ impl Win32ModuleListInfo {
         mem: &mut impl MemoryView,
         env_block: Address,
         arch: ArchitectureIdent,
+    ) -> Result<Self> {
+        Self::with_peb_order(mem, env_block, arch, Win32ModuleListOrder::Load)
+    }
+
+    /// Creates a module list that walks the given `_PEB_LDR_DATA` list.
+    ///
+    /// Entries are always reported as the address of their `_LDR_DATA_TABLE_ENTRY`,
+    /// so they can be passed to `module_info_from_entry` regardless of the order.
+    pub fn with_peb_order(
+        mem: &mut impl MemoryView,
+        env_block: Address,
+        arch: ArchitectureIdent,
+        order: Win32ModuleListOrder,
     ) -> Result<Self> {
         let offsets = Win32ArchOffsets::from(arch);
         let arch_obj = arch.into();
 
+        // the links inside of the entries are laid out in the same order as the list heads
+        let ldr_list = match order {
+            Win32ModuleListOrder::Load => offsets.ldr_list,
+            Win32ModuleListOrder::Memory => offsets.ldr_list_memory_order,
+            Win32ModuleListOrder::Init => offsets.ldr_list_init_order,
+        };
+        let links_offset = ldr_list - offsets.ldr_list;
+
         trace!("peb_ldr_offs={:x}", offsets.peb_ldr);
-        trace!("ldr_list_offs={:x}", offsets.ldr_list);
+        trace!("ldr_list_offs={:x}", ldr_list);
 
         let env_block_ldr = mem.read_addr_arch(arch_obj, env_block + offsets.peb_ldr)?;
         trace!("peb_ldr={:x}", env_block_ldr);
 
-        let module_base = mem.read_addr_arch(arch_obj, env_block_ldr + offsets.ldr_list)?;
+        let first_link = mem.read_addr_arch(arch_obj, env_block_ldr + ldr_list)?;
 
-        Self::with_base(module_base, arch)
+        let mut info = Self::with_base(first_link - links_offset, arch)?;
+        info.links_offset = links_offset;
+        Ok(info)
     }
 
     pub fn with_base(module_base: Address, arch: ArchitectureIdent) -> Result<Self> {
         trace!("module_base={:x}", module_base);
+        Ok(Self::from_parts(module_base, arch))
+    }
 
+    /// Creates a module list from a previously discovered list head without accessing memory.
+    ///
+    /// `module_base` is the first `_LDR_DATA_TABLE_ENTRY` of the load order list,
+    /// e.g. the value returned by `module_base` on a previously constructed list.
+    /// Together with the `serde` feature this allows persisting and restoring a module list.
+    pub fn from_parts(module_base: Address, arch: ArchitectureIdent) -> Self {
         let offsets = Win32ArchOffsets::from(arch);
         trace!("offsets={:?}", offsets);
 
-        Ok(Win32ModuleListInfo {
+        Win32ModuleListInfo {
             module_base,
             offsets,
-        })
+            max_iter: MAX_ITER_COUNT,
+            links_offset: 0,
+        }
     }
 
     pub fn module_base(&self) -> Address {
         self.module_base
     }
 
+    /// Limits the number of entries visited when walking the module list.
+    ///
+    /// Walking a list with more entries returns an error.
+    pub fn set_max_iter(&mut self, max_iter: usize) {
+        self.max_iter = max_iter;
+    }
+
     pub fn module_entry_list<V: MemoryView>(
         &self,
         mem: &mut impl AsMut<V>,
@@ -76,21 +217,42 @@ impl Win32ModuleListInfo {
         let list_start = self.module_base;
         let mut list_entry = list_start;
         let arch_obj = arch.into();
-        for _ in 0..MAX_ITER_COUNT {
+        let mut visited = BTreeSet::new();
+        for _ in 0..self.max_iter {
             if !callback.call(list_entry) {
-                break;
+                return Ok(());
             }
-            list_entry = mem.as_mut().read_addr_arch(arch_obj, list_entry)?;
+            visited.insert(list_entry);
+
+            let next_link = mem
+                .as_mut()
+                .read_addr_arch(arch_obj, list_entry + self.links_offset)?;
             // Break on misaligned entry. On NT 4.0 list end is misaligned, maybe it's a flag?
-            if list_entry.is_null()
-                || (list_entry.to_umem() & 0b111) != 0
-                || list_entry == self.module_base
-            {
-                break;
+            if next_link.is_null() || (next_link.to_umem() & 0b111) != 0 {
+                return Ok(());
+            }
+
+            list_entry = next_link - self.links_offset;
+            if list_entry == self.module_base {
+                return Ok(());
+            }
+
+            if visited.contains(&list_entry) {
+                return Err(
+                    Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds).log_info(format!(
+                        "module list at {:x} contains a cycle at entry {:x}",
+                        self.module_base, list_entry
+                    )),
+                );
             }
         }
 
-        Ok(())
+        Err(
+            Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds).log_info(format!(
+                "module list at {:x} exceeds the maximum of {} entries",
+                self.module_base, self.max_iter
+            )),
+        )
     }
 
     pub fn module_base_from_entry(
@@ -149,4 +311,55 @@ impl Win32ModuleListInfo {
             arch,
         })
     }
+
+    /// Reads the module information of the entry along with its timestamp and checksum.
+    pub fn module_from_entry(
+        &self,
+        entry: Address,
+        parent_eprocess: Address,
+        mem: &mut impl MemoryView,
+        arch: ArchitectureIdent,
+    ) -> Result<Win32Module> {
+        let info = self.module_info_from_entry(entry, parent_eprocess, mem, arch)?;
+
+        let time_date_stamp = mem.read::<u32>(entry + self.offsets.ldr_data_time_date_stamp)?;
+        trace!("time_date_stamp={:x}", time_date_stamp);
+
+        let check_sum =
+            crate::kernel::ntos::pehelper::try_get_pe_checksum(mem, info.base).unwrap_or_default();
+        trace!("check_sum={:x}", check_sum);
+
+        Ok(Win32Module {
+            time_date_stamp,
+            check_sum,
+            info,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_driver_path;
+
+    #[test]
+    fn resolve_driver_paths() {
+        let root = "C:\\Windows";
+        assert_eq!(
+            resolve_driver_path("\\SystemRoot\\System32\\drivers\\foo.sys", root),
+            "C:\\Windows\\System32\\drivers\\foo.sys"
+        );
+        assert_eq!(
+            resolve_driver_path("\\systemroot\\system32\\ntoskrnl.exe", "C:\\Windows\\"),
+            "C:\\Windows\\system32\\ntoskrnl.exe"
+        );
+        assert_eq!(
+            resolve_driver_path("System32\\drivers\\bar.sys", root),
+            "C:\\Windows\\System32\\drivers\\bar.sys"
+        );
+        assert_eq!(
+            resolve_driver_path("\\??\\D:\\drivers\\baz.sys", root),
+            "D:\\drivers\\baz.sys"
+        );
+        assert_eq!(resolve_driver_path("", root), "");
+    }
 }
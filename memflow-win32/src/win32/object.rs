@@ -0,0 +1,15 @@
+use std::prelude::v1::*;
+
+use memflow::types::Address;
+
+/// Entry of a kernel object directory (_OBJECT_DIRECTORY)
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32ObjectDirEntry {
+    /// Name of the object
+    pub name: String,
+    /// Object type index, see `Win32Kernel::object_types`
+    pub type_index: u8,
+    /// Address of the object body
+    pub object_address: Address,
+}
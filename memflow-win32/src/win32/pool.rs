@@ -0,0 +1,20 @@
+use std::prelude::v1::*;
+
+use memflow::cglue::callback::OpaqueCallback;
+use memflow::types::{umem, Address};
+
+/// Big pool allocation read from a `_POOL_TRACKER_BIG_PAGES` entry of the PoolBigPageTable
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32PoolEntry {
+    /// Virtual address of the allocation
+    pub va: Address,
+    /// Size of the allocation in bytes
+    pub size: umem,
+    /// Pool tag of the allocation
+    pub tag: [u8; 4],
+    /// Indicates that the allocation has been freed
+    pub freed: bool,
+}
+
+pub type Win32PoolEntryCallback<'a> = OpaqueCallback<'a, Win32PoolEntry>;
@@ -1,18 +1,28 @@
 use std::prelude::v1::*;
 
-use super::{Win32Kernel, Win32ModuleListInfo};
+use super::{
+    EnvVarInfo, MinidumpModule, VirtualReadUnicodeString, Win32EnvListInfo, Win32Kernel,
+    Win32Module, Win32ModuleListInfo, Win32ModuleListOrder,
+};
 
+use crate::kernel::ntos::pehelper;
+use crate::offsets::{Win32ArchOffsets, Win32Offsets};
 use crate::prelude::MmVadOffsetTable;
 
+use std::collections::BTreeMap;
 use std::fmt;
 
 use memflow::mem::virt_translate::*;
 use memflow::prelude::v1::{Result, *};
+use memflow::types::gap_remover::GapRemover;
+
+use pelite::{self, pe64::exports::Export, PeView};
 
 // those only required when compiling cglue code
 #[cfg(feature = "plugins")]
 use memflow::cglue;
 
+use super::scan::{PlainScanner, Scanner};
 use super::Win32VirtualTranslate;
 
 /// Exit status of a win32 process
@@ -21,6 +31,31 @@ pub type Win32ExitStatus = i32;
 /// Process has not exited yet
 pub const EXIT_STATUS_STILL_ACTIVE: i32 = 259;
 
+/// Default chunk size used by `Win32Process::dump_region`
+pub const DUMP_CHUNK_SIZE: usize = size::mb(1);
+
+/// Chunk size used by `Win32Process::search`
+const SEARCH_CHUNK_SIZE: usize = size::mb(1);
+
+/// Maximum number of VAD nodes visited while walking the VAD tree
+const MAX_VAD_COUNT: usize = 65536;
+
+/// Maximum number of threads that are walked in the thread list of a process
+const MAX_THREAD_ITER_COUNT: usize = 65536;
+
+/// _MMVAD_FLAGS::Protection values
+const MM_PROTECTION_ACCESS_MASK: u32 = 0x7;
+const MM_GUARD_PAGE: u32 = 0x10;
+
+/// Chunk size used by `Win32Process::read_wstr`, reads never cross a chunk boundary
+const WSTR_CHUNK_SIZE: usize = size::kb(4);
+
+/// Chunk size used by `Win32Process::walk_stack`
+const STACK_CHUNK_SIZE: usize = size::kb(4);
+
+/// Maximum number of pages reported by `Win32Process::page_map`
+const MAX_PAGE_MAP_PAGES: usize = 0x100000;
+
 /// EPROCESS ImageFileName byte length
 pub const IMAGE_FILE_NAME_LENGTH: usize = 15;
 
@@ -48,9 +83,209 @@ pub struct Win32ProcessInfo {
 
     // memory
     pub vad_root: Address,
+
+    // state
+    pub frozen: bool,
+}
+
+/// Basic information about a process that is read directly from its _EPROCESS
+///
+/// This is returned by `Win32Kernel::process_info_list_basic`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32ProcessBasicInfo {
+    /// Address of the _EPROCESS structure
+    pub address: Address,
+    pub pid: Pid,
+    /// Image file name as stored in _EPROCESS::ImageFileName (truncated to 15 characters)
+    pub name: ReprCString,
+    pub state: ProcessState,
+}
+
+impl From<ProcessInfo> for Win32ProcessBasicInfo {
+    fn from(info: ProcessInfo) -> Self {
+        Self {
+            address: info.address,
+            pid: info.pid,
+            name: info.name,
+            state: info.state,
+        }
+    }
+}
+
+/// Sort order of `Win32Kernel::process_info_list_sorted`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Win32ProcessSortBy {
+    /// Ascending process id
+    Pid,
+    /// Ascending creation time (_EPROCESS::CreateTime)
+    CreateTime,
+    /// Case-insensitive process name
+    Name,
+}
+
+/// Memory counters of a process as shown in the task manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32MemCounters {
+    /// Current working set size in bytes
+    pub working_set_size: umem,
+    /// Peak working set size in bytes
+    pub peak_working_set_size: umem,
+    /// Committed private memory in bytes (_EPROCESS::CommitCharge)
+    pub private_bytes: umem,
+    /// Peak committed private memory in bytes (_EPROCESS::CommitChargePeak)
+    pub peak_private_bytes: umem,
+}
+
+/// A thread of a process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32ThreadInfo {
+    /// Address of the _ETHREAD structure
+    pub ethread: Address,
+    /// Thread id (_ETHREAD::Cid.UniqueThread)
+    pub tid: u32,
+    /// Address of the thread environment block (_KTHREAD::Teb)
+    pub teb: Address,
+    /// User-mode entry point of the thread (_ETHREAD::Win32StartAddress)
+    pub win32_start_address: Address,
+}
+
+/// A memory region of a process as described by its VAD tree
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32VadEntry {
+    /// Address of the _MMVAD structure in kernel memory
+    pub vad: Address,
+    /// Start address of the region
+    pub start: Address,
+    /// Size of the region in bytes
+    pub size: umem,
+    /// _MMVAD_FLAGS::Protection of the region, `None` if the offsets are not available
+    pub protection: Option<u32>,
+    /// Path of the file mapped into the region (e.g. `\Windows\System32\kernel32.dll`)
+    ///
+    /// This is only set for image and mapped file regions by `Win32Process::vad_entries`.
+    pub file_name: Option<String>,
+}
+
+/// Offsets required to resolve the file backing a VAD
+#[derive(Debug, Clone, Copy)]
+struct VadFileOffsets {
+    mmvad_subsection: usize,
+    mmvad_private_memory_bit: usize,
+    subsection_control_area: usize,
+}
+
+impl VadFileOffsets {
+    fn new(offsets: &Win32Offsets) -> Self {
+        Self {
+            mmvad_subsection: offsets.mmvad_subsection(),
+            mmvad_private_memory_bit: offsets.mmvad_private_memory_bit(),
+            subsection_control_area: offsets.subsection_control_area(),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.mmvad_subsection != 0 && self.subsection_control_area != 0
+    }
+}
+
+/// Offsets required to walk the threads of a process
+#[derive(Debug, Clone, Copy)]
+struct ThreadOffsets {
+    eproc_thread_list: usize,
+    ethread_list_entry: usize,
+    ethread_cid: usize,
+    ethread_win32_start_address: usize,
+    kthread_teb: usize,
+}
+
+impl ThreadOffsets {
+    fn new(offsets: &Win32Offsets) -> Self {
+        Self {
+            eproc_thread_list: offsets.eproc_thread_list(),
+            ethread_list_entry: offsets.ethread_list_entry(),
+            ethread_cid: offsets.ethread_cid(),
+            ethread_win32_start_address: offsets.ethread_win32_start_address(),
+            kthread_teb: offsets.kthread_teb(),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.eproc_thread_list != 0
+            && self.ethread_list_entry != 0
+            && self.ethread_cid != 0
+            && self.ethread_win32_start_address != 0
+    }
+}
+
+/// _EPROCESS offsets required to read the `Win32MemCounters`
+#[derive(Debug, Clone, Copy)]
+struct MemCountersOffsets {
+    working_set_size: usize,
+    peak_working_set_size: usize,
+    commit_charge: usize,
+    commit_charge_peak: usize,
+}
+
+impl MemCountersOffsets {
+    fn new(offsets: &Win32Offsets) -> Self {
+        Self {
+            working_set_size: offsets.eproc_working_set_size(),
+            peak_working_set_size: offsets.eproc_peak_working_set_size(),
+            commit_charge: offsets.eproc_commit_charge(),
+            commit_charge_peak: offsets.eproc_commit_charge_peak(),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.working_set_size != 0
+            && self.peak_working_set_size != 0
+            && self.commit_charge != 0
+            && self.commit_charge_peak != 0
+    }
+}
+
+/// Offsets required to resolve the image file of a process via its section object
+#[derive(Debug, Clone, Copy)]
+struct ImageFileOffsets {
+    eproc_section_object: usize,
+    section_control_area: usize,
+    section_object_segment: usize,
+    segment_control_area: usize,
+    control_area_file_pointer: usize,
+    file_object_file_name: usize,
+}
+
+impl ImageFileOffsets {
+    fn new(offsets: &Win32Offsets) -> Self {
+        Self {
+            eproc_section_object: offsets.eproc_section_object(),
+            section_control_area: offsets.section_control_area(),
+            section_object_segment: offsets.section_object_segment(),
+            segment_control_area: offsets.segment_control_area(),
+            control_area_file_pointer: offsets.control_area_file_pointer(),
+            file_object_file_name: offsets.file_object_file_name(),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.eproc_section_object != 0
+            && (self.section_control_area != 0
+                || (self.section_object_segment != 0 && self.segment_control_area != 0))
+            && self.control_area_file_pointer != 0
+            && self.file_object_file_name != 0
+    }
 }
 
 impl Win32ProcessInfo {
+    /// Returns the address of the _EPROCESS structure of the process
+    pub fn eprocess_base(&self) -> Address {
+        self.base_info.address
+    }
+
     pub fn wow64(&self) -> Address {
         self.wow64
     }
@@ -63,26 +298,50 @@ impl Win32ProcessInfo {
         }
     }
 
+    /// Returns the address of the native PEB of the process
     pub fn peb_native(&self) -> Option<Address> {
         self.peb_native
     }
 
+    /// Returns the address of the 32-bit PEB if the process is a wow64 process
     pub fn peb_wow64(&self) -> Option<Address> {
         self.peb_wow64
     }
 
+    /// Returns the address of the native TEB of the main thread
+    pub fn teb(&self) -> Option<Address> {
+        self.teb
+    }
+
+    /// Returns the address of the 32-bit TEB of the main thread if the process is a wow64 process
+    pub fn teb_wow64(&self) -> Option<Address> {
+        self.teb_wow64
+    }
+
     /// Return the module list information of process native architecture
     ///
     /// If the process is a wow64 process, module_info_wow64 is returned, otherwise, module_info_native is
     /// returned.
     pub fn module_info(&self) -> Option<Win32ModuleListInfo> {
-        if !self.wow64.is_null() {
+        if self.is_wow64() {
             self.module_info_wow64
         } else {
             self.module_info_native
         }
     }
 
+    /// Returns true if the process has been frozen (e.g. a suspended UWP app)
+    ///
+    /// This is always false on kernels that do not support deep freezing processes.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Returns true if the process is a 32-bit process running under wow64
+    pub fn is_wow64(&self) -> bool {
+        !self.wow64.is_null()
+    }
+
     pub fn module_info_native(&self) -> Option<Win32ModuleListInfo> {
         self.module_info_native
     }
@@ -96,6 +355,36 @@ impl Win32ProcessInfo {
     }
 }
 
+/// Prints a one-line summary of the process, e.g.
+/// `pid=1234 name=notepad.exe arch=X86(64, false) wow64=false dtb=1ad000 peb=7ff6f000 modules=native`
+impl fmt::Display for Win32ProcessInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pid={} name={} arch={:?} wow64={} dtb={:x}",
+            self.base_info.pid,
+            self.base_info.name,
+            self.base_info.proc_arch,
+            self.is_wow64(),
+            self.base_info.dtb1
+        )?;
+        match self.peb() {
+            Some(peb) => write!(f, " peb={:x}", peb)?,
+            None => write!(f, " peb=none")?,
+        }
+        let modules = match (
+            self.module_info_native.is_some(),
+            self.module_info_wow64.is_some(),
+        ) {
+            (true, true) => "native+wow64",
+            (true, false) => "native",
+            (false, true) => "wow64",
+            (false, false) => "none",
+        };
+        write!(f, " modules={}", modules)
+    }
+}
+
 #[cfg(feature = "plugins")]
 cglue_impl_group!(Win32Process<T, V, D>, ProcessInstance, { VirtualTranslate });
 #[cfg(feature = "plugins")]
@@ -107,7 +396,57 @@ pub struct Win32Process<T, V, D> {
 
     sysproc_dtb: D,
     offset_eproc_exit_status: usize,
+    offset_eproc_job: usize,
     mmvad: MmVadOffsetTable,
+    mem_counters: MemCountersOffsets,
+    image_file: ImageFileOffsets,
+    threads: ThreadOffsets,
+    vad_file: VadFileOffsets,
+    mapped_mem_vad: bool,
+
+    module_cache: Option<BTreeMap<(Address, umem), Vec<u8>>>,
+    /// Module list sorted by base address, used by `module_containing`
+    module_ranges: Option<Vec<ModuleInfo>>,
+}
+
+/// Converts the _MMVAD_FLAGS::Protection of a VAD region into a `PageType`
+fn mm_protection_page_type(protection: Option<u32>) -> PageType {
+    match protection.map(|p| p & MM_PROTECTION_ACCESS_MASK) {
+        None => PageType::UNKNOWN,
+        // MM_NOACCESS
+        Some(0) => PageType::NONE,
+        // MM_READONLY
+        Some(1) => PageType::READ_ONLY | PageType::NOEXEC,
+        // MM_EXECUTE, MM_EXECUTE_READ
+        Some(2) | Some(3) => PageType::READ_ONLY,
+        // MM_READWRITE, MM_WRITECOPY
+        Some(4) | Some(5) => PageType::WRITEABLE | PageType::NOEXEC,
+        // MM_EXECUTE_READWRITE, MM_EXECUTE_WRITECOPY
+        _ => PageType::WRITEABLE,
+    }
+}
+
+/// Finds the module containing `addr` in a list of modules sorted by base address
+fn find_module_containing(modules: &[ModuleInfo], addr: Address) -> Option<&ModuleInfo> {
+    let idx = modules.partition_point(|module| module.base <= addr);
+    let module = modules.get(idx.checked_sub(1)?)?;
+    if addr.to_umem() - module.base.to_umem() < module.size {
+        Some(module)
+    } else {
+        None
+    }
+}
+
+/// Compares `buf` with `pattern`, only comparing the bits set in `mask`
+fn masked_match(buf: &[u8], pattern: &[u8], mask: Option<&[u8]>) -> bool {
+    match mask {
+        Some(mask) => buf
+            .iter()
+            .zip(pattern)
+            .zip(mask)
+            .all(|((b, p), m)| b & m == p & m),
+        None => buf == pattern,
+    }
 }
 
 // TODO: can be removed i think
@@ -118,9 +457,226 @@ impl<T: Clone, V: Clone, D: Clone> Clone for Win32Process<T, V, D> {
             proc_info: self.proc_info.clone(),
             sysproc_dtb: self.sysproc_dtb.clone(),
             offset_eproc_exit_status: self.offset_eproc_exit_status,
+            offset_eproc_job: self.offset_eproc_job,
             mmvad: self.mmvad,
+            mem_counters: self.mem_counters,
+            image_file: self.image_file,
+            threads: self.threads,
+            vad_file: self.vad_file,
+            mapped_mem_vad: self.mapped_mem_vad,
+            module_cache: self.module_cache.clone(),
+            module_ranges: self.module_ranges.clone(),
+        }
+    }
+}
+
+fn read_u16_at(buf: &[u8], offs: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        buf.get(offs..offs + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_at(buf: &[u8], offs: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        buf.get(offs..offs + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Returns the rva and size of the export directory from the pe headers of a module.
+fn pe_export_directory(headers: &[u8]) -> Result<(u32, u32)> {
+    let invalid =
+        || Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_info("invalid pe headers");
+
+    if headers.get(0..2) != Some(b"MZ") {
+        return Err(invalid());
+    }
+    let nt_offs = read_u32_at(headers, 0x3c).ok_or_else(invalid)? as usize;
+    if headers.get(nt_offs..nt_offs + 4) != Some(b"PE\0\0") {
+        return Err(invalid());
+    }
+
+    // the data directory follows the optional header which differs between pe32 and pe32+
+    let opt_offs = nt_offs + 0x18;
+    let data_dir_offs = match read_u16_at(headers, opt_offs).ok_or_else(invalid)? {
+        0x10b => opt_offs + 0x60,
+        0x20b => opt_offs + 0x70,
+        _ => return Err(invalid()),
+    };
+    if read_u32_at(headers, data_dir_offs - 4).ok_or_else(invalid)? == 0 {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound)
+            .log_info("module does not have an export directory"));
+    }
+
+    let rva = read_u32_at(headers, data_dir_offs).ok_or_else(invalid)?;
+    let size = read_u32_at(headers, data_dir_offs + 4).ok_or_else(invalid)?;
+    if rva == 0 || size == 0 {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound)
+            .log_info("module does not have an export directory"));
+    }
+    Ok((rva, size))
+}
+
+/// Parses the named exports of an export directory that was read from `dir_rva`.
+///
+/// All tables and names have to be contained in the directory. Forwarded exports are skipped.
+fn parse_export_directory(dir: &[u8], dir_rva: u32) -> Result<Vec<ExportInfo>> {
+    let invalid = || {
+        Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile)
+            .log_info("export table exceeds the export directory")
+    };
+    let offs = |rva: u32| rva.checked_sub(dir_rva).map(|offs| offs as usize);
+    let read_u32 = |rva: u32| offs(rva).and_then(|offs| read_u32_at(dir, offs));
+
+    let number_of_functions = read_u32_at(dir, 0x14).ok_or_else(invalid)?;
+    let number_of_names = read_u32_at(dir, 0x18).ok_or_else(invalid)?;
+    let address_of_functions = read_u32_at(dir, 0x1c).ok_or_else(invalid)?;
+    let address_of_names = read_u32_at(dir, 0x20).ok_or_else(invalid)?;
+    let address_of_name_ordinals = read_u32_at(dir, 0x24).ok_or_else(invalid)?;
+
+    let dir_end = dir_rva as umem + dir.len() as umem;
+    let mut out = vec![];
+    for i in 0..number_of_names {
+        let name_rva = read_u32(address_of_names.wrapping_add(i * 4)).ok_or_else(invalid)?;
+        let ordinal = offs(address_of_name_ordinals.wrapping_add(i * 2))
+            .and_then(|offs| read_u16_at(dir, offs))
+            .ok_or_else(invalid)? as u32;
+        if ordinal >= number_of_functions {
+            return Err(invalid());
+        }
+        let function_rva =
+            read_u32(address_of_functions.wrapping_add(ordinal * 4)).ok_or_else(invalid)?;
+
+        // forwarded exports point to a string inside of the export directory
+        if (dir_rva as umem..dir_end).contains(&(function_rva as umem)) {
+            continue;
+        }
+
+        let name = offs(name_rva)
+            .and_then(|offs| dir.get(offs..))
+            .and_then(|name| name.iter().position(|&b| b == 0).map(|len| &name[..len]))
+            .ok_or_else(invalid)?;
+        out.push(ExportInfo {
+            name: String::from_utf8_lossy(name).as_ref().into(),
+            offset: function_rva as umem,
+        });
+    }
+    Ok(out)
+}
+
+/// Converts an `umem` into a fixed-width integer, returns `None` if the value does not fit.
+///
+/// This is generic so the conversion is checked regardless of the size of `umem`.
+fn fit_int<T: TryFrom<U>, U>(val: U) -> Option<T> {
+    T::try_from(val).ok()
+}
+
+/// Returns the start addresses of all pages overlapping `base..base + size`.
+fn page_range(base: Address, size: umem, page_size: usize) -> Result<Vec<Address>> {
+    if size == 0 {
+        return Ok(vec![]);
+    }
+
+    // the range may end exactly at the top of the address space, so the last byte is used instead of the end
+    let last = base.to_umem().checked_add(size - 1).ok_or_else(|| {
+        Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds)
+            .log_info("page range exceeds the address space")
+    })?;
+    let start = base.as_page_aligned(page_size).to_umem();
+
+    let count = (last - start) / page_size as umem + 1;
+    if count > MAX_PAGE_MAP_PAGES as umem {
+        return Err(Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds)
+            .log_info(format!("page range spans {} pages", count)));
+    }
+
+    Ok((0..count as usize)
+        .map(|i| Address::from(start + i as umem * page_size as umem))
+        .collect())
+}
+
+/// Rewrites the image base and the section headers of a PE image that has been read
+/// with its sections at their virtual addresses so it matches the layout of a PE file.
+fn unmap_pe_image(image: &mut [u8], image_base: Address) -> Result<()> {
+    let (optional_header, section_headers, is_pe64, sections) = {
+        let pe = PeView::from_bytes(&*image)
+            .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_info(err))?;
+        // IMAGE_NT_HEADERS::Signature + IMAGE_FILE_HEADER
+        let optional_header = pe.dos_header().e_lfanew as usize + 4 + 20;
+        let section_headers = optional_header + pe.file_header().SizeOfOptionalHeader as usize;
+        let sections = pe
+            .section_headers()
+            .iter()
+            .map(|section| (section.VirtualAddress, section.VirtualSize))
+            .collect::<Vec<_>>();
+        (
+            optional_header,
+            section_headers,
+            matches!(pe.optional_header(), pelite::Wrap::T64(_)),
+            sections,
+        )
+    };
+
+    // IMAGE_OPTIONAL_HEADER::ImageBase
+    if is_pe64 {
+        // the low 8 bytes of the little endian representation, regardless of the size of umem
+        image[optional_header + 24..optional_header + 32]
+            .copy_from_slice(&image_base.to_umem().to_le_bytes()[..8]);
+    } else {
+        image[optional_header + 28..optional_header + 32]
+            .copy_from_slice(&image_base.to_umem().to_le_bytes()[..4]);
+    }
+
+    for (i, (virtual_address, virtual_size)) in sections.into_iter().enumerate() {
+        let size_of_raw_data =
+            virtual_size.min((image.len() as u32).saturating_sub(virtual_address));
+
+        // IMAGE_SECTION_HEADER::SizeOfRawData and IMAGE_SECTION_HEADER::PointerToRawData
+        let header = section_headers + i * 40;
+        image[header + 16..header + 20].copy_from_slice(&size_of_raw_data.to_le_bytes());
+        image[header + 20..header + 24].copy_from_slice(&virtual_address.to_le_bytes());
+    }
+
+    Ok(())
+}
+
+/// Reads a null-terminated utf-16 string of at most `max_chars` characters with `read`,
+/// see `Win32Process::read_wstr`.
+fn read_wstr_chunked<F: FnMut(Address, &mut [u8]) -> Result<()>>(
+    addr: Address,
+    max_chars: usize,
+    mut read: F,
+) -> Result<String> {
+    let mut chars = Vec::new();
+    let mut buf = vec![0u8; WSTR_CHUNK_SIZE];
+    let mut cur = addr;
+
+    while chars.len() < max_chars {
+        let chunk_offset: usize = fit_int(cur.to_umem() % WSTR_CHUNK_SIZE as umem)
+            .ok_or(Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds))?;
+        let len = (WSTR_CHUNK_SIZE - chunk_offset)
+            .min((max_chars - chars.len()) * 2)
+            .max(2);
+        let buf = &mut buf[..len];
+
+        if let Err(err) = read(cur, buf) {
+            if chars.is_empty() {
+                return Err(err);
+            }
+            break;
+        }
+
+        for c in buf.chunks_exact(2) {
+            let c = u16::from_le_bytes([c[0], c[1]]);
+            if c == 0 || chars.len() >= max_chars {
+                return Ok(String::from_utf16_lossy(&chars));
+            }
+            chars.push(c);
         }
+
+        cur += len - len % 2;
     }
+
+    Ok(String::from_utf16_lossy(&chars))
 }
 
 impl<T, V, D> AsMut<VirtualDma<T, V, D>> for Win32Process<T, V, D> {
@@ -164,7 +720,7 @@ impl<T: PhysicalMemory, V: VirtualTranslate2, D: VirtualTranslate3> VirtualTrans
 impl<T: PhysicalMemory, V: VirtualTranslate2> Process
     for Win32Process<T, V, Win32VirtualTranslate>
 {
-    /// Retrieves virtual address translator for the process (if applicable)
+    // Retrieves virtual address translator for the process (if applicable)
     //fn vat(&mut self) -> Option<&mut Self::VirtualTranslateType>;
 
     /// Retrieves the state of the process
@@ -186,15 +742,20 @@ impl<T: PhysicalMemory, V: VirtualTranslate2> Process
     ///
     /// # Remarks
     ///
-    /// For memflow-win32 the second parameter should be set to `Address::invalid()`.
-    fn set_dtb(&mut self, dtb1: Address, _dtb2: Address) -> Result<()> {
+    /// The second parameter is the user mode dtb of processes with page table isolation
+    /// (or `Address::invalid()`). It is only stored, translations always use the kernel side dtb.
+    fn set_dtb(&mut self, dtb1: Address, dtb2: Address) -> Result<()> {
         self.proc_info.base_info.dtb1 = dtb1;
-        self.proc_info.base_info.dtb2 = Address::invalid();
+        self.proc_info.base_info.dtb2 = dtb2;
         self.virt_mem.set_translator(self.proc_info.translator());
         Ok(())
     }
 
     /// Walks the process' module list and calls the provided callback for each module
+    ///
+    /// Passing an architecture that does not match one of the process' module lists
+    /// yields no modules. Use [`Win32Process::modules_native`] or [`Win32Process::modules_wow64`]
+    /// to explicitly select a module list instead.
     fn module_address_list_callback(
         &mut self,
         target_arch: Option<&ArchitectureIdent>,
@@ -225,7 +786,6 @@ impl<T: PhysicalMemory, V: VirtualTranslate2> Process
             .filter_map(|(info, arch)| info.zip(Some(arch)));
 
         self.module_address_list_with_infos_callback(iter, &mut callback)
-            .map_err(From::from)
     }
 
     /// Retrieves a module by its structure address and architecture
@@ -253,7 +813,6 @@ impl<T: PhysicalMemory, V: VirtualTranslate2> Process
             &mut self.virt_mem,
             architecture,
         )
-        .map_err(From::from)
     }
 
     /// Retrieves address of the primary module structure of the process
@@ -321,105 +880,16 @@ impl<T: PhysicalMemory, V: VirtualTranslate2> Process
         gap_size: imem,
         start: Address,
         end: Address,
-        out: MemoryRangeCallback,
+        mut out: MemoryRangeCallback,
     ) {
-        fn _walk_vad(
-            mem: &mut impl MemoryView,
-            vad_entry: Address,
-            offsets: &MmVadOffsetTable,
-            arch: ArchitectureObj,
-            start: Address,
-            end: Address,
-            out: &mut MemoryRangeCallback,
-        ) {
-            if vad_entry.is_null() || start == end {
-                return;
+        if self.mapped_mem_vad {
+            // the regions are collected before the callback is invoked, so falling back is safe
+            let mut forward = |range| out.call(range);
+            match self.vad_region_list_callback(gap_size, start, end, (&mut forward).into()) {
+                Ok(()) => return,
+                Err(err) => log::trace!("falling back to the page tables: {}", err),
             }
-
-            log::trace!("WALK VAD {vad_entry} {start} {end}");
-
-            let _ = (move || {
-                // Older versions of windows store starting/ending VPNs as address ranges without
-                // the high parts, as opposed to frame numbers
-                let pfn_mul = if offsets.starting_vpn_high == offsets.ending_vpn_high {
-                    1
-                } else {
-                    0x1000
-                };
-
-                // TODO: handle starting/ending vpn high values
-
-                let s = mem.read::<u32>(vad_entry + offsets.starting_vpn)? as umem;
-                let s = Address::from(s * pfn_mul);
-                let e = mem.read::<u32>(vad_entry + offsets.ending_vpn)? as umem;
-                let e = Address::from(e * pfn_mul);
-
-                let sl = mem.read::<u8>(vad_entry + offsets.starting_vpn_high)? as umem;
-                let el = mem.read::<u8>(vad_entry + offsets.ending_vpn_high)? as umem;
-
-                let fl = mem.read::<u32>(vad_entry + offsets.u)?;
-
-                // Bits are as follows:
-                // RXW (maybe)
-                println!("FL {fl:b} | {}", offsets.protection_bit);
-
-                let _r = fl & (0b1 << offsets.protection_bit);
-
-                let fl = fl >> offsets.protection_bit;
-
-                println!("FL {fl:b}");
-
-                let fl = fl & !(!0u32 << 5);
-
-                println!("S {s} E {e} | {sl:x} {el:x} | {fl:b} {fl}");
-
-                if (s >= start && s < end) || (e <= end && e > start) {
-                    let left = mem.read_addr_arch(arch, vad_entry + offsets.vad_node)?;
-                    let right =
-                        mem.read_addr_arch(arch, vad_entry + offsets.vad_node + arch.size_addr())?;
-
-                    _walk_vad(mem, left, offsets, arch, start, s, out);
-
-                    if !out.call(CTup3(
-                        s,
-                        e.to_umem() - s.to_umem() + pfn_mul,
-                        Default::default(),
-                    )) {
-                        return Result::Ok(());
-                    }
-
-                    _walk_vad(mem, right, offsets, arch, e, end, out);
-                }
-
-                Result::Ok(())
-            })();
         }
-
-        /*let mut gap_remover = memflow::types::util::GapRemover::new(out, gap_size, start, end);
-
-        // Temporarily load up the sysproc dtb into the memory view
-        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
-
-        let out = &mut |data| {
-            gap_remover.push_range(data);
-            true
-        };
-
-        let mut out = out.into();
-
-        _walk_vad(
-            &mut self.virt_mem,
-            self.proc_info.vad_root,
-            &self.mmvad,
-            self.proc_info.base_info.sys_arch.into(),
-            start,
-            end,
-            &mut out,
-        );
-
-        // Load back the original value
-        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);*/
-
         self.virt_mem.virt_page_map_range(gap_size, start, end, out)
     }
 }
@@ -437,7 +907,15 @@ impl<T: PhysicalMemory, V: VirtualTranslate2> Win32Process<T, V, Win32VirtualTra
             proc_info,
             sysproc_dtb,
             mmvad: kernel.offsets.mm_vad(),
+            mem_counters: MemCountersOffsets::new(&kernel.offsets),
+            image_file: ImageFileOffsets::new(&kernel.offsets),
+            threads: ThreadOffsets::new(&kernel.offsets),
+            vad_file: VadFileOffsets::new(&kernel.offsets),
+            mapped_mem_vad: kernel.mapped_mem_vad,
             offset_eproc_exit_status: kernel.offsets.eproc_exit_status(),
+            offset_eproc_job: kernel.offsets.eproc_job(),
+            module_cache: None,
+            module_ranges: None,
         }
     }
 
@@ -445,6 +923,104 @@ impl<T: PhysicalMemory, V: VirtualTranslate2> Win32Process<T, V, Win32VirtualTra
     pub fn into_inner(self) -> (T, V) {
         self.virt_mem.into_inner()
     }
+
+    /// Returns all threads whose start address is not located inside of any module.
+    ///
+    /// Threads that start outside of a mapped image are a common indicator of injected code.
+    /// Threads without a start address are ignored.
+    pub fn suspicious_threads(&mut self) -> Result<Vec<Win32ThreadInfo>> {
+        let mut suspicious = vec![];
+        for thread in self.threads()? {
+            if !thread.win32_start_address.is_null()
+                && self
+                    .module_containing(thread.win32_start_address)?
+                    .is_none()
+            {
+                suspicious.push(thread);
+            }
+        }
+        Ok(suspicious)
+    }
+
+    /// Returns the module whose image contains the given address.
+    ///
+    /// The module list is read on the first call and kept sorted by base address,
+    /// so subsequent lookups (e.g. during a stack walk) do not enumerate the modules again.
+    /// Call `clear_module_cache` to pick up modules that were loaded afterwards.
+    pub fn module_containing(&mut self, addr: Address) -> Result<Option<ModuleInfo>> {
+        if self.module_ranges.is_none() {
+            let mut modules = self.module_list()?;
+            modules.sort_by_key(|module| module.base);
+            self.module_ranges = Some(modules);
+        }
+
+        Ok(self
+            .module_ranges
+            .as_deref()
+            .and_then(|modules| find_module_containing(modules, addr))
+            .cloned())
+    }
+
+    /// Scans the stack of a thread for potential return addresses.
+    ///
+    /// The stack bounds are read from the `_NT_TIB` of the given TEB and every pointer sized
+    /// value between StackLimit and StackBase that points into a module is returned,
+    /// starting with the innermost frames. This is a heuristic and will also report
+    /// stale values and function pointers that happen to be stored on the stack.
+    ///
+    /// If `teb` is the 32-bit TEB of a wow64 process the 32-bit stack is scanned.
+    pub fn walk_stack(&mut self, teb: Address, max_frames: usize) -> Result<Vec<Address>> {
+        let arch = if self.proc_info.teb_wow64 == Some(teb) {
+            self.proc_info.base_info.proc_arch
+        } else {
+            self.proc_info.base_info.sys_arch
+        };
+        let offsets = Win32ArchOffsets::from(arch);
+        let arch_obj = ArchitectureObj::from(arch);
+
+        let stack_base = self
+            .virt_mem
+            .read_addr_arch(arch_obj, teb + offsets.teb_stack_base)?;
+        let stack_limit = self
+            .virt_mem
+            .read_addr_arch(arch_obj, teb + offsets.teb_stack_limit)?;
+        if stack_limit.is_null() || stack_limit >= stack_base {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_info("invalid stack bounds in teb"));
+        }
+
+        let ptr_size = arch_obj.size_addr();
+        let mut frames = vec![];
+        let mut buf = vec![0u8; STACK_CHUNK_SIZE];
+        let mut cur = stack_limit;
+        while cur < stack_base && frames.len() < max_frames {
+            let len = ((stack_base - cur) as usize).min(STACK_CHUNK_SIZE);
+            let buf = &mut buf[..len];
+
+            // pages that are not mapped (anymore) are skipped
+            if self.virt_mem.read_raw_into(cur, buf).data_part().is_ok() {
+                for value in buf.chunks_exact(ptr_size) {
+                    let value = if ptr_size == 8 {
+                        u64::from_le_bytes(value.try_into().unwrap())
+                    } else {
+                        u32::from_le_bytes(value.try_into().unwrap()) as u64
+                    };
+
+                    let addr = Address::from(value);
+                    if self.module_containing(addr)?.is_some() {
+                        frames.push(addr);
+                        if frames.len() >= max_frames {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            cur += len;
+        }
+
+        Ok(frames)
+    }
 }
 
 impl<'a, T: PhysicalMemory, V: VirtualTranslate2>
@@ -475,27 +1051,1370 @@ impl<'a, T: PhysicalMemory, V: VirtualTranslate2>
             proc_info,
             sysproc_dtb,
             mmvad: kernel.offsets.mm_vad(),
+            mem_counters: MemCountersOffsets::new(&kernel.offsets),
+            image_file: ImageFileOffsets::new(&kernel.offsets),
+            threads: ThreadOffsets::new(&kernel.offsets),
+            vad_file: VadFileOffsets::new(&kernel.offsets),
+            mapped_mem_vad: kernel.mapped_mem_vad,
             offset_eproc_exit_status: kernel.offsets.eproc_exit_status(),
+            offset_eproc_job: kernel.offsets.eproc_job(),
+            module_cache: None,
+            module_ranges: None,
         }
     }
 }
 
 impl<T: PhysicalMemory, V: VirtualTranslate2, D: VirtualTranslate3> Win32Process<T, V, D> {
-    fn module_address_list_with_infos_callback(
+    /// Returns all modules of the native module list of the process.
+    ///
+    /// For wow64 processes this is the 64-bit module list (e.g. ntdll.dll and the wow64 layer).
+    pub fn modules_native(&mut self) -> Result<Vec<ModuleInfo>> {
+        let info = self.proc_info.module_info_native.ok_or_else(|| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("process has no native module list")
+        })?;
+        self.module_list_from_info(info, self.proc_info.base_info.sys_arch)
+    }
+
+    /// Returns all modules of the 32-bit module list of a wow64 process.
+    ///
+    /// Returns an error if the process is not a wow64 process.
+    pub fn modules_wow64(&mut self) -> Result<Vec<ModuleInfo>> {
+        let info = self.proc_info.module_info_wow64.ok_or_else(|| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("process has no wow64 module list")
+        })?;
+        self.module_list_from_info(info, self.proc_info.base_info.proc_arch)
+    }
+
+    /// Returns all modules of the process in the given `_PEB_LDR_DATA` order.
+    ///
+    /// For wow64 processes the native modules are followed by the 32-bit modules.
+    pub fn modules_in_order(&mut self, order: Win32ModuleListOrder) -> Result<Vec<ModuleInfo>> {
+        let pebs = [
+            (self.proc_info.peb_native, self.proc_info.base_info.sys_arch),
+            (self.proc_info.peb_wow64, self.proc_info.base_info.proc_arch),
+        ];
+
+        let mut modules = vec![];
+        for (peb, arch) in pebs.iter().filter_map(|(peb, arch)| peb.zip(Some(*arch))) {
+            let info = Win32ModuleListInfo::with_peb_order(&mut self.virt_mem, peb, arch, order)?;
+            modules.extend(self.module_list_from_info(info, arch)?);
+        }
+        Ok(modules)
+    }
+
+    fn module_list_from_info(
         &mut self,
-        module_infos: impl Iterator<Item = (Win32ModuleListInfo, ArchitectureIdent)>,
-        out: &mut ModuleAddressCallback,
-    ) -> Result<()> {
-        for (info, arch) in module_infos {
-            let callback = &mut |address| out.call(ModuleAddressInfo { address, arch });
-            info.module_entry_list_callback(self, arch, callback.into())?;
+        info: Win32ModuleListInfo,
+        arch: ArchitectureIdent,
+    ) -> Result<Vec<ModuleInfo>> {
+        let entries = info.module_entry_list::<VirtualDma<T, V, D>>(self, arch)?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| {
+                info.module_info_from_entry(
+                    entry,
+                    self.proc_info.base_info.address,
+                    &mut self.virt_mem,
+                    arch,
+                )
+                .map_err(|err| log::trace!("error when reading module {:x} {:?}", entry, err))
+                .ok()
+            })
+            .collect())
+    }
+
+    /// Returns all modules of the process including their timestamp and checksum.
+    ///
+    /// For wow64 processes the native modules are followed by the 32-bit modules.
+    pub fn win32_module_list(&mut self) -> Result<Vec<Win32Module>> {
+        let infos = [
+            (
+                self.proc_info.module_info_native,
+                self.proc_info.base_info.sys_arch,
+            ),
+            (
+                self.proc_info.module_info_wow64,
+                self.proc_info.base_info.proc_arch,
+            ),
+        ];
+
+        let mut modules = vec![];
+        for (info, arch) in infos
+            .iter()
+            .filter_map(|(info, arch)| info.zip(Some(*arch)))
+        {
+            let entries = info.module_entry_list::<VirtualDma<T, V, D>>(self, arch)?;
+            modules.extend(entries.into_iter().filter_map(|entry| {
+                info.module_from_entry(
+                    entry,
+                    self.proc_info.base_info.address,
+                    &mut self.virt_mem,
+                    arch,
+                )
+                .map_err(|err| log::trace!("error when reading module {:x} {:?}", entry, err))
+                .ok()
+            }));
         }
-        Ok(())
+        Ok(modules)
     }
-}
 
-impl<T, V, D> fmt::Debug for Win32Process<T, V, D> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self.proc_info)
+    /// Returns the modules of the process as minidump compatible records.
+    ///
+    /// The TimeDateStamp of each module is read from its PE header.
+    /// For wow64 processes the native modules are followed by the 32-bit modules.
+    /// Modules whose base or size cannot be represented in a minidump record are skipped.
+    pub fn export_module_list_minidump(&mut self) -> Result<Vec<MinidumpModule>> {
+        let mut modules = self.modules_native()?;
+        if self.proc_info.is_wow64() {
+            modules.extend(self.modules_wow64()?);
+        }
+
+        Ok(modules
+            .into_iter()
+            .filter_map(|module| {
+                let (Some(base_of_image), Some(size_of_image)) =
+                    (fit_int(module.base.to_umem()), fit_int(module.size))
+                else {
+                    log::debug!(
+                        "skipping module {} with base {:x} and size {:x}",
+                        module.name,
+                        module.base,
+                        module.size
+                    );
+                    return None;
+                };
+                Some(MinidumpModule {
+                    base_of_image,
+                    size_of_image,
+                    time_date_stamp: pehelper::try_get_pe_timestamp(
+                        &mut self.virt_mem,
+                        module.base,
+                    )
+                    .unwrap_or_default(),
+                    module_name: module.path.to_string(),
+                })
+            })
+            .collect())
+    }
+
+    /// Writes the memory region at `base` to `out`.
+    ///
+    /// See `dump_region_chunked` for details. The region is read in chunks of `DUMP_CHUNK_SIZE` bytes.
+    #[cfg(feature = "std")]
+    pub fn dump_region(
+        &mut self,
+        base: Address,
+        size: umem,
+        out: &mut dyn std::io::Write,
+    ) -> Result<usize> {
+        self.dump_region_chunked(base, size, DUMP_CHUNK_SIZE, out)
+    }
+
+    /// Writes the memory region at `base` to `out` reading `chunk_size` bytes at a time.
+    ///
+    /// If a chunk cannot be read entirely it is re-read page by page
+    /// and all pages that cannot be read are zero-filled in the output.
+    /// The output therefore always contains exactly `size` bytes.
+    ///
+    /// Returns the number of bytes that have actually been read from memory.
+    #[cfg(feature = "std")]
+    pub fn dump_region_chunked(
+        &mut self,
+        base: Address,
+        size: umem,
+        chunk_size: usize,
+        out: &mut dyn std::io::Write,
+    ) -> Result<usize> {
+        if chunk_size == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_error("chunk size must not be zero"));
+        }
+
+        let page_size = ArchitectureObj::from(self.proc_info.base_info.proc_arch).page_size();
+        let mut buf = vec![0u8; chunk_size];
+        let mut bytes_read = 0;
+
+        let mut offset: umem = 0;
+        while offset < size {
+            let len = (size - offset).min(chunk_size as umem) as usize;
+            let chunk_base = base + offset;
+            let chunk = &mut buf[..len];
+
+            if self.virt_mem.read_raw_into(chunk_base, chunk).is_ok() {
+                bytes_read += len;
+            } else {
+                // fall back to reading the chunk page by page
+                let mut pos = 0;
+                while pos < len {
+                    let addr = chunk_base + pos;
+                    let page_len =
+                        (page_size - (addr.to_umem() as usize % page_size)).min(len - pos);
+                    let page = &mut chunk[pos..pos + page_len];
+                    if self.virt_mem.read_raw_into(addr, page).is_ok() {
+                        bytes_read += page_len;
+                    } else {
+                        log::warn!(
+                            "unable to read page at {:x}, zero-filling {:x} bytes",
+                            addr,
+                            page_len
+                        );
+                        page.fill(0);
+                    }
+                    pos += page_len;
+                }
+            }
+
+            out.write_all(chunk).map_err(|err| {
+                Error(ErrorOrigin::OsLayer, ErrorKind::UnableToWriteFile).log_error(err)
+            })?;
+            offset += len as umem;
+        }
+
+        Ok(bytes_read)
+    }
+
+    /// Reads the `_UNICODE_STRING` at the given address.
+    ///
+    /// The string is read with the pointer width of the process architecture,
+    /// i.e. 32-bit strings are expected for wow64 processes.
+    pub fn read_unicode_string_at(&mut self, addr: Address) -> Result<String> {
+        self.virt_mem
+            .read_unicode_string(self.proc_info.base_info.proc_arch.into(), addr)
+    }
+
+    /// Reads the current command line of the process from its PEB.
+    ///
+    /// In contrast to `ProcessInfo::command_line` this only reads
+    /// `ProcessParameters::CommandLine` and always reflects the current value.
+    pub fn command_line(&mut self) -> Result<String> {
+        let arch = self.proc_info.base_info.proc_arch;
+        let offsets = Win32ArchOffsets::from(arch);
+        let process_parameters = self.process_parameters()?;
+        self.virt_mem
+            .read_unicode_string(arch.into(), process_parameters + offsets.ppm_command_line)
+    }
+
+    /// Reads the current working directory of the process from its PEB.
+    ///
+    /// This reads `ProcessParameters::CurrentDirectory.DosPath` which usually ends with a backslash.
+    pub fn current_directory(&mut self) -> Result<String> {
+        let arch = self.proc_info.base_info.proc_arch;
+        let offsets = Win32ArchOffsets::from(arch);
+        let process_parameters = self.process_parameters()?;
+        self.virt_mem.read_unicode_string(
+            arch.into(),
+            process_parameters + offsets.ppm_current_directory,
+        )
+    }
+
+    /// Reads the DLL search path of the process from its PEB.
+    ///
+    /// This reads `ProcessParameters::DllPath`, the search path the process was started with.
+    /// Newer versions of Windows commonly leave this string empty, in which case an error is returned.
+    pub fn dll_search_path(&mut self) -> Result<String> {
+        let arch = self.proc_info.base_info.proc_arch;
+        let offsets = Win32ArchOffsets::from(arch);
+        let process_parameters = self.process_parameters()?;
+        self.virt_mem
+            .read_unicode_string(arch.into(), process_parameters + offsets.ppm_dll_path)
+    }
+
+    /// Returns the environment block information of the process.
+    pub fn env_list_info(&mut self) -> Result<Win32EnvListInfo> {
+        let peb = self.peb()?;
+        Win32EnvListInfo::with_peb(&mut self.virt_mem, peb, self.proc_info.base_info.proc_arch)
+    }
+
+    /// Returns all environment variables of the process.
+    pub fn envar_list(&mut self) -> Result<Vec<EnvVarInfo>> {
+        self.env_list_info()?.envar_list(&mut self.virt_mem)
+    }
+
+    /// Retrieves the value of the environment variable with the given name.
+    ///
+    /// Returns `Ok(None)` if the variable is not set.
+    pub fn envar(&mut self, name: &str) -> Result<Option<String>> {
+        self.env_list_info()?.envar(&mut self.virt_mem, name)
+    }
+
+    /// Reads `_PEB::BeingDebugged` of the process.
+    ///
+    /// For wow64 processes the 32-bit PEB is used.
+    pub fn being_debugged(&mut self) -> Result<bool> {
+        let offsets = Win32ArchOffsets::from(self.proc_info.base_info.proc_arch);
+        let peb = self.peb()?;
+        Ok(self.virt_mem.read::<u8>(peb + offsets.peb_being_debugged)? != 0)
+    }
+
+    /// Reads `_PEB::NtGlobalFlag` of the process.
+    ///
+    /// For wow64 processes the 32-bit PEB is used.
+    pub fn nt_global_flag(&mut self) -> Result<u32> {
+        let offsets = Win32ArchOffsets::from(self.proc_info.base_info.proc_arch);
+        let peb = self.peb()?;
+        Ok(self
+            .virt_mem
+            .read::<u32>(peb + offsets.peb_nt_global_flag)?)
+    }
+
+    /// Reads a value at the given offset relative to the _EPROCESS of the process.
+    ///
+    /// The _EPROCESS lives in kernel memory so it is read with the system process dtb.
+    /// This allows reading fields that are not exposed by this crate yet.
+    pub fn read_eprocess<P: Pod>(&mut self, offset: umem) -> Result<P> {
+        self.read_kernel(self.proc_info.eprocess_base() + offset)
+    }
+
+    /// Reads a value from kernel memory using the system process dtb.
+    ///
+    /// The process translator is restored afterwards, so this can be used to access
+    /// kernel structures (e.g. the _ETHREADs of the process) from a process context.
+    pub fn read_kernel<P: Pod>(&mut self, addr: Address) -> Result<P> {
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+        let value = self.virt_mem.read::<P>(addr);
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+
+        Ok(value?)
+    }
+
+    /// Reads raw bytes from kernel memory using the system process dtb.
+    ///
+    /// See `read_kernel` for details.
+    pub fn read_kernel_raw_into(&mut self, addr: Address, out: &mut [u8]) -> Result<()> {
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+        let result = self.virt_mem.read_raw_into(addr, out);
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+
+        Ok(result?)
+    }
+
+    /// Reads a pointer at the given offset relative to the _EPROCESS of the process.
+    fn read_eprocess_addr(&mut self, offset: usize) -> Result<Address> {
+        let arch = self.proc_info.base_info.sys_arch;
+        let addr = self.proc_info.eprocess_base() + offset;
+
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+        let value = self.virt_mem.read_addr_arch(arch.into(), addr);
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+
+        Ok(value?)
+    }
+
+    /// Returns the address of the _EJOB the process is assigned to.
+    ///
+    /// Returns `None` if the process is not part of a job.
+    /// The members of the job can be retrieved with `Win32Kernel::job_processes`.
+    pub fn job(&mut self) -> Result<Option<Address>> {
+        if self.offset_eproc_job == 0 {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("_EPROCESS::Job offset is not available"));
+        }
+
+        Ok(self.read_eprocess_addr(self.offset_eproc_job)?.non_null())
+    }
+
+    /// Returns all threads of the process.
+    ///
+    /// The threads are read from the `ThreadListHead` of the _EPROCESS in kernel memory.
+    pub fn threads(&mut self) -> Result<Vec<Win32ThreadInfo>> {
+        if !self.threads.is_valid() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("_ETHREAD offsets are not available"));
+        }
+
+        // the thread list lives in kernel memory so it is read with the system process dtb
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+        let threads = self.threads_walk();
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+
+        threads
+    }
+
+    fn threads_walk(&mut self) -> Result<Vec<Win32ThreadInfo>> {
+        let arch = ArchitectureObj::from(self.proc_info.base_info.sys_arch);
+        let offsets = self.threads;
+        let list_head = self.proc_info.eprocess_base() + offsets.eproc_thread_list;
+
+        let mut threads = vec![];
+        let mut list_entry = self.virt_mem.read_addr_arch(arch, list_head)?;
+        for _ in 0..MAX_THREAD_ITER_COUNT {
+            if list_entry.is_null() || list_entry == list_head {
+                return Ok(threads);
+            }
+
+            let ethread = list_entry - offsets.ethread_list_entry;
+            // Cid is a _CLIENT_ID { UniqueProcess, UniqueThread }
+            let tid = self
+                .virt_mem
+                .read_addr_arch(arch, ethread + offsets.ethread_cid + arch.size_addr())?
+                .to_umem() as u32;
+            let teb = if offsets.kthread_teb != 0 {
+                self.virt_mem
+                    .read_addr_arch(arch, ethread + offsets.kthread_teb)?
+            } else {
+                Address::null()
+            };
+            let win32_start_address = self
+                .virt_mem
+                .read_addr_arch(arch, ethread + offsets.ethread_win32_start_address)?;
+
+            threads.push(Win32ThreadInfo {
+                ethread,
+                tid,
+                teb,
+                win32_start_address,
+            });
+
+            list_entry = self.virt_mem.read_addr_arch(arch, list_entry)?;
+        }
+
+        Err(Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds)
+            .log_warn("thread list exceeds the maximum number of entries"))
+    }
+
+    /// Returns the path of the image file backing the main executable of the process.
+    ///
+    /// The path is resolved by following `SectionObject`, `ControlArea` and `FilePointer`
+    /// to the `_FILE_OBJECT` of the image, e.g. `\Windows\System32\notepad.exe`.
+    /// Unlike the `ImagePathName` in the PEB this cannot be modified by the process itself.
+    pub fn image_file_name(&mut self) -> Result<String> {
+        if !self.image_file.is_valid() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("image file offsets are not available"));
+        }
+
+        // the section and file objects live in kernel memory so they are read with the system process dtb
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+        let file_name = self.image_file_name_walk();
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+
+        file_name
+    }
+
+    fn image_file_name_walk(&mut self) -> Result<String> {
+        let offsets = self.image_file;
+        let arch = ArchitectureObj::from(self.proc_info.base_info.sys_arch);
+
+        let section = self.virt_mem.read_addr_arch(
+            arch,
+            self.proc_info.eprocess_base() + offsets.eproc_section_object,
+        )?;
+        if section.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("process has no section object"));
+        }
+
+        let control_area = if offsets.section_control_area != 0 {
+            self.virt_mem
+                .read_addr_arch(arch, section + offsets.section_control_area)?
+        } else {
+            let segment = self
+                .virt_mem
+                .read_addr_arch(arch, section + offsets.section_object_segment)?;
+            self.virt_mem
+                .read_addr_arch(arch, segment + offsets.segment_control_area)?
+        };
+        if control_area.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("section has no control area"));
+        }
+
+        self.control_area_file_name(control_area)
+    }
+
+    /// Reads the name of the file referenced by a _CONTROL_AREA.
+    fn control_area_file_name(&mut self, control_area: Address) -> Result<String> {
+        let offsets = self.image_file;
+        let arch = ArchitectureObj::from(self.proc_info.base_info.sys_arch);
+
+        // _CONTROL_AREA::FilePointer is an _EX_FAST_REF with the reference count in the low bits
+        let ref_count_mask: umem = if arch.bits() == 64 { 0xf } else { 0x7 };
+        let file_pointer = self
+            .virt_mem
+            .read_addr_arch(arch, control_area + offsets.control_area_file_pointer)?;
+        let file_object = Address::from(file_pointer.to_umem() & !ref_count_mask);
+        if file_object.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("control area has no file object"));
+        }
+
+        self.virt_mem
+            .read_unicode_string(arch, file_object + offsets.file_object_file_name)
+    }
+
+    /// Reads the working set and private memory counters of the process.
+    ///
+    /// The counters are stored in pages in the _EPROCESS and converted to bytes.
+    pub fn memory_counters(&mut self) -> Result<Win32MemCounters> {
+        let offsets = self.mem_counters;
+        if !offsets.is_valid() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("memory counter offsets are not available"));
+        }
+
+        // the counters are stored as page counts, only the low 32 bits are read
+        // as the field size differs between windows versions
+        let page_size =
+            ArchitectureObj::from(self.proc_info.base_info.sys_arch).page_size() as umem;
+        let mut read_pages = |offset: usize| -> Result<umem> {
+            Ok(self.read_eprocess::<u32>(offset as umem)? as umem * page_size)
+        };
+
+        Ok(Win32MemCounters {
+            working_set_size: read_pages(offsets.working_set_size)?,
+            peak_working_set_size: read_pages(offsets.peak_working_set_size)?,
+            private_bytes: read_pages(offsets.commit_charge)?,
+            peak_private_bytes: read_pages(offsets.commit_charge_peak)?,
+        })
+    }
+
+    fn peb(&self) -> Result<Address> {
+        self.proc_info.peb().ok_or_else(|| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::NotFound).log_info("process has no peb")
+        })
+    }
+
+    /// Reads the address of the RTL_USER_PROCESS_PARAMETERS from the PEB.
+    fn process_parameters(&mut self) -> Result<Address> {
+        let arch = self.proc_info.base_info.proc_arch;
+        let offsets = Win32ArchOffsets::from(arch);
+        let peb = self.peb()?;
+        Ok(self
+            .virt_mem
+            .read_addr_arch(arch.into(), peb + offsets.peb_process_params)?)
+    }
+
+    /// Enables the module image cache for this process.
+    ///
+    /// When enabled, module images read through `read_module_image`, `read_module_region`,
+    /// `module_export` and `find_signature` are kept in memory for the lifetime of this object
+    /// (keyed by module base and size), so repeated scans of the same module only read it once.
+    pub fn with_module_cache(mut self) -> Self {
+        self.module_cache.get_or_insert_with(BTreeMap::new);
+        self
+    }
+
+    /// Removes all cached module images and the module list cached by `module_containing`.
+    pub fn clear_module_cache(&mut self) {
+        if let Some(cache) = self.module_cache.as_mut() {
+            cache.clear();
+        }
+        self.module_ranges = None;
+    }
+
+    /// Reads a null-terminated utf-16 string of at most `max_chars` characters.
+    ///
+    /// The string is read page by page and reading stops at the first null character,
+    /// so strings that end right before an unmapped page can still be read.
+    /// Invalid utf-16 sequences are replaced with the replacement character.
+    pub fn read_wstr(&mut self, addr: Address, max_chars: usize) -> Result<String> {
+        let virt_mem = &mut self.virt_mem;
+        read_wstr_chunked(addr, max_chars, |addr, buf| {
+            virt_mem.read_raw_into(addr, buf).data_part()
+        })
+    }
+
+    /// Reads the full image of the given module.
+    ///
+    /// If the module cache is enabled the image is only read once.
+    pub fn read_module_image(&mut self, module: &ModuleInfo) -> Result<Vec<u8>> {
+        let key = (module.base, module.size);
+        if let Some(image) = self.module_cache.as_ref().and_then(|cache| cache.get(&key)) {
+            return Ok(image.clone());
+        }
+
+        self.check_module_region(module, 0, module.size as usize)?;
+        let image = self
+            .virt_mem
+            .read_raw(module.base, module.size as usize)
+            .data_part()?;
+
+        if let Some(cache) = self.module_cache.as_mut() {
+            cache.insert(key, image.clone());
+        }
+        Ok(image)
+    }
+
+    /// Reads the image of the given module and converts it into a valid PE file.
+    ///
+    /// The sections of a loaded module are laid out at their virtual addresses.
+    /// This rewrites the raw offsets and sizes of every section to match
+    /// their virtual counterparts and sets the image base to the address the module
+    /// was loaded at, so the result can be loaded in a disassembler.
+    pub fn dump_module_pe(&mut self, module: &ModuleInfo) -> Result<Vec<u8>> {
+        let mut image = self.read_module_image(module)?;
+        unmap_pe_image(&mut image, module.base)?;
+        Ok(image)
+    }
+
+    /// Reads `len` bytes at `offset` relative to the base of the given module.
+    ///
+    /// Returns `ErrorKind::OutOfBounds` if the region exceeds the size of the module.
+    /// If the module cache is enabled the region is served from the cached module image.
+    pub fn read_module_region(
+        &mut self,
+        module: &ModuleInfo,
+        offset: umem,
+        len: usize,
+    ) -> Result<Vec<u8>> {
+        self.check_module_region(module, offset, len)?;
+
+        if self.module_cache.is_some() {
+            let image = self.read_module_image(module)?;
+            Ok(image[offset as usize..offset as usize + len].to_vec())
+        } else {
+            self.virt_mem
+                .read_raw(module.base + offset, len)
+                .data_part()
+        }
+    }
+
+    fn check_module_region(&self, module: &ModuleInfo, offset: umem, len: usize) -> Result<()> {
+        match offset.checked_add(len as umem) {
+            Some(end) if end <= module.size => Ok(()),
+            _ => Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds).log_info(format!(
+                    "region {:x}+{:x} is out of bounds of module {} (size {:x})",
+                    offset, len, module.name, module.size
+                )),
+            ),
+        }
+    }
+
+    /// Scans the image of the given module for an IDA-style signature.
+    ///
+    /// Returns the address of the first match or `None` if the signature could not be found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memflow::prelude::v1::*;
+    /// use memflow::mem::virt_translate::VirtualTranslate3;
+    /// use memflow_win32::win32::Win32Process;
+    ///
+    /// fn test<T: PhysicalMemory, V: VirtualTranslate2, D: VirtualTranslate3>(
+    ///     process: &mut Win32Process<T, V, D>,
+    ///     module: &ModuleInfo,
+    /// ) {
+    ///     if let Some(addr) = process.find_signature(module, "48 8B 05 ? ? ? ? 48 89 81").unwrap() {
+    ///         println!("signature found at {:x}", addr);
+    ///     }
+    /// }
+    /// ```
+    pub fn find_signature(
+        &mut self,
+        module: &ModuleInfo,
+        ida_pattern: &str,
+    ) -> Result<Option<Address>> {
+        let scanner = PlainScanner::new(ida_pattern)?;
+        let image = self.read_module_image(module)?;
+        Ok(scanner.find(&image).map(|offset| module.base + offset))
+    }
+
+    /// Searches all committed memory regions of the process for the given pattern.
+    ///
+    /// The regions are taken from the VAD tree of the process, regions without access
+    /// and guard pages are skipped. Only the pages of a region that are mapped in the page tables are searched.
+    /// Each mapped range is read separately in chunks, pages that cannot be read are skipped.
+    ///
+    /// If `mask` is set it must be as long as `pattern`, only the bits set in the mask are compared.
+    /// The search stops after `max_results` matches have been found.
+    pub fn search(
+        &mut self,
+        pattern: &[u8],
+        mask: Option<&[u8]>,
+        max_results: usize,
+    ) -> Result<Vec<Address>> {
+        if pattern.is_empty() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_error("search pattern must not be empty"));
+        }
+        if mask
+            .map(|mask| mask.len() != pattern.len())
+            .unwrap_or(false)
+        {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_error("search mask must have the same length as the pattern"));
+        }
+
+        let page_size = ArchitectureObj::from(self.proc_info.base_info.proc_arch).page_size();
+        let mut out = vec![];
+
+        // only the mapped pages of each region are searched, reserved but uncommitted
+        // regions (e.g. the CFG bitmap) can span terabytes of address space
+        let mut ranges = vec![];
+        for Win32VadEntry {
+            start,
+            size,
+            protection,
+            ..
+        } in self.vad_regions()?
+        {
+            if let Some(protection) = protection {
+                if protection & MM_PROTECTION_ACCESS_MASK == 0 || protection & MM_GUARD_PAGE != 0 {
+                    continue;
+                }
+            }
+
+            ranges.extend(
+                self.virt_mem
+                    .virt_page_map_range_vec(0, start, start + size)
+                    .into_iter()
+                    .map(|CTup3(start, size, _)| (start, size)),
+            );
+        }
+
+        for (start, size) in ranges {
+            let mut offset: umem = 0;
+            while offset < size && out.len() < max_results {
+                let chunk_base = start + offset;
+                let len = (size - offset).min(SEARCH_CHUNK_SIZE as umem) as usize;
+                // read a few more bytes to find matches crossing the chunk boundary
+                let read_len = (size - offset).min((len + pattern.len() - 1) as umem) as usize;
+
+                let mut buf = vec![0u8; read_len];
+                let runs = if self.virt_mem.read_raw_into(chunk_base, &mut buf).is_ok() {
+                    vec![(0, read_len)]
+                } else {
+                    // fall back to reading the chunk page by page and only search readable runs of pages
+                    let mut runs: Vec<(usize, usize)> = vec![];
+                    let mut pos = 0;
+                    while pos < read_len {
+                        let addr = chunk_base + pos;
+                        let page_len =
+                            (page_size - (addr.to_umem() as usize % page_size)).min(read_len - pos);
+                        if self
+                            .virt_mem
+                            .read_raw_into(addr, &mut buf[pos..pos + page_len])
+                            .is_ok()
+                        {
+                            match runs.last_mut() {
+                                Some((_, end)) if *end == pos => *end = pos + page_len,
+                                _ => runs.push((pos, pos + page_len)),
+                            }
+                        }
+                        pos += page_len;
+                    }
+                    runs
+                };
+
+                for (run_start, run_end) in runs {
+                    let run = &buf[run_start..run_end];
+                    if run.len() < pattern.len() {
+                        continue;
+                    }
+                    let matches = (0..=run.len() - pattern.len())
+                        .filter(|&i| run_start + i < len)
+                        .filter(|&i| masked_match(&run[i..i + pattern.len()], pattern, mask))
+                        .map(|i| chunk_base + run_start + i)
+                        .take(max_results - out.len())
+                        .collect::<Vec<_>>();
+                    out.extend(matches);
+                }
+
+                offset += len as umem;
+            }
+
+            if out.len() >= max_results {
+                break;
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Controls whether `Process::mapped_mem_range` reports the regions of the VAD tree
+    /// instead of walking the page tables.
+    ///
+    /// This defaults to the setting of the kernel, see `Win32Kernel::set_mapped_mem_vad`.
+    pub fn set_mapped_mem_vad(&mut self, enabled: bool) {
+        self.mapped_mem_vad = enabled;
+    }
+
+    /// Reports the memory regions of the VAD tree that intersect `start..end` to the callback.
+    ///
+    /// Unlike `mapped_mem_range`, which walks the page tables, this reports whole
+    /// VAD reservations, including memory that is reserved but not committed.
+    /// Regions closer than `gap_size` to each other are merged.
+    ///
+    /// `mapped_mem_range` reports these regions instead of walking the page tables
+    /// if enabled via `set_mapped_mem_vad`.
+    pub fn vad_region_list_callback(
+        &mut self,
+        gap_size: imem,
+        start: Address,
+        end: Address,
+        out: MemoryRangeCallback,
+    ) -> Result<()> {
+        let regions = self.vad_regions()?;
+
+        let mut gap_remover = GapRemover::new(out, gap_size, start, end);
+        for Win32VadEntry {
+            start: base,
+            size,
+            protection,
+            ..
+        } in regions.into_iter()
+        {
+            let region_start = std::cmp::max(base, start);
+            let region_end = std::cmp::min(base + size, end);
+            if region_start < region_end {
+                gap_remover.push_range(CTup3(
+                    region_start,
+                    (region_end - region_start) as umem,
+                    mm_protection_page_type(protection),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns all memory regions in the VAD tree together with the names of their mapped files.
+    ///
+    /// The file name of image and mapped file regions is resolved by following
+    /// `Subsection`, `ControlArea` and `FilePointer` to the `_FILE_OBJECT`.
+    /// Private regions and regions whose file cannot be resolved have no file name.
+    /// Returns `ErrorKind::Offset` if the offsets required to resolve the file names are not available.
+    pub fn vad_entries(&mut self) -> Result<Vec<Win32VadEntry>> {
+        if !self.vad_file.is_valid() || !self.image_file.is_valid() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::Offset)
+                .log_info("vad file offsets are not available"));
+        }
+        let mut entries = self.vad_regions()?;
+
+        // the vad tree lives in kernel memory so it is read with the system process dtb
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+        for entry in entries.iter_mut() {
+            entry.file_name = self.vad_file_name(entry.vad).ok();
+        }
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+
+        Ok(entries)
+    }
+
+    fn vad_file_name(&mut self, vad: Address) -> Result<String> {
+        let offsets = self.vad_file;
+        let arch = ArchitectureObj::from(self.proc_info.base_info.sys_arch);
+
+        // private regions are described by an _MMVAD_SHORT which has no subsection
+        if offsets.mmvad_private_memory_bit != 0 && self.mmvad.u != 0 {
+            let flags = self.virt_mem.read::<u64>(vad + self.mmvad.u)?;
+            if (flags >> offsets.mmvad_private_memory_bit) & 1 != 0 {
+                return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                    .log_trace("vad describes private memory"));
+            }
+        }
+
+        let subsection = self
+            .virt_mem
+            .read_addr_arch(arch, vad + offsets.mmvad_subsection)?;
+        if subsection.is_null() {
+            return Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::NotFound).log_trace("vad has no subsection")
+            );
+        }
+
+        let control_area = self
+            .virt_mem
+            .read_addr_arch(arch, subsection + offsets.subsection_control_area)?;
+        if control_area.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_trace("subsection has no control area"));
+        }
+
+        self.control_area_file_name(control_area)
+    }
+
+    /// Returns all memory regions in the VAD tree sorted by their start address.
+    ///
+    /// The file names of the regions are not resolved.
+    fn vad_regions(&mut self) -> Result<Vec<Win32VadEntry>> {
+        if self.proc_info.vad_root.is_null() {
+            return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                .log_info("process has no vad root"));
+        }
+
+        // the vad tree lives in kernel memory so it is read with the system process dtb
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+        let regions = self.vad_regions_walk();
+        self.sysproc_dtb = self.virt_mem.set_translator(self.sysproc_dtb);
+
+        regions
+    }
+
+    fn vad_regions_walk(&mut self) -> Result<Vec<Win32VadEntry>> {
+        let offsets = self.mmvad;
+        let arch = ArchitectureObj::from(self.proc_info.base_info.sys_arch);
+
+        // Older versions of windows store starting/ending VPNs as address ranges without
+        // the high parts, as opposed to frame numbers
+        let pfn_mul: umem = if offsets.starting_vpn_high == offsets.ending_vpn_high {
+            1
+        } else {
+            0x1000
+        };
+
+        let mut regions = vec![];
+        let mut stack = vec![self.proc_info.vad_root];
+        while let Some(vad_entry) = stack.pop() {
+            if regions.len() >= MAX_VAD_COUNT {
+                break;
+            }
+
+            let mut start = self
+                .virt_mem
+                .read::<u32>(vad_entry + offsets.starting_vpn)? as umem;
+            let mut end = self.virt_mem.read::<u32>(vad_entry + offsets.ending_vpn)? as umem;
+            if pfn_mul != 1 {
+                start |= (self
+                    .virt_mem
+                    .read::<u8>(vad_entry + offsets.starting_vpn_high)?
+                    as umem)
+                    << 32;
+                end |= (self
+                    .virt_mem
+                    .read::<u8>(vad_entry + offsets.ending_vpn_high)?
+                    as umem)
+                    << 32;
+            }
+
+            let protection = if offsets.u != 0 && offsets.protection_bit != 0 {
+                let flags = self.virt_mem.read::<u32>(vad_entry + offsets.u)?;
+                Some((flags >> offsets.protection_bit) & 0x1f)
+            } else {
+                None
+            };
+
+            if end >= start {
+                regions.push(Win32VadEntry {
+                    vad: vad_entry,
+                    start: Address::from(start * pfn_mul),
+                    size: (end - start) * pfn_mul + pfn_mul,
+                    protection,
+                    file_name: None,
+                });
+            }
+
+            let left = self
+                .virt_mem
+                .read_addr_arch(arch, vad_entry + offsets.vad_node)?;
+            let right = self
+                .virt_mem
+                .read_addr_arch(arch, vad_entry + offsets.vad_node + arch.size_addr())?;
+            stack.extend([right, left].into_iter().filter(|node| !node.is_null()));
+        }
+
+        regions.sort_by_key(|region| region.start);
+        Ok(regions)
+    }
+
+    /// Returns the readability of every page in the range `base..base + size`.
+    ///
+    /// Each page is only translated and not read, so this can be used to compute
+    /// the coverage of a region before dumping it.
+    /// The first page starts at `base` aligned down to the page size of the process.
+    ///
+    /// Returns `ErrorKind::OutOfBounds` if the range exceeds the address space
+    /// or spans more than 0x100000 pages.
+    pub fn page_map(&mut self, base: Address, size: umem) -> Result<Vec<(Address, bool)>> {
+        let page_size = ArchitectureObj::from(self.proc_info.base_info.proc_arch).page_size();
+        let pages = page_range(base, size, page_size)?;
+
+        let translated = self.virt_to_phys_batch(&pages);
+        Ok(pages
+            .into_iter()
+            .zip(translated)
+            .map(|(page, phys)| (page, phys.is_some()))
+            .collect())
+    }
+
+    /// Translates a list of virtual addresses into physical addresses.
+    ///
+    /// All addresses are translated in a single batch using the process translator
+    /// and the translation cache of this process (if any).
+    /// The returned list has the same order as `addrs`, addresses that could not be translated are `None`.
+    pub fn virt_to_phys_batch(&mut self, addrs: &[Address]) -> Vec<Option<PhysicalAddress>> {
+        let ranges = addrs
+            .iter()
+            .map(|&addr| CTup2(addr, 1))
+            .collect::<Vec<VtopRange>>();
+
+        let mut translated = vec![];
+        self.virt_mem.virt_to_phys_list(
+            &ranges,
+            (&mut |t: VirtualTranslation| {
+                translated.push((t.in_virtual, t.out_physical));
+                true
+            })
+                .into(),
+            (&mut |_| true).into(),
+        );
+        translated.sort_unstable_by_key(|(addr, _)| *addr);
+
+        addrs
+            .iter()
+            .map(|addr| {
+                translated
+                    .binary_search_by_key(addr, |(addr, _)| *addr)
+                    .ok()
+                    .map(|i| translated[i].1)
+            })
+            .collect()
+    }
+
+    /// Retrieves the address of the export with the given name in the given module.
+    ///
+    /// The module image is read through `read_module_image` so the module cache is used if enabled.
+    pub fn module_export(&mut self, module: &ModuleInfo, name: &str) -> Result<Address> {
+        let image = self.read_module_image(module)?;
+        let pe = PeView::from_bytes(&image)
+            .map_err(|err| Error(ErrorOrigin::OsLayer, ErrorKind::InvalidExeFile).log_info(err))?;
+        match pe.get_export_by_name(name).map_err(|_| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound)
+                .log_info(format!("unable to find export {} in {}", name, module.name))
+        })? {
+            Export::Symbol(s) => Ok(module.base + *s as umem),
+            Export::Forward(_) => Err(Error(ErrorOrigin::OsLayer, ErrorKind::ExportNotFound)
+                .log_info(format!("{} found but it was a forwarded export", name))),
+        }
+    }
+
+    /// Retrieves the named exports of the given module without reading the entire module image.
+    ///
+    /// Only the pe headers and the region of the export directory are read from the target.
+    /// This requires the export tables and names to be placed inside of the export directory
+    /// which is the case for images produced by the microsoft linker.
+    /// Forwarded exports are skipped.
+    pub fn module_exports_light(&mut self, module: &ModuleInfo) -> Result<Vec<ExportInfo>> {
+        let headers_size = std::cmp::min(size::kb(4) as umem, module.size) as usize;
+        self.check_module_region(module, 0, headers_size)?;
+        let headers = self
+            .virt_mem
+            .read_raw(module.base, headers_size)
+            .data_part()?;
+        let (dir_rva, dir_size) = pe_export_directory(&headers)?;
+
+        self.check_module_region(module, dir_rva as umem, dir_size as usize)?;
+        let dir = self
+            .virt_mem
+            .read_raw(module.base + dir_rva as umem, dir_size as usize)
+            .data_part()?;
+        parse_export_directory(&dir, dir_rva)
+    }
+
+    /// Retrieves the address of the import with the given name in the given module.
+    ///
+    /// The import table is walked until the first import with a matching name is found.
+    /// The returned address points to the import address table entry of the import.
+    pub fn module_import(&mut self, module: &ModuleInfo, name: &str) -> Result<Address> {
+        let mut offset = None;
+        let callback = &mut |import: ImportInfo| {
+            if import.name.as_ref() == name {
+                offset = Some(import.offset);
+                false
+            } else {
+                true
+            }
+        };
+        memflow::os::util::module_import_list_callback(
+            &mut self.virt_mem,
+            module,
+            callback.into(),
+        )?;
+        offset.map(|offset| module.base + offset).ok_or_else(|| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::ImportNotFound)
+                .log_info(format!("unable to find import {} in {}", name, module.name))
+        })
+    }
+
+    fn module_address_list_with_infos_callback(
+        &mut self,
+        module_infos: impl Iterator<Item = (Win32ModuleListInfo, ArchitectureIdent)>,
+        out: &mut ModuleAddressCallback,
+    ) -> Result<()> {
+        for (info, arch) in module_infos {
+            let callback = &mut |address| out.call(ModuleAddressInfo { address, arch });
+            info.module_entry_list_callback(self, arch, callback.into())?;
+        }
+        Ok(())
+    }
+}
+
+impl<T, V, D> fmt::Debug for Win32Process<T, V, D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.proc_info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(base: umem, size: umem) -> ModuleInfo {
+        ModuleInfo {
+            address: Address::NULL,
+            parent_process: Address::NULL,
+            base: Address::from(base),
+            size,
+            name: "test.dll".into(),
+            path: "test.dll".into(),
+            arch: ArchitectureIdent::X86(64, false),
+        }
+    }
+
+    /// Reads from `data` mapped at `base`, everything outside of it is unmapped
+    fn wstr_reader(base: umem, data: &[u8]) -> impl FnMut(Address, &mut [u8]) -> Result<()> + '_ {
+        move |addr, buf| {
+            let start = (addr.to_umem() - base) as usize;
+            match data.get(start..start + buf.len()) {
+                Some(src) => {
+                    buf.copy_from_slice(src);
+                    Ok(())
+                }
+                None => Err(Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds)),
+            }
+        }
+    }
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn read_wstr_terminated() {
+        let base = 0x1000;
+        let mut data = utf16("C:\\Windows\0garbage");
+        data.resize(WSTR_CHUNK_SIZE, 0);
+
+        let s = read_wstr_chunked(Address::from(base), 260, wstr_reader(base, &data)).unwrap();
+        assert_eq!(s, "C:\\Windows");
+    }
+
+    #[test]
+    fn read_wstr_max_chars() {
+        let base = 0x1000;
+        let mut data = utf16("C:\\Windows");
+        data.resize(WSTR_CHUNK_SIZE, 0);
+
+        let s = read_wstr_chunked(Address::from(base), 2, wstr_reader(base, &data)).unwrap();
+        assert_eq!(s, "C:");
+        let s = read_wstr_chunked(Address::from(base), 0, wstr_reader(base, &data)).unwrap();
+        assert_eq!(s, "");
+    }
+
+    #[test]
+    fn read_wstr_unterminated() {
+        // the string runs into an unmapped chunk without a null terminator
+        let base = 0x1000;
+        let data = utf16("ab").repeat(WSTR_CHUNK_SIZE / 4);
+        let start = Address::from(base + WSTR_CHUNK_SIZE as umem - 8);
+
+        let s = read_wstr_chunked(start, 260, wstr_reader(base, &data)).unwrap();
+        assert_eq!(s, "abab");
+
+        // nothing could be read at all
+        let end = Address::from(base + WSTR_CHUNK_SIZE as umem);
+        assert!(read_wstr_chunked(end, 260, wstr_reader(base, &data)).is_err());
+    }
+
+    #[test]
+    fn process_info_display() {
+        let info = Win32ProcessInfo {
+            base_info: ProcessInfo {
+                address: Address::from(0xffff_8000_1234_0000u64),
+                pid: 1234,
+                state: ProcessState::Alive,
+                name: "notepad.exe".into(),
+                path: "".into(),
+                command_line: "".into(),
+                sys_arch: ArchitectureIdent::X86(64, false),
+                proc_arch: ArchitectureIdent::X86(64, false),
+                dtb1: Address::from(0x1ad000u64),
+                dtb2: Address::invalid(),
+            },
+            section_base: Address::NULL,
+            ethread: Address::NULL,
+            wow64: Address::NULL,
+            teb: None,
+            teb_wow64: None,
+            peb_native: Some(Address::from(0x7ff6_f000u64)),
+            peb_wow64: None,
+            module_info_native: None,
+            module_info_wow64: None,
+            vad_root: Address::NULL,
+            frozen: false,
+        };
+
+        assert_eq!(
+            info.to_string(),
+            "pid=1234 name=notepad.exe arch=X86(64, false) wow64=false dtb=1ad000 peb=7ff6f000 modules=none"
+        );
+    }
+
+    #[test]
+    fn export_directory() {
+        let mut headers = vec![0u8; 0x200];
+        headers[0..2].copy_from_slice(b"MZ");
+        headers[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        headers[0x40..0x44].copy_from_slice(b"PE\0\0");
+        headers[0x58..0x5a].copy_from_slice(&0x20bu16.to_le_bytes());
+        headers[0xc4..0xc8].copy_from_slice(&16u32.to_le_bytes());
+        headers[0xc8..0xcc].copy_from_slice(&0x1000u32.to_le_bytes());
+        headers[0xcc..0xd0].copy_from_slice(&0x68u32.to_le_bytes());
+        assert_eq!(pe_export_directory(&headers).unwrap(), (0x1000, 0x68));
+
+        let mut dir = vec![0u8; 0x68];
+        let mut put_u32 =
+            |offs: usize, val: u32| dir[offs..offs + 4].copy_from_slice(&val.to_le_bytes());
+        put_u32(0x14, 2); // NumberOfFunctions
+        put_u32(0x18, 2); // NumberOfNames
+        put_u32(0x1c, 0x1028); // AddressOfFunctions
+        put_u32(0x20, 0x1030); // AddressOfNames
+        put_u32(0x24, 0x1038); // AddressOfNameOrdinals
+        put_u32(0x28, 0x2000);
+        put_u32(0x2c, 0x1060); // forwarded
+        put_u32(0x30, 0x1050);
+        put_u32(0x34, 0x1058);
+        dir[0x3a..0x3c].copy_from_slice(&1u16.to_le_bytes());
+        dir[0x50..0x54].copy_from_slice(b"foo\0");
+        dir[0x58..0x5c].copy_from_slice(b"bar\0");
+        dir[0x60..0x64].copy_from_slice(b"X.y\0");
+
+        let exports = parse_export_directory(&dir, 0x1000).unwrap();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].name.as_ref(), "foo");
+        assert_eq!(exports[0].offset, 0x2000);
+
+        // the name table is not contained in the directory
+        assert!(parse_export_directory(&dir[..0x40], 0x1000).is_err());
+    }
+
+    /// Builds a loaded image with a `.text` and a `.data` section, the latter exceeding the image
+    fn loaded_pe_image(pe64: bool) -> Vec<u8> {
+        let mut image = vec![0u8; 0x3000];
+        let put_u16 = |image: &mut [u8], offs: usize, val: u16| {
+            image[offs..offs + 2].copy_from_slice(&val.to_le_bytes())
+        };
+        let put_u32 = |image: &mut [u8], offs: usize, val: u32| {
+            image[offs..offs + 4].copy_from_slice(&val.to_le_bytes())
+        };
+        let (magic, size_of_optional_header, rva_count) = if pe64 {
+            (0x20b, 0xf0, 0x58 + 108)
+        } else {
+            (0x10b, 0xe0, 0x58 + 92)
+        };
+
+        image[0..2].copy_from_slice(b"MZ");
+        put_u32(&mut image, 0x3c, 0x40);
+        image[0x40..0x44].copy_from_slice(b"PE\0\0");
+        put_u16(&mut image, 0x44, if pe64 { 0x8664 } else { 0x14c });
+        put_u16(&mut image, 0x46, 2); // NumberOfSections
+        put_u16(&mut image, 0x54, size_of_optional_header);
+        put_u16(&mut image, 0x58, magic);
+        put_u32(&mut image, 0x58 + 32, 0x1000); // SectionAlignment
+        put_u32(&mut image, 0x58 + 36, 0x200); // FileAlignment
+        put_u32(&mut image, 0x58 + 56, 0x3000); // SizeOfImage
+        put_u32(&mut image, 0x58 + 60, 0x400); // SizeOfHeaders
+        put_u32(&mut image, rva_count, 16);
+
+        let sections = 0x58 + size_of_optional_header as usize;
+        image[sections..sections + 5].copy_from_slice(b".text");
+        put_u32(&mut image, sections + 8, 0x800);
+        put_u32(&mut image, sections + 12, 0x1000);
+        put_u32(&mut image, sections + 16, 0x200);
+        put_u32(&mut image, sections + 20, 0x400);
+        image[sections + 40..sections + 45].copy_from_slice(b".data");
+        put_u32(&mut image, sections + 48, 0x1800);
+        put_u32(&mut image, sections + 52, 0x2000);
+        put_u32(&mut image, sections + 56, 0x600);
+        put_u32(&mut image, sections + 60, 0x600);
+        image
+    }
+
+    #[test]
+    fn unmap_pe() {
+        let read_u64 = |image: &[u8], offs: usize| {
+            u64::from_le_bytes(image[offs..offs + 8].try_into().unwrap())
+        };
+        let read_u32 = |image: &[u8], offs: usize| {
+            u32::from_le_bytes(image[offs..offs + 4].try_into().unwrap())
+        };
+
+        let mut image = loaded_pe_image(true);
+        unmap_pe_image(&mut image, Address::from(0x7ff8_1234_0000u64)).unwrap();
+        assert_eq!(read_u64(&image, 0x58 + 24), 0x7ff8_1234_0000);
+        let sections = 0x58 + 0xf0;
+        assert_eq!(read_u32(&image, sections + 16), 0x800);
+        assert_eq!(read_u32(&image, sections + 20), 0x1000);
+        // the raw size is clamped to the end of the image
+        assert_eq!(read_u32(&image, sections + 56), 0x1000);
+        assert_eq!(read_u32(&image, sections + 60), 0x2000);
+
+        let mut image = loaded_pe_image(false);
+        unmap_pe_image(&mut image, Address::from(0x7712_0000u64)).unwrap();
+        assert_eq!(read_u32(&image, 0x58 + 28), 0x7712_0000);
+        // BaseOfData is left untouched
+        assert_eq!(read_u32(&image, 0x58 + 24), 0);
+        let sections = 0x58 + 0xe0;
+        assert_eq!(read_u32(&image, sections + 16), 0x800);
+        assert_eq!(read_u32(&image, sections + 20), 0x1000);
+
+        assert!(unmap_pe_image(&mut [0u8; 0x40], Address::NULL).is_err());
+    }
+
+    #[test]
+    fn minidump_field_conversion() {
+        assert_eq!(fit_int::<u32, umem>(0x1000), Some(0x1000));
+        assert_eq!(fit_int::<u32, umem>(0x1_0000_0000), None);
+        assert_eq!(
+            fit_int::<u64, umem>(0xfffff800_12340000),
+            Some(0xfffff800_12340000)
+        );
+    }
+
+    #[test]
+    fn page_range_bounds() {
+        let pages = page_range(Address::from(0x1234u64), 0x2000, 0x1000).unwrap();
+        assert_eq!(
+            pages,
+            vec![
+                Address::from(0x1000u64),
+                Address::from(0x2000u64),
+                Address::from(0x3000u64)
+            ]
+        );
+        assert!(page_range(Address::from(0x1000u64), 0, 0x1000)
+            .unwrap()
+            .is_empty());
+
+        // the last page of the address space
+        let top = Address::from(umem::MAX - 0xfff);
+        assert_eq!(page_range(top, 0x1000, 0x1000).unwrap(), vec![top]);
+
+        assert!(page_range(top, 0x1001, 0x1000).is_err());
+        assert!(page_range(Address::NULL, 0x1000 * 0x100001, 0x1000).is_err());
+    }
+
+    #[test]
+    fn module_containing_address() {
+        let modules = [module(0x1000, 0x1000), module(0x4000, 0x2000)];
+
+        assert!(find_module_containing(&modules, Address::from(0xfffu64)).is_none());
+        assert_eq!(
+            find_module_containing(&modules, Address::from(0x1000u64))
+                .unwrap()
+                .base,
+            Address::from(0x1000u64)
+        );
+        assert!(find_module_containing(&modules, Address::from(0x2000u64)).is_none());
+        assert_eq!(
+            find_module_containing(&modules, Address::from(0x5fffu64))
+                .unwrap()
+                .base,
+            Address::from(0x4000u64)
+        );
+        assert!(find_module_containing(&modules, Address::from(0x6000u64)).is_none());
     }
 }
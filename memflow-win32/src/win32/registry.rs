@@ -0,0 +1,344 @@
+use std::prelude::v1::*;
+
+use std::convert::TryInto;
+
+use memflow::error::{Error, ErrorKind, ErrorOrigin, Result};
+use memflow::types::Address;
+
+/// Registry value types
+pub const REG_NONE: u32 = 0;
+pub const REG_SZ: u32 = 1;
+pub const REG_EXPAND_SZ: u32 = 2;
+pub const REG_BINARY: u32 = 3;
+pub const REG_DWORD: u32 = 4;
+pub const REG_DWORD_BIG_ENDIAN: u32 = 5;
+pub const REG_LINK: u32 = 6;
+pub const REG_MULTI_SZ: u32 = 7;
+pub const REG_QWORD: u32 = 11;
+
+/// _CM_KEY_NODE::Flags, the key name is stored as an ascii string
+const KEY_COMP_NAME: u16 = 0x20;
+/// _CM_KEY_VALUE::Flags, the value name is stored as an ascii string
+const VALUE_COMP_NAME: u16 = 0x1;
+
+/// Data of a _CM_KEY_VALUE is stored in the DataLength field if this bit is set
+pub(crate) const CM_KEY_VALUE_SPECIAL_SIZE: u32 = 0x8000_0000;
+/// Maximum size of a single value data cell, larger values are stored in a big data (db) record
+pub(crate) const CM_KEY_VALUE_BIG: u32 = 0x3fd8;
+
+/// Registry hive loaded by the configuration manager
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32Hive {
+    /// Address of the _CMHIVE
+    pub address: Address,
+    /// _CMHIVE::FileFullPath (e.g. `\Device\HarddiskVolume3\Windows\System32\config\SYSTEM`)
+    pub file_path: String,
+    /// _CMHIVE::HiveRootPath (e.g. `\REGISTRY\MACHINE\SYSTEM`)
+    pub root_path: String,
+    /// Cell index of the root key of the hive
+    pub root_cell: u32,
+}
+
+/// Registry key read from a hive
+///
+/// The values and the names of the subkeys are read when the key is opened.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32RegKey {
+    /// Name of the key
+    pub name: String,
+    /// Cell index of the _CM_KEY_NODE
+    pub cell: u32,
+    /// _CM_KEY_NODE::LastWriteTime as a windows FILETIME
+    pub last_write_time: u64,
+    pub(crate) values: Vec<(String, RegValue)>,
+    pub(crate) subkeys: Vec<String>,
+}
+
+impl Win32RegKey {
+    /// Returns the names and data of all values of this key
+    ///
+    /// The default value of a key has an empty name.
+    pub fn values(&self) -> &[(String, RegValue)] {
+        &self.values
+    }
+
+    /// Returns the value with the given name (case-insensitive)
+    pub fn value(&self, name: &str) -> Option<&RegValue> {
+        self.values
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Returns the names of all subkeys of this key
+    pub fn subkeys(&self) -> &[String] {
+        &self.subkeys
+    }
+}
+
+/// Data of a registry value
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub enum RegValue {
+    None,
+    String(String),
+    ExpandString(String),
+    Binary(Vec<u8>),
+    Dword(u32),
+    DwordBigEndian(u32),
+    Link(String),
+    MultiString(Vec<String>),
+    Qword(u64),
+    /// Value of an unknown type or with data that does not match its type
+    Raw {
+        value_type: u32,
+        data: Vec<u8>,
+    },
+}
+
+impl RegValue {
+    /// Decodes the raw data of a registry value of the given type
+    pub fn from_raw(value_type: u32, data: &[u8]) -> Self {
+        match (value_type, data.len()) {
+            (REG_NONE, 0) => RegValue::None,
+            (REG_SZ, _) => RegValue::String(utf16_string(data)),
+            (REG_EXPAND_SZ, _) => RegValue::ExpandString(utf16_string(data)),
+            (REG_BINARY, _) => RegValue::Binary(data.to_vec()),
+            (REG_DWORD, 4) => RegValue::Dword(u32::from_le_bytes(data.try_into().unwrap())),
+            (REG_DWORD_BIG_ENDIAN, 4) => {
+                RegValue::DwordBigEndian(u32::from_be_bytes(data.try_into().unwrap()))
+            }
+            (REG_LINK, _) => RegValue::Link(utf16_string(data)),
+            (REG_MULTI_SZ, _) => RegValue::MultiString(
+                utf16_string_raw(data)
+                    .split('\0')
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect(),
+            ),
+            (REG_QWORD, 8) => RegValue::Qword(u64::from_le_bytes(data.try_into().unwrap())),
+            _ => RegValue::Raw {
+                value_type,
+                data: data.to_vec(),
+            },
+        }
+    }
+}
+
+/// Decodes a utf-16 string up to the first null terminator
+fn utf16_string(data: &[u8]) -> String {
+    let s = utf16_string_raw(data);
+    match s.find('\0') {
+        Some(end) => s[..end].to_string(),
+        None => s,
+    }
+}
+
+fn utf16_string_raw(data: &[u8]) -> String {
+    let wide = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect::<Vec<_>>();
+    String::from_utf16_lossy(&wide)
+}
+
+/// Decodes the name of a key or value which is either stored as ascii or as utf-16
+fn cell_name(data: &[u8], compressed: bool) -> String {
+    if compressed {
+        data.iter().map(|&c| c as char).collect()
+    } else {
+        utf16_string_raw(data)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds).log_info("registry cell too small")
+        })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds).log_info("registry cell too small")
+        })
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    data.get(offset..offset + len).ok_or_else(|| {
+        Error(ErrorOrigin::OsLayer, ErrorKind::OutOfBounds).log_info("registry cell too small")
+    })
+}
+
+fn check_signature(data: &[u8], signature: &[u8; 2]) -> Result<()> {
+    if data.get(..2) == Some(&signature[..]) {
+        Ok(())
+    } else {
+        Err(
+            Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument).log_info(format!(
+                "registry cell is not a {} record",
+                String::from_utf8_lossy(signature)
+            )),
+        )
+    }
+}
+
+/// _CM_KEY_NODE (nk) record
+#[derive(Debug, Clone)]
+pub(crate) struct KeyNode {
+    pub name: String,
+    pub last_write_time: u64,
+    /// Subkey lists in the stable and volatile storage
+    pub subkey_lists: [(u32, u32); 2],
+    pub value_count: u32,
+    pub value_list: u32,
+}
+
+impl KeyNode {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        check_signature(data, b"nk")?;
+        let flags = read_u16(data, 0x2)?;
+        let last_write_time =
+            u64::from(read_u32(data, 0x4)?) | (u64::from(read_u32(data, 0x8)?) << 32);
+        let name_length = read_u16(data, 0x48)? as usize;
+        let name = cell_name(
+            read_bytes(data, 0x4c, name_length)?,
+            flags & KEY_COMP_NAME != 0,
+        );
+
+        Ok(Self {
+            name,
+            last_write_time,
+            subkey_lists: [
+                (read_u32(data, 0x14)?, read_u32(data, 0x1c)?),
+                (read_u32(data, 0x18)?, read_u32(data, 0x20)?),
+            ],
+            value_count: read_u32(data, 0x24)?,
+            value_list: read_u32(data, 0x28)?,
+        })
+    }
+}
+
+/// _CM_KEY_VALUE (vk) record
+#[derive(Debug, Clone)]
+pub(crate) struct KeyValue {
+    pub name: String,
+    pub data_length: u32,
+    pub data: u32,
+    pub value_type: u32,
+}
+
+impl KeyValue {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        check_signature(data, b"vk")?;
+        let name_length = read_u16(data, 0x2)? as usize;
+        let flags = read_u16(data, 0x10)?;
+        let name = cell_name(
+            read_bytes(data, 0x14, name_length)?,
+            flags & VALUE_COMP_NAME != 0,
+        );
+
+        Ok(Self {
+            name,
+            data_length: read_u32(data, 0x4)?,
+            data: read_u32(data, 0x8)?,
+            value_type: read_u32(data, 0xc)?,
+        })
+    }
+}
+
+/// Subkey index record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SubkeyList {
+    /// Cell indices of key nodes (lf, lh and li records)
+    Keys(Vec<u32>),
+    /// Cell indices of further subkey lists (ri records)
+    Lists(Vec<u32>),
+}
+
+impl SubkeyList {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let count = read_u16(data, 0x2)? as usize;
+        let cells = |stride| {
+            (0..count)
+                .map(|i| read_u32(data, 0x4 + i * stride))
+                .collect::<Result<Vec<_>>>()
+        };
+
+        match data.get(..2) {
+            Some(b"lf") | Some(b"lh") => Ok(SubkeyList::Keys(cells(8)?)),
+            Some(b"li") => Ok(SubkeyList::Keys(cells(4)?)),
+            Some(b"ri") => Ok(SubkeyList::Lists(cells(4)?)),
+            _ => Err(Error(ErrorOrigin::OsLayer, ErrorKind::InvalidArgument)
+                .log_info("registry cell is not a subkey list")),
+        }
+    }
+}
+
+/// Parses a big data (db) record and returns the cell index of its segment list and the number of segments
+pub(crate) fn parse_big_data(data: &[u8]) -> Result<(u32, usize)> {
+    check_signature(data, b"db")?;
+    Ok((read_u32(data, 0x4)?, read_u16(data, 0x2)? as usize))
+}
+
+/// Parses a list of cell indices (e.g. a value list or a big data segment list)
+pub(crate) fn parse_cell_list(data: &[u8], count: usize) -> Result<Vec<u32>> {
+    (0..count).map(|i| read_u32(data, i * 4)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn reg_value_from_raw() {
+        assert_eq!(
+            RegValue::from_raw(REG_SZ, &utf16("foo\0")),
+            RegValue::String("foo".to_string())
+        );
+        assert_eq!(
+            RegValue::from_raw(REG_MULTI_SZ, &utf16("a\0bc\0\0")),
+            RegValue::MultiString(vec!["a".to_string(), "bc".to_string()])
+        );
+        assert_eq!(
+            RegValue::from_raw(REG_DWORD, &[1, 0, 0, 0]),
+            RegValue::Dword(1)
+        );
+        assert_eq!(
+            RegValue::from_raw(REG_DWORD_BIG_ENDIAN, &[0, 0, 0, 1]),
+            RegValue::DwordBigEndian(1)
+        );
+        assert_eq!(
+            RegValue::from_raw(REG_DWORD, &[1, 0]),
+            RegValue::Raw {
+                value_type: REG_DWORD,
+                data: vec![1, 0]
+            }
+        );
+    }
+
+    #[test]
+    fn subkey_list_parse() {
+        let lf = [
+            b'l', b'f', 2, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(
+            SubkeyList::parse(&lf).unwrap(),
+            SubkeyList::Keys(vec![1, 2])
+        );
+
+        let ri = [b'r', b'i', 1, 0, 3, 0, 0, 0];
+        assert_eq!(SubkeyList::parse(&ri).unwrap(), SubkeyList::Lists(vec![3]));
+
+        assert!(SubkeyList::parse(b"nk\x00\x00").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+/*!
+Signature scanners for IDA-style patterns.
+
+All scanners take an IDA-style pattern (see [`Signature`]) and report the offsets of its matches in a buffer.
+The [`PlainScanner`] is always available, the [`RegexScanner`] requires the `regex` feature.
+
+# Examples:
+
+```
+use memflow_win32::win32::scan::{PlainScanner, Scanner};
+
+let scanner = PlainScanner::new("48 8B 05 ? ? ? ? 48 89 81").unwrap();
+let buf = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, 0x48, 0x89, 0x81];
+assert_eq!(scanner.find(&buf), Some(1));
+```
+
+Instructions referencing data via a rip relative displacement can be resolved with [`scan_rip_relative`]:
+
+```
+use memflow_win32::win32::scan::{scan_rip_relative, PlainScanner};
+
+// mov rax, [rip+0x10]
+let scanner = PlainScanner::new("48 8B 05 ? ? ? ? 48 89 81").unwrap();
+let buf = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, 0x48, 0x89, 0x81];
+assert_eq!(scan_rip_relative(&scanner, &buf, 3), Some(0x18));
+```
+*/
+use std::prelude::v1::*;
+
+use super::sigscan::Signature;
+
+use memflow::error::Result;
+use memflow::types::umem;
+
+/// Scans a buffer for a fixed pattern
+pub trait Scanner {
+    /// Returns the length of the pattern in bytes.
+    fn pattern_len(&self) -> usize;
+
+    /// Returns the offset of the first match of the pattern in `buf`.
+    fn find(&self, buf: &[u8]) -> Option<usize>;
+
+    /// Returns the offsets of all matches of the pattern in `buf`.
+    ///
+    /// Matches may overlap.
+    fn find_all(&self, buf: &[u8]) -> Vec<usize> {
+        let mut out = vec![];
+        let mut pos = 0;
+        while let Some(offs) = buf.get(pos..).and_then(|buf| self.find(buf)) {
+            out.push(pos + offs);
+            pos += offs + 1;
+        }
+        out
+    }
+}
+
+/// Resolves the target of a 32-bit rip relative displacement in `buf`.
+///
+/// `match_offset` is the offset of the signature match in `buf` and `disp_offset` the offset
+/// of the displacement inside the match. The displacement has to be the last operand of the
+/// instruction so that rip points directly behind it.
+/// The returned target is relative to the start of `buf`.
+///
+/// # Panics
+///
+/// Panics if the displacement is not fully contained in `buf`.
+pub fn resolve_rip_relative(buf: &[u8], match_offset: usize, disp_offset: usize) -> umem {
+    let disp_offs = match_offset + disp_offset;
+    let disp = i32::from_le_bytes(buf[disp_offs..disp_offs + 4].try_into().unwrap());
+    (disp_offs as i64 + 4 + disp as i64) as umem
+}
+
+/// Finds the first match of `scanner` in `buf` and resolves the rip relative displacement
+/// at `disp_offset` inside the match, see [`resolve_rip_relative`].
+///
+/// Returns `None` if the pattern is not found or the displacement exceeds `buf`.
+pub fn scan_rip_relative<S: Scanner + ?Sized>(
+    scanner: &S,
+    buf: &[u8],
+    disp_offset: usize,
+) -> Option<umem> {
+    let match_offset = scanner.find(buf)?;
+    if match_offset + disp_offset + 4 > buf.len() {
+        return None;
+    }
+    Some(resolve_rip_relative(buf, match_offset, disp_offset))
+}
+
+/// Scanner that compares the pattern byte by byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlainScanner(Signature);
+
+impl PlainScanner {
+    /// Creates a new scanner from an IDA-style pattern.
+    pub fn new(ida_pattern: &str) -> Result<Self> {
+        Signature::parse(ida_pattern).map(Self)
+    }
+
+    /// Returns the parsed signature of this scanner.
+    pub fn signature(&self) -> &Signature {
+        &self.0
+    }
+}
+
+impl From<Signature> for PlainScanner {
+    fn from(signature: Signature) -> Self {
+        Self(signature)
+    }
+}
+
+impl Scanner for PlainScanner {
+    fn pattern_len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn find(&self, buf: &[u8]) -> Option<usize> {
+        self.0.find(buf)
+    }
+}
+
+/// Scanner that compiles the pattern into a byte regex
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct RegexScanner {
+    regex: ::regex::bytes::Regex,
+    len: usize,
+}
+
+#[cfg(feature = "regex")]
+impl RegexScanner {
+    /// Creates a new scanner from an IDA-style pattern.
+    pub fn new(ida_pattern: &str) -> Result<Self> {
+        use memflow::error::{Error, ErrorKind, ErrorOrigin};
+
+        let signature = Signature::parse(ida_pattern)?;
+        let mut pattern = "(?s-u)".to_string();
+        for byte in signature.bytes() {
+            match byte {
+                Some(byte) => pattern.push_str(&format!("\\x{:02X}", byte)),
+                None => pattern.push('.'),
+            }
+        }
+
+        let regex = ::regex::bytes::Regex::new(&pattern).map_err(|err| {
+            Error(ErrorOrigin::OsLayer, ErrorKind::Encoding)
+                .log_info(format!("unable to compile signature: {}", err))
+        })?;
+        Ok(Self {
+            regex,
+            len: signature.len(),
+        })
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Scanner for RegexScanner {
+    fn pattern_len(&self) -> usize {
+        self.len
+    }
+
+    fn find(&self, buf: &[u8]) -> Option<usize> {
+        self.regex.find(buf).map(|m| m.start())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUF: [u8; 12] = [
+        0x90, 0x4C, 0x8D, 0x15, 0x01, 0x02, 0x03, 0x04, 0x4C, 0x8D, 0x15, 0x4C,
+    ];
+
+    #[test]
+    fn plain_scanner() {
+        let scanner = PlainScanner::new("4C 8D 15 ?").unwrap();
+        assert_eq!(scanner.pattern_len(), 4);
+        assert_eq!(scanner.find(&BUF), Some(1));
+        assert_eq!(scanner.find_all(&BUF), vec![1, 8]);
+        assert_eq!(scanner.find(&BUF[..4]), None);
+    }
+
+    #[test]
+    fn rip_relative() {
+        // lea r10, [rip-0x10]
+        let buf = [
+            0x90, 0x4C, 0x8D, 0x15, 0xF0, 0xFF, 0xFF, 0xFF, 0x4C, 0x8D, 0x1D,
+        ];
+        assert_eq!(
+            resolve_rip_relative(&buf, 1, 3),
+            (0x8 - 0x10) as i64 as umem
+        );
+
+        let scanner = PlainScanner::new("4C 8D 15 ? ? ? ? 4C 8D 1D").unwrap();
+        let mut buf = buf.to_vec();
+        buf[4..8].copy_from_slice(&0x20i32.to_le_bytes());
+        assert_eq!(scan_rip_relative(&scanner, &buf, 3), Some(0x28));
+
+        // displacement outside of the buffer
+        let scanner = PlainScanner::new("4C 8D 15").unwrap();
+        assert_eq!(scan_rip_relative(&scanner, &buf[..6], 3), None);
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn regex_scanner() {
+        let scanner = RegexScanner::new("4C 8D 15 ?").unwrap();
+        assert_eq!(scanner.pattern_len(), 4);
+        assert_eq!(scanner.find(&BUF), Some(1));
+        assert_eq!(scanner.find_all(&BUF), vec![1, 8]);
+
+        // wildcards have to match newlines and non-utf8 bytes as well
+        let scanner = RegexScanner::new("? 4C").unwrap();
+        assert_eq!(scanner.find(&[0x0A, 0x4C]), Some(0));
+        assert_eq!(scanner.find(&[0xFF, 0x4C]), Some(0));
+    }
+}
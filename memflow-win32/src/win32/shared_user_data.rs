@@ -0,0 +1,215 @@
+use std::convert::TryInto;
+use std::prelude::v1::*;
+
+use log::trace;
+
+use memflow::architecture::ArchitectureIdent;
+use memflow::error::{Error, ErrorKind, ErrorOrigin, Result};
+use memflow::mem::MemoryView;
+use memflow::types::Address;
+
+/// Address of _KUSER_SHARED_DATA in the kernel address space of 64-bit systems
+const KUSER_SHARED_DATA_64: u64 = 0xFFFFF78000000000;
+
+/// Address of _KUSER_SHARED_DATA in the kernel address space of 32-bit systems
+const KUSER_SHARED_DATA_32: u64 = 0xFFDF0000;
+
+const KUSER_INTERRUPT_TIME: usize = 0x8;
+const KUSER_SYSTEM_TIME: usize = 0x14;
+const KUSER_NT_BUILD_NUMBER: usize = 0x260;
+const KUSER_NT_MAJOR_VERSION: usize = 0x26c;
+const KUSER_NT_MINOR_VERSION: usize = 0x270;
+const KUSER_SAFE_BOOT_MODE: usize = 0x2ec;
+const KUSER_QPC_BYPASS_ENABLED: usize = 0x3c6;
+
+/// Number of bytes of _KUSER_SHARED_DATA that are required to parse all fields
+const KUSER_SHARED_DATA_SIZE: usize = 0x3c8;
+
+/// QueryPerformanceCounter reads the reference time page provided by the hypervisor
+const SHARED_GLOBAL_FLAGS_QPC_BYPASS_USE_HV_PAGE: u8 = 0x02;
+
+/// Seconds between January 1, 1601 and the UNIX epoch (January 1, 1970)
+const SECONDS_1601_TO_1970: u64 = 11_644_473_600;
+
+/// Number of 100ns intervals per second
+const INTERVALS_PER_SECOND: u64 = 10_000_000;
+
+/// Number of attempts to read a consistent _KSYSTEM_TIME while the target is running
+const KSYSTEM_TIME_RETRIES: usize = 8;
+
+/// Returns the address of `_KUSER_SHARED_DATA` in the kernel address space of the given architecture.
+///
+/// The user mode mapping at 0x7ffe0000 is not guaranteed to be mapped by the system process.
+pub(crate) fn kuser_shared_data_address(arch: ArchitectureIdent) -> Address {
+    match arch {
+        ArchitectureIdent::X86(32, _) => Address::from(KUSER_SHARED_DATA_32),
+        _ => Address::from(KUSER_SHARED_DATA_64),
+    }
+}
+
+/// Fields read from `_KUSER_SHARED_DATA`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Win32SharedUserData {
+    /// _KUSER_SHARED_DATA::NtBuildNumber
+    ///
+    /// This field only exists since Windows 10 and is `None` on older versions.
+    pub nt_build_number: Option<u32>,
+    /// _KUSER_SHARED_DATA::NtMajorVersion
+    pub nt_major_version: u32,
+    /// _KUSER_SHARED_DATA::NtMinorVersion
+    pub nt_minor_version: u32,
+    /// _KUSER_SHARED_DATA::SystemTime in 100ns intervals since January 1, 1601 (UTC)
+    pub system_time: u64,
+    /// _KUSER_SHARED_DATA::InterruptTime in 100ns intervals since boot
+    pub interrupt_time: u64,
+    /// _KUSER_SHARED_DATA::SafeBootMode
+    pub safe_boot_mode: bool,
+    /// _KUSER_SHARED_DATA::QpcBypassEnabled
+    ///
+    /// This field is only parsed on Windows 10 and later and is `None` on older versions.
+    pub qpc_bypass_enabled: Option<u8>,
+}
+
+impl Win32SharedUserData {
+    /// Reads and parses `_KUSER_SHARED_DATA` from the kernel address space of a system with the given architecture.
+    ///
+    /// The read is repeated a few times in case the time fields were
+    /// torn by a concurrent update on a running target.
+    pub fn read<T: MemoryView>(mem: &mut T, arch: ArchitectureIdent) -> Result<Self> {
+        let address = kuser_shared_data_address(arch);
+        let mut buf = vec![0u8; KUSER_SHARED_DATA_SIZE];
+        for _ in 0..KSYSTEM_TIME_RETRIES {
+            mem.read_raw_into(address, &mut buf).map_err(|err| {
+                Error(ErrorOrigin::OsLayer, ErrorKind::UnableToReadMemory)
+                    .log_info(format!("unable to read KUSER_SHARED_DATA: {}", err))
+            })?;
+
+            if let Some(shared_user_data) = Self::from_bytes(&buf) {
+                if shared_user_data.nt_major_version == 0 {
+                    return Err(Error(ErrorOrigin::OsLayer, ErrorKind::NotFound)
+                        .log_info("KUSER_SHARED_DATA::NtMajorVersion is zero"));
+                }
+                return Ok(shared_user_data);
+            }
+
+            trace!("inconsistent KSYSTEM_TIME in KUSER_SHARED_DATA, retrying");
+        }
+
+        Err(Error(ErrorOrigin::OsLayer, ErrorKind::UnableToReadMemory)
+            .log_info("unable to read a consistent KSYSTEM_TIME from KUSER_SHARED_DATA"))
+    }
+
+    /// Returns `system_time` converted to seconds since the UNIX epoch.
+    ///
+    /// Times before the UNIX epoch are clamped to zero.
+    pub fn system_time_unix(&self) -> u64 {
+        (self.system_time / INTERVALS_PER_SECOND).saturating_sub(SECONDS_1601_TO_1970)
+    }
+
+    /// Returns true if QueryPerformanceCounter uses the reference time page of a hypervisor.
+    ///
+    /// Returns `None` if `qpc_bypass_enabled` is not available.
+    pub fn qpc_uses_hypervisor_page(&self) -> Option<bool> {
+        self.qpc_bypass_enabled
+            .map(|flags| flags & SHARED_GLOBAL_FLAGS_QPC_BYPASS_USE_HV_PAGE != 0)
+    }
+
+    /// Parses a raw `_KUSER_SHARED_DATA` buffer.
+    ///
+    /// Returns `None` if the buffer is too small or one of the time fields is torn.
+    pub(crate) fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() < KUSER_SHARED_DATA_SIZE {
+            return None;
+        }
+
+        let read_u32 = |offs: usize| u32::from_le_bytes(buf[offs..offs + 4].try_into().unwrap());
+
+        let nt_build_number = read_u32(KUSER_NT_BUILD_NUMBER);
+        Some(Self {
+            nt_build_number: if nt_build_number != 0 {
+                Some(nt_build_number)
+            } else {
+                None
+            },
+            nt_major_version: read_u32(KUSER_NT_MAJOR_VERSION),
+            nt_minor_version: read_u32(KUSER_NT_MINOR_VERSION),
+            system_time: ksystem_time(&buf[KUSER_SYSTEM_TIME..])?,
+            interrupt_time: ksystem_time(&buf[KUSER_INTERRUPT_TIME..])?,
+            safe_boot_mode: buf[KUSER_SAFE_BOOT_MODE] != 0,
+            // the qpc data was moved to this offset in windows 10 which also introduced NtBuildNumber
+            qpc_bypass_enabled: if nt_build_number != 0 {
+                Some(buf[KUSER_QPC_BYPASS_ENABLED])
+            } else {
+                None
+            },
+        })
+    }
+}
+
+/// Parses a _KSYSTEM_TIME (LowPart, High1Time, High2Time).
+///
+/// High1Time and High2Time only differ while the value is being updated.
+fn ksystem_time(buf: &[u8]) -> Option<u64> {
+    let low = u32::from_le_bytes(buf[0..4].try_into().unwrap());
+    let high1 = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+    let high2 = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+    if high1 == high2 {
+        Some(((high1 as u64) << 32) | low as u64)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_shared_user_data() {
+        let mut buf = vec![0u8; KUSER_SHARED_DATA_SIZE];
+        buf[KUSER_INTERRUPT_TIME..KUSER_INTERRUPT_TIME + 4].copy_from_slice(&5u32.to_le_bytes());
+        buf[KUSER_SYSTEM_TIME..KUSER_SYSTEM_TIME + 4].copy_from_slice(&1u32.to_le_bytes());
+        buf[KUSER_SYSTEM_TIME + 4..KUSER_SYSTEM_TIME + 8].copy_from_slice(&2u32.to_le_bytes());
+        buf[KUSER_SYSTEM_TIME + 8..KUSER_SYSTEM_TIME + 12].copy_from_slice(&2u32.to_le_bytes());
+        buf[KUSER_NT_MAJOR_VERSION..KUSER_NT_MAJOR_VERSION + 4]
+            .copy_from_slice(&10u32.to_le_bytes());
+        buf[KUSER_NT_BUILD_NUMBER..KUSER_NT_BUILD_NUMBER + 4]
+            .copy_from_slice(&19045u32.to_le_bytes());
+        buf[KUSER_SAFE_BOOT_MODE] = 1;
+        buf[KUSER_QPC_BYPASS_ENABLED] = 0x3;
+
+        let data = Win32SharedUserData::from_bytes(&buf).unwrap();
+        assert_eq!(data.nt_build_number, Some(19045));
+        assert_eq!(data.nt_major_version, 10);
+        assert_eq!(data.nt_minor_version, 0);
+        assert_eq!(data.system_time, (2 << 32) | 1);
+        assert_eq!(data.qpc_uses_hypervisor_page(), Some(true));
+        assert_eq!(data.interrupt_time, 5);
+        assert!(data.safe_boot_mode);
+
+        assert_eq!(data.system_time_unix(), 0);
+        let data = Win32SharedUserData {
+            // 2024-01-01 00:00:00 UTC
+            system_time: 133_485_408_000_000_000,
+            ..data
+        };
+        assert_eq!(data.system_time_unix(), 1_704_067_200);
+
+        // torn system time
+        buf[KUSER_SYSTEM_TIME + 8..KUSER_SYSTEM_TIME + 12].copy_from_slice(&3u32.to_le_bytes());
+        assert_eq!(Win32SharedUserData::from_bytes(&buf), None);
+    }
+
+    #[test]
+    fn shared_user_data_address() {
+        assert_eq!(
+            kuser_shared_data_address(ArchitectureIdent::X86(64, false)),
+            Address::from(0xFFFFF78000000000u64)
+        );
+        assert_eq!(
+            kuser_shared_data_address(ArchitectureIdent::X86(32, true)),
+            Address::from(0xFFDF0000u64)
+        );
+    }
+}
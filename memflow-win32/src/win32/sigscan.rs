@@ -0,0 +1,259 @@
+/*!
+IDA-style signature scanning.
+
+Patterns are written as space separated hex bytes where `?` or `??` matches any byte,
+e.g. `48 8B 05 ? ? ? ? 48 89 81`.
+Runs of wildcards can be written as `[?;n]`, e.g. `48 8B 05 [?;4] 48 89 81`,
+where `n` may not exceed [`MAX_WILDCARD_RUN`].
+
+Signatures that are known at compile time should be constructed with the [`ida_signature!`](crate::ida_signature)
+macro which rejects malformed patterns during compilation.
+*/
+use std::prelude::v1::*;
+
+use memflow::error::{Error, ErrorKind, ErrorOrigin, Result};
+
+/// The maximum number of wildcards a single `[?;n]` run may expand to.
+pub const MAX_WILDCARD_RUN: usize = 4096;
+
+/// Constructs a [`Signature`] from a string literal that is validated at compile time.
+///
+/// # Examples:
+///
+/// ```
+/// use memflow_win32::ida_signature;
+///
+/// let sig = ida_signature!("48 8B 05 [?;4] 48 89 81");
+/// assert_eq!(sig.len(), 10);
+/// ```
+///
+/// A malformed pattern fails to compile:
+///
+/// ```compile_fail
+/// use memflow_win32::ida_signature;
+///
+/// let sig = ida_signature!("48 8B 5 ?");
+/// ```
+#[macro_export]
+macro_rules! ida_signature {
+    ($pattern:literal) => {{
+        const _: () = assert!(
+            $crate::win32::sigscan::is_valid_pattern($pattern),
+            "malformed IDA signature"
+        );
+        $crate::win32::sigscan::Signature::parse($pattern).unwrap()
+    }};
+}
+
+/// Returns true if `ida_pattern` is a well-formed IDA-style pattern.
+///
+/// This can be evaluated at compile time, see [`ida_signature!`](crate::ida_signature).
+pub const fn is_valid_pattern(ida_pattern: &str) -> bool {
+    let buf = ida_pattern.as_bytes();
+    let mut tokens = 0;
+    let mut i = 0;
+    while i < buf.len() {
+        if buf[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < buf.len() && !buf[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if !is_valid_token(buf, start, i) {
+            return false;
+        }
+        tokens += 1;
+    }
+    tokens > 0
+}
+
+/// Validates a single token `buf[start..end]` of a pattern.
+const fn is_valid_token(buf: &[u8], start: usize, end: usize) -> bool {
+    match end - start {
+        1 => buf[start] == b'?',
+        2 => {
+            (buf[start] == b'?' && buf[start + 1] == b'?')
+                || (buf[start].is_ascii_hexdigit() && buf[start + 1].is_ascii_hexdigit())
+        }
+        _ => {
+            // [?;n] or [??;n]
+            if buf[start] != b'[' || buf[end - 1] != b']' {
+                return false;
+            }
+            let mut i = start + 1;
+            while i < end - 1 && buf[i] == b'?' {
+                i += 1;
+            }
+            let wildcards = i - start - 1;
+            if (wildcards != 1 && wildcards != 2) || buf[i] != b';' || i + 1 >= end - 1 {
+                return false;
+            }
+            i += 1;
+
+            let mut count = 0usize;
+            while i < end - 1 {
+                if !buf[i].is_ascii_digit() {
+                    return false;
+                }
+                count = count
+                    .saturating_mul(10)
+                    .saturating_add((buf[i] - b'0') as usize);
+                i += 1;
+            }
+            count > 0 && count <= MAX_WILDCARD_RUN
+        }
+    }
+}
+
+/// A parsed IDA-style signature. `None` entries match any byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature(Vec<Option<u8>>);
+
+impl Signature {
+    /// Parses an IDA-style pattern string.
+    pub fn parse(ida_pattern: &str) -> Result<Self> {
+        let mut bytes = vec![];
+        for token in ida_pattern.split_whitespace() {
+            match token {
+                "?" | "??" => bytes.push(None),
+                _ if token.len() == 2 => {
+                    bytes.push(Some(u8::from_str_radix(token, 16).map_err(|_| {
+                        Error(ErrorOrigin::OsLayer, ErrorKind::Encoding)
+                            .log_info(format!("invalid byte `{}` in signature", token))
+                    })?))
+                }
+                _ => {
+                    let count = Self::parse_wildcard_run(token).ok_or_else(|| {
+                        Error(ErrorOrigin::OsLayer, ErrorKind::Encoding)
+                            .log_info(format!("invalid token `{}` in signature", token))
+                    })?;
+                    bytes.extend(std::iter::repeat(None).take(count));
+                }
+            }
+        }
+
+        if bytes.is_empty() {
+            return Err(
+                Error(ErrorOrigin::OsLayer, ErrorKind::Encoding).log_info("signature is empty")
+            );
+        }
+
+        Ok(Self(bytes))
+    }
+
+    /// Parses a run of wildcards in the form `[?;n]` with `n` in `1..=MAX_WILDCARD_RUN`.
+    fn parse_wildcard_run(token: &str) -> Option<usize> {
+        let count = token
+            .strip_prefix("[?;")
+            .or_else(|| token.strip_prefix("[??;"))?
+            .strip_suffix(']')?;
+        // `usize::from_str` would also accept a leading `+`
+        if !count.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        count
+            .parse()
+            .ok()
+            .filter(|&count| count > 0 && count <= MAX_WILDCARD_RUN)
+    }
+
+    /// Returns the length of the signature in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns the bytes of the signature, `None` entries match any byte.
+    pub fn bytes(&self) -> &[Option<u8>] {
+        &self.0
+    }
+
+    /// Returns true if the signature does not contain any bytes.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true if the signature matches the beginning of `buf`.
+    pub fn matches(&self, buf: &[u8]) -> bool {
+        buf.len() >= self.0.len()
+            && self
+                .0
+                .iter()
+                .zip(buf)
+                .all(|(p, b)| p.map(|p| p == *b).unwrap_or(true))
+    }
+
+    /// Returns the offset of the first match of the signature in `buf`.
+    pub fn find(&self, buf: &[u8]) -> Option<usize> {
+        if buf.len() < self.0.len() {
+            return None;
+        }
+        (0..=buf.len() - self.0.len()).find(|&i| self.matches(&buf[i..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_find() {
+        let sig = Signature::parse("48 8B 05 ? ?? 00").unwrap();
+        assert_eq!(sig.len(), 6);
+
+        let buf = [0x90, 0x48, 0x8B, 0x05, 0x12, 0x34, 0x00, 0x90];
+        assert_eq!(sig.find(&buf), Some(1));
+        assert_eq!(sig.find(&buf[2..]), None);
+    }
+
+    #[test]
+    fn parse_wildcard_run() {
+        assert_eq!(
+            Signature::parse("48 8B 05 [?;4] 48").unwrap(),
+            Signature::parse("48 8B 05 ? ? ? ? 48").unwrap()
+        );
+        assert_eq!(Signature::parse("[??;2] 00").unwrap().len(), 3);
+        assert!(Signature::parse("48 [?;0]").is_err());
+        assert!(Signature::parse("48 [?;x]").is_err());
+        assert!(Signature::parse("48 [?;4").is_err());
+        assert_eq!(
+            Signature::parse("[?;4096]").unwrap().len(),
+            MAX_WILDCARD_RUN
+        );
+        assert!(Signature::parse("[?;4097]").is_err());
+        assert!(Signature::parse("[?;99999999999999999999]").is_err());
+    }
+
+    #[test]
+    fn validate_pattern() {
+        const VALID: &[&str] = &["48 8B 05 ? ?? 00", "  48\t[?;4] [??;12] ff  "];
+        const INVALID: &[&str] = &[
+            "", "   ", "48 8", "48 GG", "488B", "[?;0]", "[?;]", "[?4]", "[???;4]", "[?;4", "[;4]",
+            "[?;4097]", "[?;+4]",
+        ];
+
+        for pattern in VALID {
+            assert!(is_valid_pattern(pattern), "{}", pattern);
+            assert!(Signature::parse(pattern).is_ok(), "{}", pattern);
+        }
+        for pattern in INVALID {
+            assert!(!is_valid_pattern(pattern), "{}", pattern);
+            assert!(Signature::parse(pattern).is_err(), "{}", pattern);
+        }
+
+        assert_eq!(
+            crate::ida_signature!("48 [?;2]"),
+            Signature::parse("48 ? ?").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Signature::parse("").is_err());
+        assert!(Signature::parse("48 8").is_err());
+        assert!(Signature::parse("48 GG").is_err());
+        assert!(Signature::parse("488B").is_err());
+    }
+}